@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri_plugin_shell::ShellExt;
+
+use crate::commands::hardware::hardware_get_summary;
+use crate::commands::logs::get_log_location;
+use crate::commands::mcp_clients_install::{get_mcp_clients_status, McpClientStatus};
+use crate::diagnostics::{DiagnosticsBundle, DiagnosticsManifest};
+use crate::globals::SHINKAI_NODE_MANAGER_INSTANCE;
+use crate::hardware::HardwareSummary;
+use crate::local_shinkai_node::node_storage::storage_usage::StorageUsage;
+use crate::local_shinkai_node::process_handlers::ollama_process_handler::OllamaOptions;
+use crate::local_shinkai_node::process_handlers::process_utils::PortListener;
+use crate::local_shinkai_node::shinkai_node_manager::ShinkaiNodeManager;
+use crate::local_shinkai_node::shinkai_node_options::ShinkaiNodeOptions;
+use crate::log_query::{self, LogEntry, LogFilter, LogQuery, SIDECAR_PROCESSES};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Latest entries of each sidecar process included in the bundle
+const PROCESS_LOG_ENTRIES: usize = 2000;
+const STORAGE_USAGE_TOP: usize = 20;
+
+#[derive(Serialize, Clone, Debug)]
+struct ComponentVersion {
+    version: Option<String>,
+    error: Option<String>,
+}
+
+impl From<Result<String, String>> for ComponentVersion {
+    fn from(result: Result<String, String>) -> Self {
+        match result {
+            Ok(version) => ComponentVersion {
+                version: Some(version),
+                error: None,
+            },
+            Err(error) => ComponentVersion {
+                version: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct Versions {
+    app: String,
+    shinkai_node: ComponentVersion,
+    ollama: ComponentVersion,
+    deno: ComponentVersion,
+    uv: ComponentVersion,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct HealthResponse {
+    url: String,
+    status: u16,
+    body: Value,
+}
+
+/// First line printed by `binary --version`
+async fn binary_version(
+    app: &tauri::AppHandle,
+    binary_path: Option<String>,
+) -> Result<String, String> {
+    let binary_path = binary_path.ok_or_else(|| "binary path isn't set".to_string())?;
+    let output = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        app.shell()
+            .command(&binary_path)
+            .args(["--version"])
+            .output(),
+    )
+    .await
+    .map_err(|_| format!("{} --version timed out", binary_path))?
+    .map_err(|e| format!("failed to run {}: {}", binary_path, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} --version failed: {}",
+            binary_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
+
+async fn health(url: String) -> Result<HealthResponse, String> {
+    let response = reqwest::Client::new()
+        .get(&url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("failed to reach {}: {}", url, e))?;
+    let status = response.status().as_u16();
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("failed to read the response of {}: {}", url, e))?;
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    Ok(HealthResponse { url, status, body })
+}
+
+fn format_entries(entries: &[LogEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "[{}][{}][{}] {}\n",
+                entry.timestamp, entry.level, entry.target, entry.message
+            )
+        })
+        .collect()
+}
+
+/// Everything gathered from the app and its sidecars before the zip is written
+struct CollectedDiagnostics {
+    hardware: Result<HardwareSummary, String>,
+    node_options: ShinkaiNodeOptions,
+    ollama_options: OllamaOptions,
+    versions: Versions,
+    port_listeners: Vec<PortListener>,
+    node_health: Result<HealthResponse, String>,
+    ollama_health: Result<HealthResponse, String>,
+    storage_usage: Result<StorageUsage, String>,
+    mcp_clients: Vec<McpClientStatus>,
+}
+
+fn write_items(
+    bundle: &mut DiagnosticsBundle,
+    log_dir: &Path,
+    file_stem: &str,
+    collected: CollectedDiagnostics,
+) -> Result<(), String> {
+    bundle.add_text(
+        "logs/app.log",
+        "Current app log file",
+        fs::read_to_string(log_dir.join(format!("{}.log", file_stem))).map_err(|e| e.to_string()),
+    )?;
+    for process in SIDECAR_PROCESSES {
+        let query = LogQuery {
+            filter: LogFilter {
+                targets: Some(vec![process.to_string()]),
+                ..Default::default()
+            },
+            limit: Some(PROCESS_LOG_ENTRIES),
+            ..Default::default()
+        };
+        bundle.add_text(
+            &format!("logs/{}.log", process),
+            &format!("Latest {} output", process),
+            log_query::query_logs(log_dir, file_stem, &query)
+                .map(|page| format_entries(&page.entries)),
+        )?;
+    }
+    bundle.add_json("hardware.json", "Hardware summary", collected.hardware)?;
+    bundle.add_json(
+        "options/shinkai-node.json",
+        "Shinkai Node options, secrets redacted",
+        Ok(collected.node_options),
+    )?;
+    bundle.add_json(
+        "options/ollama.json",
+        "Ollama options, secrets redacted",
+        Ok(collected.ollama_options),
+    )?;
+    bundle.add_json(
+        "versions.json",
+        "App and sidecar versions",
+        Ok(collected.versions),
+    )?;
+    bundle.add_json(
+        "ports.json",
+        "Processes listening on the configured ports",
+        Ok(collected.port_listeners),
+    )?;
+    bundle.add_json(
+        "health/shinkai-node.json",
+        "Shinkai Node health check",
+        collected.node_health,
+    )?;
+    bundle.add_json(
+        "health/ollama.json",
+        "Ollama health check",
+        collected.ollama_health,
+    )?;
+    bundle.add_json(
+        "storage-usage.json",
+        "Node storage usage",
+        collected.storage_usage,
+    )?;
+    bundle.add_json(
+        "mcp-clients.json",
+        "MCP clients configuration status",
+        Ok(collected.mcp_clients),
+    )
+}
+
+/// Writes a zip with logs, settings and health of the app and its sidecars to `save_path`
+#[tauri::command]
+pub async fn export_diagnostics(
+    app_handle: tauri::AppHandle,
+    save_path: PathBuf,
+) -> Result<DiagnosticsManifest, String> {
+    let app_version = app_handle.package_info().version.to_string();
+    let (log_dir, file_stem) = get_log_location(&app_handle)?;
+
+    let hardware = hardware_get_summary(app_handle.clone()).await;
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    let node_options = shinkai_node_manager_guard.get_shinkai_node_options().await;
+    let ollama_options = shinkai_node_manager_guard.get_ollama_options();
+    let node_url = shinkai_node_manager_guard.get_shinkai_node_api_url();
+    let ollama_url = shinkai_node_manager_guard.get_ollama_api_url();
+    let node_version = shinkai_node_manager_guard.get_shinkai_node_version();
+    let port_listeners = shinkai_node_manager_guard.get_port_listeners();
+    let storage_usage = shinkai_node_manager_guard
+        .get_storage_usage(Some(STORAGE_USAGE_TOP))
+        .await;
+    drop(shinkai_node_manager_guard);
+    let versions = Versions {
+        app: app_version.clone(),
+        shinkai_node: node_version
+            .ok_or_else(|| "shinkai-node hasn't reported its version".to_string())
+            .into(),
+        ollama: ShinkaiNodeManager::get_ollama_version(app_handle.clone())
+            .await
+            .map_err(|e| e.to_string())
+            .into(),
+        deno: binary_version(
+            &app_handle,
+            node_options.shinkai_tools_runner_deno_binary_path.clone(),
+        )
+        .await
+        .into(),
+        uv: binary_version(
+            &app_handle,
+            node_options.shinkai_tools_runner_uv_binary_path.clone(),
+        )
+        .await
+        .into(),
+    };
+    let collected = CollectedDiagnostics {
+        hardware,
+        node_options,
+        ollama_options,
+        versions,
+        port_listeners,
+        node_health: health(format!("{}/v2/health_check", node_url)).await,
+        ollama_health: health(format!("{}/api/version", ollama_url)).await,
+        storage_usage,
+        mcp_clients: get_mcp_clients_status(),
+    };
+
+    let destination = save_path.clone();
+    let manifest = tauri::async_runtime::spawn_blocking(move || {
+        let mut bundle = DiagnosticsBundle::create(&destination, &app_version)?;
+        match write_items(&mut bundle, &log_dir, &file_stem, collected) {
+            Ok(()) => bundle.finish(&destination),
+            Err(e) => {
+                bundle.abort();
+                Err(e)
+            }
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)?;
+    log::info!(
+        "diagnostics bundle with {} items saved to {}",
+        manifest.items.len(),
+        save_path.display()
+    );
+    Ok(manifest)
+}
@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::commands::hardware::hardware_get_summary;
+use crate::disk_space::{volume_space, VolumeSpace};
+use crate::doctor::{
+    apply_file_fix, check_binary, check_disk_space, check_embedding_model, check_hardware,
+    check_ollama_version, check_port, check_storage_writable, DoctorCheck, DoctorFix,
+    DoctorFixResult, DoctorReport,
+};
+use crate::globals::SHINKAI_NODE_MANAGER_INSTANCE;
+use crate::local_shinkai_node::process_handlers::process_utils::kill_process_by_pid;
+use crate::local_shinkai_node::shinkai_node_manager::ShinkaiNodeManager;
+use crate::models::embedding_model;
+
+/// Time given to killed processes to release their ports before checking again
+const PORT_RELEASE_WAIT: Duration = Duration::from_secs(1);
+
+/// Sidecars are bundled next to the app executable
+fn sidecar_path(name: &str) -> PathBuf {
+    let file_name = if cfg!(target_os = "windows") {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(&file_name)))
+        .unwrap_or_else(|| PathBuf::from(file_name))
+}
+
+async fn run_checks(app: &tauri::AppHandle) -> Vec<DoctorCheck> {
+    let hardware = hardware_get_summary(app.clone()).await;
+    let ollama_version = ShinkaiNodeManager::get_ollama_version(app.clone())
+        .await
+        .map_err(|e| e.to_string());
+
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    let options = shinkai_node_manager_guard.get_shinkai_node_options().await;
+    let storage_path = shinkai_node_manager_guard.get_node_storage_path();
+    let llm_models_path = shinkai_node_manager_guard.get_llm_models_path();
+    let ollama_models_path = shinkai_node_manager_guard.get_ollama_models_path();
+    let port_listeners = shinkai_node_manager_guard.get_port_listeners();
+    let own_pids = shinkai_node_manager_guard.get_sidecar_pids().await;
+    let previous_run_sidecars = shinkai_node_manager_guard.get_previous_run_sidecars();
+    drop(shinkai_node_manager_guard);
+
+    let mut checks = vec![
+        check_binary("shinkai-node", &sidecar_path("shinkai-node")),
+        check_binary("ollama", &sidecar_path("ollama")),
+    ];
+    for (name, path) in [
+        ("deno", options.shinkai_tools_runner_deno_binary_path),
+        ("uv", options.shinkai_tools_runner_uv_binary_path),
+    ] {
+        if let Some(path) = path {
+            checks.push(check_binary(name, Path::new(&path)));
+        }
+    }
+    checks.push(check_storage_writable(&storage_path));
+    checks.push(check_embedding_model(&embedding_model::get_model_path(
+        &llm_models_path,
+    )));
+    checks.push(check_ollama_version(ollama_version));
+
+    // Storage and models usually live in the same volume, it's checked once
+    let mut volumes: Vec<Result<VolumeSpace, String>> = vec![];
+    for path in std::iter::once(Ok(storage_path)).chain(std::iter::once(ollama_models_path)) {
+        let volume = path.and_then(|path| volume_space(&path));
+        let seen = volumes.iter().any(|seen| match (seen, &volume) {
+            (Ok(seen), Ok(volume)) => seen.mount_point == volume.mount_point,
+            _ => false,
+        });
+        if !seen {
+            volumes.push(volume);
+        }
+    }
+    checks.extend(volumes.into_iter().map(check_disk_space));
+
+    checks.extend(
+        port_listeners
+            .iter()
+            .map(|listener| check_port(listener, &own_pids, &previous_run_sidecars)),
+    );
+    checks.push(check_hardware(hardware));
+    checks
+}
+
+/// Checks the environment the node and ollama run in, with `fix` the safe automatic fixes are
+/// applied and the checks run again
+#[tauri::command]
+pub async fn doctor(app_handle: tauri::AppHandle, fix: bool) -> Result<DoctorReport, String> {
+    let checks = run_checks(&app_handle).await;
+    let available_fixes: Vec<DoctorFix> = checks
+        .iter()
+        .filter_map(|check| check.fix.clone())
+        .collect();
+    if !fix || available_fixes.is_empty() {
+        return Ok(DoctorReport::new(checks, vec![]));
+    }
+
+    let mut fixes = vec![];
+    for fix in available_fixes {
+        let error = match &fix {
+            DoctorFix::FreePort { pids, .. } => {
+                let mut errors = vec![];
+                for pid in pids {
+                    let result = kill_process_by_pid(app_handle.clone(), &pid.to_string()).await;
+                    errors.extend(result.err());
+                }
+                tokio::time::sleep(PORT_RELEASE_WAIT).await;
+                (!errors.is_empty()).then(|| errors.join(", "))
+            }
+            _ => apply_file_fix(&fix).err(),
+        };
+        match &error {
+            Some(error) => log::warn!("doctor fix {:?} failed: {}", fix, error),
+            None => log::info!("doctor applied fix {:?}", fix),
+        }
+        fixes.push(DoctorFixResult { fix, error });
+    }
+    Ok(DoctorReport::new(run_checks(&app_handle).await, fixes))
+}
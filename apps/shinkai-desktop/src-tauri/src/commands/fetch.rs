@@ -3,6 +3,8 @@ use std::collections::HashMap;
 use reqwest::header::HeaderMap;
 use serde_json;
 
+use crate::log_redaction::redact_map;
+
 #[derive(Serialize)]
 pub struct FetchResponse {
     status: u16,
@@ -25,13 +27,13 @@ pub fn header_map_to_hashmap(headers: &HeaderMap) -> HashMap<String, Vec<String>
 #[tauri::command]
 pub async fn get_request(url: String, custom_headers: String) -> Result<FetchResponse, String> {
     log::debug!("get_request called with url: {}", url);
-    log::debug!("get_request called with custom_headers: {}", custom_headers);
     println!("get_request called with url: {}", url);
     eprintln!("get_request");
 
     // Deserialize the JSON string into a HashMap
     let custom_headers: HashMap<String, String> = serde_json::from_str(&custom_headers)
         .map_err(|e| e.to_string())?;
+    log::debug!("get_request called with custom_headers: {:?}", redact_map(&custom_headers));
 
     // Create a client
     let client = reqwest::Client::new();
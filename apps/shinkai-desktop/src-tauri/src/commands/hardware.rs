@@ -1,7 +1,66 @@
+use std::path::PathBuf;
+
+use tauri::Manager;
+
+use crate::globals::SHINKAI_NODE_MANAGER_INSTANCE;
 use crate::hardware;
+use crate::hardware_requirements::{RequirementProfiles, PROFILES_OVERRIDE_FILE_NAME};
+
+async fn node_storage_path() -> Option<PathBuf> {
+    match SHINKAI_NODE_MANAGER_INSTANCE.get() {
+        Some(shinkai_node_manager) => shinkai_node_manager
+            .read()
+            .await
+            .get_shinkai_node_options()
+            .await
+            .node_storage_path
+            .map(PathBuf::from),
+        None => None,
+    }
+}
+
+/// Bundled profiles merged with the ones users or support drop in the app data folder
+fn requirement_profiles(app: &tauri::AppHandle) -> RequirementProfiles {
+    let override_path = app
+        .path()
+        .app_data_dir()
+        .ok()
+        .map(|app_data_dir| app_data_dir.join(PROFILES_OVERRIDE_FILE_NAME));
+    RequirementProfiles::load(override_path.as_deref())
+}
+
+#[tauri::command]
+pub async fn hardware_get_summary(
+    app: tauri::AppHandle,
+) -> Result<hardware::HardwareSummary, String> {
+    hardware_evaluate_requirements(app, None).await
+}
+
+#[tauri::command]
+pub async fn hardware_get_requirement_profiles(
+    app: tauri::AppHandle,
+) -> Result<RequirementProfiles, String> {
+    Ok(requirement_profiles(&app))
+}
 
+/// Summary evaluated against `profile`, the default profile when None
 #[tauri::command]
-pub fn hardware_get_summary() -> Result<hardware::HardwareSummary, String> {
-    let hardware_summary = hardware::hardware_get_summary();
-    Ok(hardware_summary)
+pub async fn hardware_evaluate_requirements(
+    app: tauri::AppHandle,
+    profile: Option<String>,
+) -> Result<hardware::HardwareSummary, String> {
+    let profiles = requirement_profiles(&app);
+    let profile = match profile {
+        Some(id) => profiles
+            .get(&id)
+            .ok_or(format!("unknown requirement profile {}", id))?
+            .clone(),
+        None => profiles.default_profile().clone(),
+    };
+    let storage_path = node_storage_path().await;
+    tauri::async_runtime::spawn_blocking(move || {
+        hardware::hardware_get_summary(storage_path.as_deref(), &profile)
+    })
+    .await
+    .map_err(|e| e.to_string())
 }
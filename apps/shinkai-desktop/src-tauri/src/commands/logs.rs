@@ -1,70 +1,146 @@
-use std::path::{self, PathBuf};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 use serde::Serialize;
-use tauri::Manager;
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
+
+use crate::log_query::{self, LogEntry, LogFilter, LogPage, LogQuery, LogTail};
+use crate::log_redaction::redact;
+use crate::log_settings::{self, LogSettings, LOG_SETTINGS_FILE_NAME};
+
+const LOG_TAIL_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 #[derive(Debug, Clone, Serialize)]
-pub struct LogEntry {
-    timestamp: String,
-    level: String,
-    target: String,
-    message: String,
+pub struct LogTailEvent {
+    subscription_id: u32,
+    entries: Vec<LogEntry>,
 }
 
-fn get_log_file_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+fn log_tails() -> &'static Mutex<HashMap<u32, JoinHandle<()>>> {
+    static LOG_TAILS: OnceLock<Mutex<HashMap<u32, JoinHandle<()>>>> = OnceLock::new();
+    LOG_TAILS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Log folder and name of the log file without extension
+pub fn get_log_location(app_handle: &tauri::AppHandle) -> Result<(PathBuf, String), String> {
     let log_dir = app_handle.path().app_log_dir().map_err(|e| e.to_string())?;
     let product_name = app_handle
         .config()
         .product_name
         .clone()
         .unwrap_or("Shinkai Desktop".to_string());
-    let log_file = format!("{}/{}.log", log_dir.display(), product_name);
-    Ok(PathBuf::from(log_file))
+    Ok((log_dir, product_name))
+}
+
+fn get_log_file_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let (log_dir, file_stem) = get_log_location(app_handle)?;
+    Ok(log_dir.join(format!("{}.log", file_stem)))
 }
 
 #[tauri::command]
 pub fn retrieve_logs(app_handle: tauri::AppHandle) -> Result<Vec<LogEntry>, String> {
     let log_file = get_log_file_path(&app_handle)?;
 
-    let log_contents = match std::fs::read_to_string(log_file) {
-        Ok(contents) => contents,
+    match log_query::read_entries(&log_file) {
+        Ok(log_entries) => Ok(log_entries),
         Err(e) => {
             log::error!("Failed to read log file: {}", e);
-            return Err("Failed to read log file".to_string());
+            Err("Failed to read log file".to_string())
         }
-    };
-
-    // Split log contents into lines and parse each line into a LogEntry
-    let log_entries: Vec<LogEntry> = log_contents
-        .split("　　　")
-        .filter(|line| !line.is_empty())
-        .filter_map(|line| {
-            // Expected format: [timestamp][level][target] message
-            let re = regex::Regex::new(r"\[(.*?)\]\[(.*?)\]\[(.*?)\] ((?s).*)").unwrap();
-            if let Some(captures) = re.captures(line) {
-                let timestamp = captures.get(1).unwrap().as_str().to_string();
-                let level = captures.get(2).unwrap().as_str().to_string();
-                let target = captures.get(3).unwrap().as_str().to_string();
-                let message = captures.get(4).unwrap().as_str().trim().to_string();
-
-                return Some(LogEntry {
-                    timestamp,
-                    level,
-                    target,
-                    message,
-                });
+    }
+}
+
+#[tauri::command]
+pub async fn query_logs(app_handle: tauri::AppHandle, query: LogQuery) -> Result<LogPage, String> {
+    let (log_dir, file_stem) = get_log_location(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        log_query::query_logs(&log_dir, &file_stem, &query)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)
+}
+
+/// Emits `logs-tail` events with the new entries matching `filter` until unsubscribed
+#[tauri::command]
+pub fn subscribe_logs(app_handle: tauri::AppHandle, filter: LogFilter) -> Result<u32, String> {
+    static NEXT_SUBSCRIPTION_ID: AtomicU32 = AtomicU32::new(1);
+    let matcher = filter.matcher()?;
+    let mut tail = LogTail::new(get_log_file_path(&app_handle)?);
+    let subscription_id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+    let handle = tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(LOG_TAIL_POLL_INTERVAL).await;
+            // Errors aren't logged, they would be appended to the file being followed
+            let Ok(entries) = tail.poll() else {
+                continue;
+            };
+            let entries: Vec<LogEntry> = entries
+                .into_iter()
+                .filter(|entry| matcher.matches(entry))
+                .collect();
+            if !entries.is_empty() {
+                let _ = app_handle.emit(
+                    "logs-tail",
+                    LogTailEvent {
+                        subscription_id,
+                        entries,
+                    },
+                );
             }
-            None
-        })
-        .collect();
+        }
+    });
+    log_tails().lock().unwrap().insert(subscription_id, handle);
+    Ok(subscription_id)
+}
+
+#[tauri::command]
+pub fn unsubscribe_logs(subscription_id: u32) -> Result<(), String> {
+    if let Some(handle) = log_tails().lock().unwrap().remove(&subscription_id) {
+        handle.abort();
+    }
+    Ok(())
+}
 
-    Ok(log_entries)
+fn get_log_settings_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join(LOG_SETTINGS_FILE_NAME))
+}
+
+#[tauri::command]
+pub fn get_log_settings(app_handle: tauri::AppHandle) -> Result<LogSettings, String> {
+    Ok(log_settings::load_settings(&get_log_settings_path(
+        &app_handle,
+    )?))
+}
+
+/// Retention applies right away, format and file size once the app restarts
+#[tauri::command]
+pub fn set_log_settings(
+    app_handle: tauri::AppHandle,
+    settings: LogSettings,
+) -> Result<LogSettings, String> {
+    log_settings::save_settings(&get_log_settings_path(&app_handle)?, &settings)?;
+    let (log_dir, file_stem) = get_log_location(&app_handle)?;
+    log_settings::prune_rotated_logs(&log_dir, &file_stem, &settings)?;
+    log::info!("log settings updated: {:?}", settings);
+    Ok(settings)
 }
 
 #[tauri::command]
 pub fn download_logs(app_handle: tauri::AppHandle, save_path: String) -> Result<String, String> {
     let log_file = get_log_file_path(&app_handle)?;
-    match std::fs::copy(log_file.clone(), save_path.clone()) {
+    // Lines written before redaction existed may still hold secrets
+    let result = std::fs::read_to_string(&log_file)
+        .and_then(|contents| std::fs::write(&save_path, redact(&contents).as_bytes()));
+    match result {
         Ok(_) => Ok(save_path),
         Err(e) => {
             let log_file_str = log_file.to_string_lossy();
@@ -875,3 +875,74 @@ pub async fn get_cursor_sse_config_help(
         e.to_string()
     })
 }
+
+// --- Status ---
+#[derive(Serialize, Clone, Debug)]
+pub struct McpClientStatus {
+    pub client: String,
+    pub installed: bool,
+    pub config_path: Option<String>,
+    /// Names of the configured servers, their commands and env are left out
+    pub configured_servers: Vec<String>,
+    pub error: Option<String>,
+}
+
+fn read_configured_servers(config_path: &PathBuf) -> Result<Vec<String>, String> {
+    if !config_path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(config_path).map_err(|e| e.to_string())?;
+    if content.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let parsed: Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let mut servers: Vec<String> = parsed
+        .get("mcpServers")
+        .and_then(|servers| servers.as_object())
+        .map(|servers| servers.keys().cloned().collect())
+        .unwrap_or_default();
+    servers.sort();
+    Ok(servers)
+}
+
+fn mcp_client_status(
+    client: &str,
+    config_path: Result<PathBuf, String>,
+    installed: impl Fn(&PathBuf) -> bool,
+) -> McpClientStatus {
+    let mut status = McpClientStatus {
+        client: client.to_string(),
+        installed: false,
+        config_path: None,
+        configured_servers: vec![],
+        error: None,
+    };
+    match config_path {
+        Ok(config_path) => {
+            status.installed = installed(&config_path);
+            status.config_path = Some(config_path.display().to_string());
+            match read_configured_servers(&config_path) {
+                Ok(servers) => status.configured_servers = servers,
+                Err(e) => status.error = Some(e),
+            }
+        }
+        Err(e) => status.error = Some(e),
+    }
+    status
+}
+
+/// Whether Claude and Cursor are installed and which MCP servers they have configured
+pub fn get_mcp_clients_status() -> Vec<McpClientStatus> {
+    vec![
+        mcp_client_status(
+            "claude",
+            get_claude_config_path().map_err(|e| e.to_string()),
+            |config_path| config_path.exists(),
+        ),
+        mcp_client_status(
+            "cursor",
+            get_cursor_config_path().map_err(|e| e.to_string()),
+            |config_path| config_path.parent().is_some_and(|p| p.exists()),
+        ),
+    ]
+}
@@ -1,7 +1,10 @@
+pub mod diagnostics;
+pub mod doctor;
 pub mod fetch;
 pub mod galxe;
 pub mod hardware;
 pub mod logs;
+pub mod ollama_commands;
 pub mod shinkai_node_manager_commands;
 pub mod mcp_clients_install;
 pub mod spotlight_commands;
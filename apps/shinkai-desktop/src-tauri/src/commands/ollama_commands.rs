@@ -0,0 +1,190 @@
+use std::path::PathBuf;
+
+use crate::globals::SHINKAI_NODE_MANAGER_INSTANCE;
+use crate::local_shinkai_node::ollama_api::ollama_disk_preflight::{
+    DiskSpacePreflight, PullModelError,
+};
+use crate::local_shinkai_node::ollama_api::ollama_model_archive::ModelArchiveManifest;
+use crate::local_shinkai_node::ollama_api::ollama_model_benchmark::BenchmarkRecord;
+use crate::local_shinkai_node::ollama_api::ollama_model_memory::{
+    LoadedModel, MemoryPressureOptions,
+};
+use crate::local_shinkai_node::ollama_api::ollama_model_store::PartialDownloadsCleanup;
+use crate::local_shinkai_node::ollama_api::ollama_model_usage::{
+    ModelGarbageCollection, ModelStoreUsage,
+};
+use crate::local_shinkai_node::ollama_api::ollama_registry_client::RegistryReachability;
+use crate::local_shinkai_node::process_handlers::ollama_process_handler::OllamaOptions;
+use crate::models::model_recommendations::ModelRecommendations;
+
+#[tauri::command]
+pub async fn ollama_get_options() -> Result<OllamaOptions, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_ollama_options())
+}
+
+#[tauri::command]
+pub async fn ollama_set_options(options: OllamaOptions) -> Result<OllamaOptions, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.set_ollama_options(options)
+}
+
+#[tauri::command]
+pub async fn ollama_pull_preflight(model: String) -> Result<DiskSpacePreflight, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .ollama_pull_preflight(&model)
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_pull_model(model: String) -> Result<String, PullModelError> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.pull_ollama_model(&model).await
+}
+
+#[tauri::command]
+pub async fn ollama_cleanup_partial_downloads() -> Result<PartialDownloadsCleanup, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .cleanup_ollama_partial_downloads()
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_check_registry(
+    registry: Option<String>,
+    insecure: Option<bool>,
+    model: Option<String>,
+) -> Result<RegistryReachability, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .check_ollama_registry(registry, insecure, model)
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_export_model(
+    model: String,
+    archive_path: String,
+) -> Result<ModelArchiveManifest, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .export_ollama_model(&model, PathBuf::from(archive_path))
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_import_model(
+    archive_path: String,
+    model: Option<String>,
+) -> Result<ModelArchiveManifest, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .import_ollama_model(PathBuf::from(archive_path), model)
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_get_models_disk_usage() -> Result<ModelStoreUsage, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .get_ollama_models_disk_usage()
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_gc_models(dry_run: bool) -> Result<ModelGarbageCollection, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.gc_ollama_models(dry_run).await
+}
+
+#[tauri::command]
+pub async fn ollama_list_loaded_models() -> Result<Vec<LoadedModel>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.list_ollama_loaded_models().await
+}
+
+#[tauri::command]
+pub async fn ollama_preload_model(model: String, keep_alive: Option<i64>) -> Result<(), String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .preload_ollama_model(&model, keep_alive)
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_unload_model(model: String) -> Result<(), String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.unload_ollama_model(&model).await
+}
+
+#[tauri::command]
+pub async fn ollama_unload_all_models() -> Result<Vec<String>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.unload_all_ollama_models().await
+}
+
+#[tauri::command]
+pub async fn ollama_get_idle_unload_timeout() -> Result<Option<u64>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_ollama_idle_unload_timeout())
+}
+
+#[tauri::command]
+pub async fn ollama_set_idle_unload_timeout(minutes: Option<u64>) -> Result<Option<u64>, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.set_ollama_idle_unload_timeout(minutes)
+}
+
+#[tauri::command]
+pub async fn ollama_get_memory_pressure_options() -> Result<Option<MemoryPressureOptions>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_ollama_memory_pressure_options())
+}
+
+#[tauri::command]
+pub async fn ollama_set_memory_pressure_options(
+    options: Option<MemoryPressureOptions>,
+) -> Result<Option<MemoryPressureOptions>, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.set_ollama_memory_pressure_options(options)
+}
+
+#[tauri::command]
+pub async fn ollama_get_suggested_memory_pressure_options() -> Result<MemoryPressureOptions, String>
+{
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_suggested_memory_pressure_options())
+}
+
+#[tauri::command]
+pub async fn ollama_is_under_memory_pressure() -> Result<bool, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.is_under_memory_pressure())
+}
+
+#[tauri::command]
+pub async fn ollama_benchmark_model(
+    model: String,
+    embedding_model: Option<String>,
+) -> Result<BenchmarkRecord, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .benchmark_ollama_model(&model, embedding_model)
+        .await
+}
+
+#[tauri::command]
+pub async fn ollama_get_benchmark_history() -> Result<Vec<BenchmarkRecord>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.get_ollama_benchmark_history()
+}
+
+#[tauri::command]
+pub async fn ollama_get_model_recommendations() -> Result<ModelRecommendations, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .get_ollama_model_recommendations()
+        .await
+}
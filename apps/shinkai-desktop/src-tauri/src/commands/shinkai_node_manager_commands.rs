@@ -1,7 +1,23 @@
+use std::path::PathBuf;
+
 use log::error;
 
 use crate::globals::SHINKAI_NODE_MANAGER_INSTANCE;
-use crate::local_shinkai_node::shinkai_node_manager::ShinkaiNodeManager;
+use crate::local_shinkai_node::node_storage::backup_schedule::{
+    BackupSchedule, StorageBackupEntry,
+};
+use crate::local_shinkai_node::node_storage::identity_keys::IdentityKeys;
+use crate::local_shinkai_node::node_storage::storage_backup::{
+    StorageBackupManifest, StorageRestore,
+};
+use crate::local_shinkai_node::node_storage::storage_reset::{StorageReset, StorageResetScope};
+use crate::local_shinkai_node::node_storage::storage_usage::StorageUsage;
+use crate::local_shinkai_node::process_handlers::process_limits::ProcessLimits;
+use crate::local_shinkai_node::process_handlers::resource_usage::ResourceUsageSample;
+use crate::local_shinkai_node::secrets_store::{SecretsProtection, SecretsStoreStatus};
+use crate::local_shinkai_node::shinkai_node_manager::{
+    RelocatedStorage, ShinkaiNodeManager, SidecarLimits,
+};
 use crate::local_shinkai_node::shinkai_node_options::ShinkaiNodeOptions;
 use crate::windows::{recreate_window, Window};
 
@@ -35,6 +51,31 @@ pub async fn shinkai_node_get_options() -> Result<ShinkaiNodeOptions, String> {
     Ok(options)
 }
 
+#[tauri::command]
+pub async fn shinkai_node_get_secrets_status() -> Result<SecretsStoreStatus, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_secrets_status())
+}
+
+#[tauri::command]
+pub async fn shinkai_node_unlock_secrets(
+    passphrase: String,
+) -> Result<SecretsStoreStatus, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.unlock_secrets(passphrase).await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_set_secrets_protection(
+    protection: SecretsProtection,
+    passphrase: Option<String>,
+) -> Result<SecretsStoreStatus, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard
+        .set_secrets_protection(protection, passphrase)
+        .await
+}
+
 #[tauri::command]
 pub async fn shinkai_node_spawn() -> Result<(), String> {
     let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
@@ -62,10 +103,130 @@ pub async fn shinkai_node_remove_storage(preserve_keys: bool) -> Result<(), Stri
         .await
     {
         Ok(_) => Ok(()),
-        Err(_) => Ok(()),
+        Err(message) => {
+            error!("error removing shinkai node storage: {}", message);
+            Err(message)
+        }
     }
 }
 
+#[tauri::command]
+pub async fn shinkai_node_reset_storage(
+    scopes: Vec<StorageResetScope>,
+    preserve_keys: bool,
+    dry_run: bool,
+    purge: bool,
+) -> Result<StorageReset, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard
+        .reset_storage(scopes, preserve_keys, dry_run, purge)
+        .await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_get_last_storage_reset() -> Result<Option<StorageReset>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.get_last_storage_reset()
+}
+
+#[tauri::command]
+pub async fn shinkai_node_undo_storage_reset() -> Result<StorageReset, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.undo_storage_reset().await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_export_identity(
+    export_path: PathBuf,
+    passphrase: String,
+) -> Result<IdentityKeys, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .export_identity_keys(export_path, passphrase)
+        .await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_inspect_identity_export(
+    export_path: PathBuf,
+) -> Result<IdentityKeys, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.inspect_identity_export(export_path)
+}
+
+#[tauri::command]
+pub async fn shinkai_node_import_identity(
+    export_path: PathBuf,
+    passphrase: String,
+    overwrite: bool,
+) -> Result<IdentityKeys, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard
+        .import_identity_keys(export_path, passphrase, overwrite)
+        .await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_get_storage_usage(top: Option<usize>) -> Result<StorageUsage, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.get_storage_usage(top).await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_backup_storage(
+    archive_path: PathBuf,
+) -> Result<StorageBackupManifest, String> {
+    // Write lock so the node can't be spawned while its storage is read
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.backup_storage(archive_path).await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_verify_storage_backup(
+    archive_path: PathBuf,
+) -> Result<StorageBackupManifest, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard
+        .verify_storage_backup(archive_path)
+        .await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_restore_storage(archive_path: PathBuf) -> Result<StorageRestore, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.restore_storage(archive_path).await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_relocate_storage(
+    destination: PathBuf,
+) -> Result<RelocatedStorage, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.relocate_storage(destination).await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_get_backup_schedule() -> Result<Option<BackupSchedule>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_backup_schedule())
+}
+
+#[tauri::command]
+pub async fn shinkai_node_set_backup_schedule(
+    schedule: Option<BackupSchedule>,
+) -> Result<Option<BackupSchedule>, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.set_backup_schedule(schedule)
+}
+
+#[tauri::command]
+pub async fn shinkai_node_list_storage_backups(
+    verify: bool,
+) -> Result<Vec<StorageBackupEntry>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    shinkai_node_manager_guard.list_storage_backups(verify).await
+}
+
 #[tauri::command]
 pub async fn shinkai_node_set_default_options() -> Result<ShinkaiNodeOptions, String> {
     let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
@@ -123,3 +284,32 @@ pub async fn shinkai_node_open_chat_folder(storage_location: &str, chat_folder_n
         Err(message) => Err(message),
     }
 }
+
+#[tauri::command]
+pub async fn shinkai_node_get_resource_usage_history() -> Result<Vec<ResourceUsageSample>, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_resource_usage_history())
+}
+
+#[tauri::command]
+pub async fn shinkai_node_get_sidecar_limits() -> Result<SidecarLimits, String> {
+    let shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+    Ok(shinkai_node_manager_guard.get_sidecar_limits())
+}
+
+#[tauri::command]
+pub async fn shinkai_node_set_sidecar_limits(
+    shinkai_node: ProcessLimits,
+    ollama: ProcessLimits,
+) -> Result<SidecarLimits, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard
+        .set_sidecar_limits(shinkai_node, ollama)
+        .await
+}
+
+#[tauri::command]
+pub async fn shinkai_node_set_background_mode(enabled: bool) -> Result<SidecarLimits, String> {
+    let mut shinkai_node_manager_guard = SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+    shinkai_node_manager_guard.set_background_mode(enabled).await
+}
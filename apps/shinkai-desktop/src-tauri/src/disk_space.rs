@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::Disks;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VolumeSpace {
+    pub path: String,
+    pub mount_point: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+/// Closest ancestor of `path` that exists, target folders (eg: a fresh node storage) may not exist yet
+fn closest_existing_ancestor(path: &Path) -> PathBuf {
+    let mut current = path.to_path_buf();
+    loop {
+        if let Ok(canonical) = current.canonicalize() {
+            // Windows canonical paths are verbatim (\\?\C:\...) while mount points are not
+            let canonical = canonical.to_string_lossy().to_string();
+            return PathBuf::from(canonical.trim_start_matches(r"\\?\"));
+        }
+        if !current.pop() {
+            return path.to_path_buf();
+        }
+    }
+}
+
+/// Returns total and available space of the volume holding `path`
+pub fn volume_space(path: &Path) -> Result<VolumeSpace, String> {
+    let existing_path = closest_existing_ancestor(path);
+    let disks = Disks::new_with_refreshed_list();
+    // The volume is the one with the longest mount point containing the path
+    let disk = disks
+        .list()
+        .iter()
+        .filter(|disk| existing_path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .ok_or_else(|| format!("failed to find the volume of {}", path.display()))?;
+    Ok(VolumeSpace {
+        path: path.to_string_lossy().to_string(),
+        mount_point: disk.mount_point().to_string_lossy().to_string(),
+        total_bytes: disk.total_space(),
+        available_bytes: disk.available_space(),
+    })
+}
@@ -0,0 +1,502 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::disk_space::VolumeSpace;
+use crate::hardware::{HardwareSummary, RequirementsStatus};
+use crate::local_shinkai_node::ollama_api::ollama_api_client::OLLAMA_VERSION_REQUIREMENT;
+use crate::local_shinkai_node::ollama_api::ollama_disk_preflight::FREE_SPACE_RESERVE_BYTES;
+use crate::local_shinkai_node::process_handlers::process_utils::{PortListener, SidecarPid};
+
+/// Below this much free space models can't be pulled and the node database may stop growing soon
+pub const LOW_DISK_SPACE_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+const WRITE_PROBE_FILE_NAME: &str = ".shinkai-doctor-probe";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DoctorCheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Fixes the doctor applies by itself, they never touch data or processes the app doesn't own
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum DoctorFix {
+    CreateDirectory { path: String },
+    MakeExecutable { path: String },
+    FreePort { port: u16, pids: Vec<u32> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DoctorFixResult {
+    pub fix: DoctorFix,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DoctorCheck {
+    pub id: String,
+    pub name: String,
+    pub status: DoctorCheckStatus,
+    pub message: String,
+    pub remediation: Option<String>,
+    /// Safe automatic fix for this check, None when it passes or has to be fixed by hand
+    pub fix: Option<DoctorFix>,
+}
+
+impl DoctorCheck {
+    fn new(id: &str, name: &str, status: DoctorCheckStatus, message: String) -> Self {
+        DoctorCheck {
+            id: id.to_string(),
+            name: name.to_string(),
+            status,
+            message,
+            remediation: None,
+            fix: None,
+        }
+    }
+
+    fn pass(id: &str, name: &str, message: String) -> Self {
+        Self::new(id, name, DoctorCheckStatus::Pass, message)
+    }
+
+    fn warn(id: &str, name: &str, message: String, remediation: &str) -> Self {
+        Self::new(id, name, DoctorCheckStatus::Warn, message).with_remediation(remediation)
+    }
+
+    fn fail(id: &str, name: &str, message: String, remediation: &str) -> Self {
+        Self::new(id, name, DoctorCheckStatus::Fail, message).with_remediation(remediation)
+    }
+
+    fn with_remediation(mut self, remediation: &str) -> Self {
+        self.remediation = Some(remediation.to_string());
+        self
+    }
+
+    fn with_fix(mut self, fix: DoctorFix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DoctorReport {
+    pub created_at: String,
+    /// Worst status among the checks
+    pub status: DoctorCheckStatus,
+    pub checks: Vec<DoctorCheck>,
+    /// Fixes applied before `checks` were run again, empty unless fixes were requested
+    pub fixes: Vec<DoctorFixResult>,
+}
+
+impl DoctorReport {
+    pub fn new(checks: Vec<DoctorCheck>, fixes: Vec<DoctorFixResult>) -> Self {
+        DoctorReport {
+            created_at: chrono::Utc::now().to_rfc3339(),
+            status: checks
+                .iter()
+                .map(|check| check.status)
+                .max()
+                .unwrap_or(DoctorCheckStatus::Pass),
+            checks,
+            fixes,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
+
+/// Sidecar or tool binary shipped with the app
+pub fn check_binary(name: &str, path: &Path) -> DoctorCheck {
+    let id = format!("binary:{}", name);
+    let title = format!("{} binary", name);
+    let display = path.display();
+    match fs::metadata(path) {
+        Ok(metadata) if !metadata.is_file() => DoctorCheck::fail(
+            &id,
+            &title,
+            format!("{} is not a file", display),
+            "Reinstall Shinkai Desktop",
+        ),
+        Ok(metadata) if !is_executable(&metadata) => DoctorCheck::fail(
+            &id,
+            &title,
+            format!("{} is not executable", display),
+            "Allow the file to be executed or reinstall Shinkai Desktop",
+        )
+        .with_fix(DoctorFix::MakeExecutable {
+            path: path.to_string_lossy().to_string(),
+        }),
+        Ok(_) => DoctorCheck::pass(&id, &title, format!("{} is executable", display)),
+        Err(e) => DoctorCheck::fail(
+            &id,
+            &title,
+            format!("{} can't be read: {}", display, e),
+            "Reinstall Shinkai Desktop, an antivirus may have quarantined the file",
+        ),
+    }
+}
+
+/// The node storage must exist and accept new files
+pub fn check_storage_writable(path: &Path) -> DoctorCheck {
+    let id = "storage-writable";
+    let title = "Node storage";
+    if !path.exists() {
+        return DoctorCheck::fail(
+            id,
+            title,
+            format!("{} doesn't exist", path.display()),
+            "Recreate the folder or relocate the node storage",
+        )
+        .with_fix(DoctorFix::CreateDirectory {
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+    if !path.is_dir() {
+        return DoctorCheck::fail(
+            id,
+            title,
+            format!("{} is not a folder", path.display()),
+            "Move the file away or relocate the node storage",
+        );
+    }
+    let probe = path.join(WRITE_PROBE_FILE_NAME);
+    match fs::write(&probe, b"shinkai").and_then(|_| fs::remove_file(&probe)) {
+        Ok(()) => DoctorCheck::pass(id, title, format!("{} is writable", path.display())),
+        Err(e) => DoctorCheck::fail(
+            id,
+            title,
+            format!("{} is not writable: {}", path.display(), e),
+            "Give your user write access to the folder or relocate the node storage",
+        ),
+    }
+}
+
+/// The embedded GGUF the default embedding model is created from
+pub fn check_embedding_model(path: &Path) -> DoctorCheck {
+    let id = "embedding-model";
+    let title = "Embedding model";
+    let remediation =
+        "Reinstall Shinkai Desktop, the default embedding model can't be created without this file";
+    let mut magic = [0u8; 4];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut magic)) {
+        Ok(()) if &magic == b"GGUF" => {
+            DoctorCheck::pass(id, title, format!("{} is a GGUF file", path.display()))
+        }
+        Ok(()) => DoctorCheck::fail(
+            id,
+            title,
+            format!("{} is not a GGUF file", path.display()),
+            remediation,
+        ),
+        Err(e) => DoctorCheck::fail(
+            id,
+            title,
+            format!("{} can't be read: {}", path.display(), e),
+            remediation,
+        ),
+    }
+}
+
+pub fn check_ollama_version(version: Result<String, String>) -> DoctorCheck {
+    let id = "ollama-version";
+    let title = "Ollama version";
+    let version = match version {
+        Ok(version) => version,
+        Err(e) => {
+            return DoctorCheck::fail(
+                id,
+                title,
+                format!("ollama version couldn't be read: {}", e),
+                "Reinstall Shinkai Desktop, the bundled ollama fails to run",
+            )
+        }
+    };
+    let requirement = VersionReq::parse(OLLAMA_VERSION_REQUIREMENT).unwrap();
+    match Version::parse(&version) {
+        Ok(parsed) if requirement.matches(&parsed) => {
+            DoctorCheck::pass(id, title, format!("ollama {}", version))
+        }
+        Ok(_) => DoctorCheck::fail(
+            id,
+            title,
+            format!(
+                "ollama {} doesn't match {}",
+                version, OLLAMA_VERSION_REQUIREMENT
+            ),
+            "Quit any other ollama installation and update Shinkai Desktop",
+        ),
+        Err(_) => DoctorCheck::warn(
+            id,
+            title,
+            format!("ollama version {} couldn't be compared", version),
+            "Update Shinkai Desktop if models fail to be created",
+        ),
+    }
+}
+
+/// Free space of a volume holding the node storage or the models
+pub fn check_disk_space(volume: Result<VolumeSpace, String>) -> DoctorCheck {
+    let volume = match volume {
+        Ok(volume) => volume,
+        Err(e) => {
+            return DoctorCheck::warn(
+                "disk-space",
+                "Disk space",
+                format!("free space couldn't be read: {}", e),
+                "Make sure the volume is mounted",
+            )
+        }
+    };
+    let id = format!("disk-space:{}", volume.mount_point);
+    let title = format!("Disk space of {}", volume.mount_point);
+    let message = format!(
+        "{} MiB free on {}",
+        volume.available_bytes / 1024 / 1024,
+        volume.mount_point
+    );
+    let remediation = "Free disk space by removing unused models or relocate the node storage";
+    if volume.available_bytes < FREE_SPACE_RESERVE_BYTES {
+        DoctorCheck::fail(&id, &title, message, remediation)
+    } else if volume.available_bytes < LOW_DISK_SPACE_BYTES {
+        DoctorCheck::warn(&id, &title, message, remediation)
+    } else {
+        DoctorCheck::pass(&id, &title, message)
+    }
+}
+
+/// A port is fine when it's free or held by a sidecar the app is running, `own_pids`. It can only
+/// be freed when the holders are sidecars the previous run recorded, `previous_run_sidecars`
+pub fn check_port(
+    listener: &PortListener,
+    own_pids: &[u32],
+    previous_run_sidecars: &[SidecarPid],
+) -> DoctorCheck {
+    let id = format!("port:{}", listener.port);
+    let title = format!("Port {} ({})", listener.port, listener.role);
+    if let Some(error) = &listener.error {
+        return DoctorCheck::warn(
+            &id,
+            &title,
+            format!("listeners couldn't be read: {}", error),
+            "Check no other application uses the port",
+        );
+    }
+    let (own, others): (Vec<_>, Vec<_>) = listener
+        .processes
+        .iter()
+        .partition(|process| own_pids.contains(&process.pid));
+    if others.is_empty() {
+        let message = if own.is_empty() {
+            format!("port {} is free", listener.port)
+        } else {
+            format!("port {} is used by shinkai", listener.port)
+        };
+        return DoctorCheck::pass(&id, &title, message);
+    }
+    let holders = others
+        .iter()
+        .map(|process| format!("{} ({})", process.name, process.pid))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = format!("port {} is taken by {}", listener.port, holders);
+    // The name is checked too in case the pid was reused by another process
+    let stale = others.iter().all(|process| {
+        previous_run_sidecars
+            .iter()
+            .any(|sidecar| sidecar.pid == process.pid && process.name.starts_with(&sidecar.name))
+    });
+    if stale {
+        DoctorCheck::warn(
+            &id,
+            &title,
+            message,
+            "A shinkai process left over from a previous run holds the port, it can be stopped",
+        )
+        .with_fix(DoctorFix::FreePort {
+            port: listener.port,
+            pids: others.iter().map(|process| process.pid).collect(),
+        })
+    } else {
+        DoctorCheck::fail(
+            &id,
+            &title,
+            message,
+            "Quit the application using the port or change the port in the node options",
+        )
+    }
+}
+
+pub fn check_hardware(summary: Result<HardwareSummary, String>) -> DoctorCheck {
+    let id = "hardware";
+    let title = "Hardware requirements";
+    let summary = match summary {
+        Ok(summary) => summary,
+        Err(e) => {
+            return DoctorCheck::warn(
+                id,
+                title,
+                format!("hardware couldn't be evaluated: {}", e),
+                "Restart Shinkai Desktop",
+            )
+        }
+    };
+    let reasons = summary
+        .evaluation
+        .downgrade_reasons
+        .iter()
+        .map(|reason| {
+            format!(
+                "{:?} needs {} (found {})",
+                reason.criterion, reason.required, reason.actual
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = format!(
+        "{:?} for the {} profile",
+        summary.evaluation.requirements_status, summary.evaluation.requirements_profile
+    );
+    let message = if reasons.is_empty() {
+        message
+    } else {
+        format!("{}: {}", message, reasons)
+    };
+    match summary.evaluation.requirements_status {
+        RequirementsStatus::Unmeet => DoctorCheck::fail(
+            id,
+            title,
+            message,
+            "Use a cloud model, local models won't run on this machine",
+        ),
+        RequirementsStatus::StillUsable => DoctorCheck::warn(
+            id,
+            title,
+            message,
+            "Prefer small local models or a cloud model",
+        ),
+        _ => DoctorCheck::pass(id, title, message),
+    }
+}
+
+/// Applies the fixes that only touch files, `FreePort` needs the app to kill processes
+pub fn apply_file_fix(fix: &DoctorFix) -> Result<(), String> {
+    match fix {
+        DoctorFix::CreateDirectory { path } => {
+            fs::create_dir_all(path).map_err(|e| format!("failed to create {}: {}", path, e))
+        }
+        DoctorFix::MakeExecutable { path } => make_executable(Path::new(path)),
+        DoctorFix::FreePort { port, .. } => Err(format!("port {} can't be freed here", port)),
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?
+        .permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    fs::set_permissions(path, permissions)
+        .map_err(|e| format!("failed to make {} executable: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_shinkai_node::process_handlers::process_utils::PortProcess;
+
+    fn listener(processes: Vec<(u32, &str)>) -> PortListener {
+        PortListener {
+            port: 9550,
+            role: "shinkai-node api".to_string(),
+            processes: processes
+                .into_iter()
+                .map(|(pid, name)| PortProcess {
+                    pid,
+                    name: name.to_string(),
+                })
+                .collect(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_port_fix_only_targets_recorded_sidecars() {
+        let previous_run = vec![SidecarPid {
+            name: "shinkai-node".to_string(),
+            pid: 11,
+        }];
+        assert_eq!(
+            check_port(&listener(vec![]), &[], &previous_run).status,
+            DoctorCheckStatus::Pass
+        );
+        assert_eq!(
+            check_port(&listener(vec![(10, "shinkai-node")]), &[10], &previous_run).status,
+            DoctorCheckStatus::Pass
+        );
+
+        let stale = check_port(&listener(vec![(11, "shinkai-node")]), &[10], &previous_run);
+        assert_eq!(stale.status, DoctorCheckStatus::Warn);
+        assert_eq!(
+            stale.fix,
+            Some(DoctorFix::FreePort {
+                port: 9550,
+                pids: vec![11]
+            })
+        );
+
+        // Same name but not recorded, or a recorded pid reused by another process
+        for processes in [vec![(12, "shinkai-node")], vec![(11, "postgres")]] {
+            let foreign = check_port(&listener(processes), &[], &previous_run);
+            assert_eq!(foreign.status, DoctorCheckStatus::Fail);
+            assert_eq!(foreign.fix, None);
+        }
+    }
+
+    #[test]
+    fn test_storage_fix_recreates_the_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let storage = root.join("node-storage");
+
+        let check = check_storage_writable(&storage);
+        assert_eq!(check.status, DoctorCheckStatus::Fail);
+        apply_file_fix(check.fix.as_ref().unwrap()).unwrap();
+        let check = check_storage_writable(&storage);
+        assert_eq!(check.status, DoctorCheckStatus::Pass);
+        assert!(!storage.join(WRITE_PROBE_FILE_NAME).exists());
+
+        fs::write(root.join("embedding.gguf"), b"").unwrap();
+        assert_eq!(
+            check_embedding_model(&root.join("embedding.gguf")).status,
+            DoctorCheckStatus::Fail
+        );
+        assert_eq!(
+            check_ollama_version(Ok("0.5.1".to_string())).status,
+            DoctorCheckStatus::Fail
+        );
+        assert_eq!(
+            check_ollama_version(Ok("0.6.2".to_string())).status,
+            DoctorCheckStatus::Pass
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::local_shinkai_node::process_handlers::process_utils::command_output;
+
+pub const PCI_VENDOR_NVIDIA: u32 = 0x10de;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuMemorySource {
+    Sysfs,
+    NvidiaSmi,
+    SystemProfiler,
+    WindowsRegistry,
+    /// The GPU shares the system RAM (Apple silicon)
+    UnifiedMemory,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GpuMemory {
+    pub name: Option<String>,
+    /// PCI ids, None when the source doesn't expose them
+    pub vendor_id: Option<u32>,
+    pub device_id: Option<u32>,
+    pub memory_bytes: u64,
+    pub unified_memory: bool,
+    pub source: GpuMemorySource,
+}
+
+impl GpuMemory {
+    /// Matches a wgpu adapter by PCI ids when both sides know them, by name otherwise
+    pub fn matches_adapter(&self, adapter_name: &str, vendor_id: u32, device_id: u32) -> bool {
+        if let (Some(vendor), Some(device)) = (self.vendor_id, self.device_id) {
+            if vendor_id != 0 && device_id != 0 {
+                return vendor == vendor_id && device == device_id;
+            }
+        }
+        match &self.name {
+            Some(name) => {
+                let name = name.to_lowercase();
+                let adapter_name = adapter_name.to_lowercase();
+                !name.is_empty() && (adapter_name.contains(&name) || name.contains(&adapter_name))
+            }
+            None => false,
+        }
+    }
+}
+
+fn parse_hex_id(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim().trim_start_matches("0x"), 16).ok()
+}
+
+/// amdgpu exposes the dedicated memory of every card in `class/drm/cardN/device/mem_info_vram_total`
+pub fn read_sysfs_gpu_memory(sysfs_root: &Path) -> Vec<GpuMemory> {
+    let Ok(entries) = fs::read_dir(sysfs_root.join("class/drm")) else {
+        return vec![];
+    };
+    let mut cards: Vec<(String, GpuMemory)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let card = entry.file_name().to_string_lossy().to_string();
+            // Connectors (card0-HDMI-A-1) share the device folder of their card
+            if !card.starts_with("card") || card.contains('-') {
+                return None;
+            }
+            let device_path = entry.path().join("device");
+            let memory_bytes = fs::read_to_string(device_path.join("mem_info_vram_total"))
+                .ok()?
+                .trim()
+                .parse::<u64>()
+                .ok()?;
+            let read_id = |file: &str| {
+                fs::read_to_string(device_path.join(file))
+                    .ok()
+                    .and_then(|value| parse_hex_id(&value))
+            };
+            Some((
+                card,
+                GpuMemory {
+                    name: None,
+                    vendor_id: read_id("vendor"),
+                    device_id: read_id("device"),
+                    memory_bytes,
+                    unified_memory: false,
+                    source: GpuMemorySource::Sysfs,
+                },
+            ))
+        })
+        .collect();
+    cards.sort_by(|a, b| a.0.cmp(&b.0));
+    cards.into_iter().map(|(_, memory)| memory).collect()
+}
+
+/// Parses `nvidia-smi --query-gpu=name,memory.total,pci.device_id --format=csv,noheader,nounits`,
+/// memory is in MiB and the pci id packs the device in the high half and the vendor in the low one
+pub fn parse_nvidia_smi(output: &str) -> Vec<GpuMemory> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let [name, memory_mib, pci_id] = fields.as_slice() else {
+                return None;
+            };
+            let memory_mib = memory_mib.parse::<u64>().ok()?;
+            let pci_id = parse_hex_id(pci_id);
+            Some(GpuMemory {
+                name: Some(name.to_string()),
+                vendor_id: pci_id.map(|id| id & 0xffff),
+                device_id: pci_id.map(|id| id >> 16),
+                memory_bytes: memory_mib * 1024 * 1024,
+                unified_memory: false,
+                source: GpuMemorySource::NvidiaSmi,
+            })
+        })
+        .collect()
+}
+
+fn parse_size(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let amount = parts.next()?.parse::<u64>().ok()?;
+    let multiplier = match parts.next()? {
+        "MB" => 1024 * 1024,
+        "GB" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    Some(amount * multiplier)
+}
+
+/// Parses `system_profiler SPDisplaysDataType`, Intel macs list discrete GPUs with their VRAM
+/// while Apple silicon GPUs have none because they use the unified memory
+pub fn parse_system_profiler(output: &str) -> Vec<GpuMemory> {
+    let mut gpus: Vec<GpuMemory> = Vec::new();
+    let mut name: Option<String> = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(model) = line.strip_prefix("Chipset Model:") {
+            name = Some(model.trim().to_string());
+            continue;
+        }
+        let vram = line
+            .strip_prefix("VRAM (Total):")
+            .or_else(|| line.strip_prefix("VRAM (Dynamic, Max):"));
+        if let Some(memory_bytes) = vram.and_then(|vram| parse_size(vram.trim())) {
+            gpus.push(GpuMemory {
+                name: name.take(),
+                vendor_id: None,
+                device_id: None,
+                memory_bytes,
+                unified_memory: false,
+                source: GpuMemorySource::SystemProfiler,
+            });
+        }
+    }
+    gpus
+}
+
+/// Groups `reg query` output by key, returning the value of `value_name` in every key
+fn parse_registry_values(output: &str, value_name: &str) -> Vec<(String, String)> {
+    let mut values = Vec::new();
+    let mut key: Option<&str> = None;
+    for line in output.lines() {
+        if line.starts_with("HKEY_") {
+            key = Some(line.trim());
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        if let (Some(name), Some(_kind), Some(key)) = (fields.next(), fields.next(), key) {
+            if name == value_name {
+                values.push((key.to_string(), fields.collect::<Vec<&str>>().join(" ")));
+            }
+        }
+    }
+    values
+}
+
+/// Parses the display adapters class keys, `qwMemorySize` is a 64 bits value unlike the
+/// `AdapterRAM` WMI property that saturates at 4GB
+pub fn parse_windows_registry(memory_output: &str, description_output: &str) -> Vec<GpuMemory> {
+    let descriptions = parse_registry_values(description_output, "DriverDesc");
+    parse_registry_values(memory_output, "HardwareInformation.qwMemorySize")
+        .into_iter()
+        .filter_map(|(key, memory)| {
+            let memory_bytes = u64::from_str_radix(memory.trim_start_matches("0x"), 16).ok()?;
+            Some(GpuMemory {
+                name: descriptions
+                    .iter()
+                    .find(|(description_key, _)| *description_key == key)
+                    .map(|(_, description)| description.clone()),
+                vendor_id: None,
+                device_id: None,
+                memory_bytes,
+                unified_memory: false,
+                source: GpuMemorySource::WindowsRegistry,
+            })
+        })
+        .collect()
+}
+
+fn detect_nvidia_gpu_memory() -> Vec<GpuMemory> {
+    command_output(
+        "nvidia-smi",
+        &[
+            "--query-gpu=name,memory.total,pci.device_id",
+            "--format=csv,noheader,nounits",
+        ],
+    )
+    .map(|output| parse_nvidia_smi(&output))
+    .unwrap_or_default()
+}
+
+/// Memory of every GPU the OS or vendor tools expose, `total_memory_bytes` is the system RAM
+/// reported for GPUs using unified memory
+pub fn detect_gpu_memory(total_memory_bytes: u64) -> Vec<GpuMemory> {
+    let mut gpus = detect_nvidia_gpu_memory();
+    if cfg!(target_os = "linux") {
+        // The nvidia proprietary driver doesn't expose vram in sysfs so both sources don't overlap
+        gpus.extend(
+            read_sysfs_gpu_memory(Path::new("/sys"))
+                .into_iter()
+                .filter(|gpu| gpu.vendor_id != Some(PCI_VENDOR_NVIDIA)),
+        );
+    } else if cfg!(target_os = "macos") {
+        if cfg!(target_arch = "aarch64") {
+            gpus.push(GpuMemory {
+                name: None,
+                vendor_id: None,
+                device_id: None,
+                memory_bytes: total_memory_bytes,
+                unified_memory: true,
+                source: GpuMemorySource::UnifiedMemory,
+            });
+        } else if let Some(output) = command_output("system_profiler", &["SPDisplaysDataType"]) {
+            gpus.extend(parse_system_profiler(&output));
+        }
+    } else if cfg!(target_os = "windows") && gpus.is_empty() {
+        const DISPLAY_ADAPTERS_KEY: &str =
+            r"HKLM\SYSTEM\CurrentControlSet\Control\Class\{4d36e968-e325-11ce-bfc1-08002be10318}";
+        let memory_output = command_output(
+            "reg",
+            &[
+                "query",
+                DISPLAY_ADAPTERS_KEY,
+                "/s",
+                "/v",
+                "HardwareInformation.qwMemorySize",
+            ],
+        );
+        let description_output = command_output(
+            "reg",
+            &["query", DISPLAY_ADAPTERS_KEY, "/s", "/v", "DriverDesc"],
+        );
+        if let Some(memory_output) = memory_output {
+            gpus.extend(parse_windows_registry(
+                &memory_output,
+                &description_output.unwrap_or_default(),
+            ));
+        }
+    }
+    gpus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a sysfs tree like the one of a machine with an AMD discrete card, an Intel iGPU
+    /// without vram information and a connector folder
+    fn sysfs_fixture() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let files = [
+            (
+                "class/drm/card1/device/mem_info_vram_total",
+                "17163091968\n",
+            ),
+            ("class/drm/card1/device/vendor", "0x1002\n"),
+            ("class/drm/card1/device/device", "0x73bf\n"),
+            (
+                "class/drm/card1-DP-1/device/mem_info_vram_total",
+                "17163091968\n",
+            ),
+            ("class/drm/card0/device/vendor", "0x8086\n"),
+            ("class/drm/card0/device/device", "0x4680\n"),
+            (
+                "class/drm/renderD128/device/mem_info_vram_total",
+                "17163091968\n",
+            ),
+        ];
+        for (path, content) in files {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_read_sysfs_gpu_memory() {
+        let root = sysfs_fixture();
+        let gpus = read_sysfs_gpu_memory(root.path());
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].memory_bytes, 17163091968);
+        assert_eq!(gpus[0].vendor_id, Some(0x1002));
+        assert_eq!(gpus[0].device_id, Some(0x73bf));
+        assert!(gpus[0].matches_adapter("AMD Radeon RX 6800 XT", 0x1002, 0x73bf));
+        assert!(!gpus[0].matches_adapter("Intel(R) UHD Graphics 770", 0x8086, 0x4680));
+    }
+
+    #[test]
+    fn test_read_sysfs_gpu_memory_without_drm() {
+        assert!(read_sysfs_gpu_memory(Path::new("/nonexistent-sysfs")).is_empty());
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi() {
+        let gpus = parse_nvidia_smi(
+            "NVIDIA GeForce RTX 3080, 10240, 0x220610DE\nNVIDIA RTX A2000, 6138, 0x253110DE\n",
+        );
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].memory_bytes, 10240 * 1024 * 1024);
+        assert_eq!(gpus[0].vendor_id, Some(PCI_VENDOR_NVIDIA));
+        assert_eq!(gpus[0].device_id, Some(0x2206));
+        assert!(gpus[1].matches_adapter("NVIDIA RTX A2000", 0, 0));
+    }
+
+    #[test]
+    fn test_parse_system_profiler() {
+        let output = "Graphics/Displays:
+
+    Intel UHD Graphics 630:
+
+      Chipset Model: Intel UHD Graphics 630
+      Type: GPU
+      VRAM (Dynamic, Max): 1536 MB
+
+    AMD Radeon Pro 5500M:
+
+      Chipset Model: AMD Radeon Pro 5500M
+      Type: GPU
+      Bus: PCIe
+      VRAM (Total): 8 GB
+";
+        let gpus = parse_system_profiler(output);
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].memory_bytes, 1536 * 1024 * 1024);
+        assert_eq!(gpus[1].name.as_deref(), Some("AMD Radeon Pro 5500M"));
+        assert_eq!(gpus[1].memory_bytes, 8 * 1024 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_parse_windows_registry() {
+        let key = r"HKEY_LOCAL_MACHINE\SYSTEM\CurrentControlSet\Control\Class\{4d36e968-e325-11ce-bfc1-08002be10318}\0000";
+        let memory_output = format!(
+            "\r\n{}\r\n    HardwareInformation.qwMemorySize    REG_QWORD    0x200000000\r\n\r\nEnd of search: 1 match(es) found.\r\n",
+            key
+        );
+        let description_output = format!(
+            "\r\n{}\r\n    DriverDesc    REG_SZ    AMD Radeon RX 6600\r\n\r\nEnd of search: 1 match(es) found.\r\n",
+            key
+        );
+        let gpus = parse_windows_registry(&memory_output, &description_output);
+        assert_eq!(gpus.len(), 1);
+        assert_eq!(gpus[0].name.as_deref(), Some("AMD Radeon RX 6600"));
+        assert_eq!(gpus[0].memory_bytes, 8 * 1024 * 1024 * 1024);
+    }
+}
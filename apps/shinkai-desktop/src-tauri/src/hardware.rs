@@ -1,30 +1,91 @@
-use serde::Serialize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
 use sysinfo::System;
 use wgpu::DeviceType;
 
-#[derive(Serialize, Debug)]
+use crate::disk_space::{volume_space, VolumeSpace};
+use crate::gpu_memory::{detect_gpu_memory, GpuMemory, GpuMemorySource};
+use crate::hardware_requirements::{
+    evaluate_requirements, RequirementProfile, RequirementsEvaluation,
+};
+use crate::models::model_recommendations::MachineResources;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Hardware {
     pub cpus: usize,
+    #[serde(default)]
+    pub cpu_model: String,
+    /// Instruction sets llama.cpp uses to speed up cpu inference
+    #[serde(default)]
+    pub cpu_features: Vec<String>,
+    /// System RAM in GB
     pub memory: u64,
+    #[serde(default)]
+    pub available_memory_bytes: u64,
+    #[serde(default)]
+    pub total_swap_bytes: u64,
+    #[serde(default)]
+    pub free_swap_bytes: u64,
     pub discrete_gpu: bool,
+    /// Dedicated memory of the biggest GPU in GB, 0 when it couldn't be detected
+    #[serde(default)]
+    pub gpu_memory: u64,
+    /// Empty on headless machines and VMs without GPU drivers
+    #[serde(default)]
+    pub gpus: Vec<GpuAdapter>,
+    /// Volume holding the node storage
+    #[serde(default)]
+    pub storage: Option<VolumeSpace>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GpuAdapter {
+    pub name: String,
+    pub device_type: String,
+    pub backend: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    #[serde(default)]
+    pub driver: String,
+    #[serde(default)]
+    pub driver_info: String,
+    /// None when neither the OS nor vendor tools report the memory of this adapter
+    pub memory_bytes: Option<u64>,
+    pub unified_memory: bool,
+    pub memory_source: Option<GpuMemorySource>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Requirement {
-    cpus: usize,
-    memory: u64,
-    discrete_gpu: bool,
+    pub cpus: usize,
+    /// System RAM in GB
+    pub memory: u64,
+    /// Unified memory GPUs (Apple silicon) count as a discrete GPU
+    #[serde(default)]
+    pub discrete_gpu: bool,
+    /// GPU memory in GB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu_memory: Option<u64>,
+    /// Free space in GB on the node storage volume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub free_disk: Option<u64>,
+    /// Any of these instruction sets is required
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu_features: Vec<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Requirements {
-    still_usable: Requirement,
-    minimum: Requirement,
-    recommended: Requirement,
-    optimal: Requirement,
+    pub still_usable: Requirement,
+    pub minimum: Requirement,
+    pub recommended: Requirement,
+    pub optimal: Requirement,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum RequirementsStatus {
     Unmeet,
     StillUsable,
@@ -33,96 +94,163 @@ pub enum RequirementsStatus {
     Optimal,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct HardwareSummary {
     pub hardware: Hardware,
-    pub requirements: Requirements,
-    pub requirements_status: RequirementsStatus,
+    #[serde(flatten)]
+    pub evaluation: RequirementsEvaluation,
 }
 
-pub const STILL_USABLE_CPUS: usize = 4;
-pub const STILL_USABLE_MEMORY: u64 = 8;
-pub const MIN_CPUS: usize = 4;
-pub const MIN_MEMORY: u64 = 16;
-pub const RECOMMENDED_CPUS: usize = 10;
-pub const RECOMMENDED_MEMORY: u64 = 32;
-pub const REQUIREMENTS: Requirements = Requirements {
-    still_usable: Requirement {
-        cpus: STILL_USABLE_CPUS,
-        memory: STILL_USABLE_MEMORY,
-        discrete_gpu: false,
-    },
-    minimum: Requirement {
-        cpus: MIN_CPUS,
-        memory: MIN_MEMORY,
-        discrete_gpu: false,
-    },
-    recommended: Requirement {
-        cpus: RECOMMENDED_CPUS,
-        memory: RECOMMENDED_MEMORY,
-        discrete_gpu: false,
-    },
-    optimal: Requirement {
-        cpus: RECOMMENDED_CPUS,
-        memory: RECOMMENDED_MEMORY,
-        discrete_gpu: true,
-    },
-};
+/// Adapters from every backend, an empty list when there is no GPU or wgpu fails to load its
+/// drivers (headless machines, VMs)
+fn enumerate_adapters() -> Vec<wgpu::AdapterInfo> {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let instance = wgpu::Instance::default();
+        instance
+            .enumerate_adapters(wgpu::Backends::all())
+            .iter()
+            .map(|adapter| adapter.get_info())
+            .collect()
+    }))
+    .unwrap_or_else(|_| {
+        warn!("gpu adapters enumeration failed, assuming a cpu only machine");
+        vec![]
+    })
+}
 
-fn get_total_vram(adapter: &wgpu::Adapter) -> u64 {
-    if adapter.get_info().device_type != DeviceType::DiscreteGpu {
-        let mut sys = System::new_all();
-        sys.refresh_all();
-        return sys.total_memory() / 1024 / 1024 / 1024;
-    }
+/// Every adapter wgpu finds with the memory detected for it
+fn get_gpu_adapters(adapters: &[wgpu::AdapterInfo], gpu_memory: &[GpuMemory]) -> Vec<GpuAdapter> {
+    adapters
+        .iter()
+        .map(|info| {
+            let memory = gpu_memory.iter().find(|memory| {
+                memory.matches_adapter(&info.name, info.vendor, info.device)
+                    || (memory.unified_memory && info.device_type == DeviceType::IntegratedGpu)
+            });
+            GpuAdapter {
+                name: info.name.clone(),
+                device_type: format!("{:?}", info.device_type),
+                backend: format!("{:?}", info.backend),
+                vendor_id: info.vendor,
+                device_id: info.device,
+                driver: info.driver.clone(),
+                driver_info: info.driver_info.clone(),
+                memory_bytes: memory.map(|memory| memory.memory_bytes),
+                unified_memory: memory.is_some_and(|memory| memory.unified_memory),
+                memory_source: memory.map(|memory| memory.source),
+            }
+        })
+        .collect()
+}
 
-    let features = adapter.features();
-    if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
-        let limits = adapter.limits();
-        limits.max_texture_dimension_1d as u64 * limits.max_texture_dimension_2d as u64 * 4
-    } else {
-        0
+fn get_cpu_features() -> Vec<String> {
+    let mut features: Vec<&str> = Vec::new();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        let detected = [
+            ("sse4.2", is_x86_feature_detected!("sse4.2")),
+            ("avx", is_x86_feature_detected!("avx")),
+            ("avx2", is_x86_feature_detected!("avx2")),
+            ("fma", is_x86_feature_detected!("fma")),
+            ("f16c", is_x86_feature_detected!("f16c")),
+            ("avx512f", is_x86_feature_detected!("avx512f")),
+            ("avx512vnni", is_x86_feature_detected!("avx512vnni")),
+        ];
+        features.extend(detected.iter().filter(|(_, on)| *on).map(|(name, _)| *name));
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        let detected = [
+            ("neon", std::arch::is_aarch64_feature_detected!("neon")),
+            (
+                "dotprod",
+                std::arch::is_aarch64_feature_detected!("dotprod"),
+            ),
+            ("fp16", std::arch::is_aarch64_feature_detected!("fp16")),
+            ("sve", std::arch::is_aarch64_feature_detected!("sve")),
+        ];
+        features.extend(detected.iter().filter(|(_, on)| *on).map(|(name, _)| *name));
     }
+    features
+        .into_iter()
+        .map(|feature| feature.to_string())
+        .collect()
 }
 
-pub fn hardware_get_summary() -> HardwareSummary {
-    let instance = wgpu::Instance::default();
-    let adapters = instance.enumerate_adapters(wgpu::Backends::all());
-    let adapter = adapters
+/// Biggest dedicated GPU memory in bytes, GPUs sharing the system RAM don't count
+fn get_dedicated_gpu_memory(gpu_memory: &[GpuMemory]) -> u64 {
+    gpu_memory
         .iter()
-        .find(|adapter| adapter.get_info().device_type == wgpu::DeviceType::DiscreteGpu)
-        .unwrap_or_else(|| adapters.first().expect("no gpu adapter found"));
+        .filter(|memory| !memory.unified_memory)
+        .map(|memory| memory.memory_bytes)
+        .max()
+        .unwrap_or_default()
+}
 
-    let discrete_gpu = adapter.get_info().device_type == DeviceType::DiscreteGpu;
-    let is_macos = cfg!(target_os = "macos");
+/// Never fails, machines without GPU get a cpu only hardware. `storage_path` is the node storage,
+/// its volume free space is reported when given.
+pub fn hardware_get_hardware(storage_path: Option<&Path>) -> Hardware {
+    let adapters = enumerate_adapters();
+    let discrete_gpu = adapters
+        .iter()
+        .any(|adapter| adapter.device_type == DeviceType::DiscreteGpu);
 
     let mut sys = System::new_all();
     sys.refresh_all();
 
-    // v memory in GB
-    let memory = get_total_vram(adapter);
-    let cpus = sys.cpus().len();
-
-    let requirement_status;
-    if is_macos || (cpus >= RECOMMENDED_CPUS && memory >= RECOMMENDED_MEMORY && discrete_gpu) {
-        requirement_status = RequirementsStatus::Optimal;
-    } else if cpus >= RECOMMENDED_CPUS && memory >= RECOMMENDED_MEMORY {
-        requirement_status = RequirementsStatus::Recommended;
-    } else if cpus >= MIN_CPUS && memory >= MIN_MEMORY {
-        requirement_status = RequirementsStatus::Minimum;
-    } else if cpus >= STILL_USABLE_CPUS && memory >= STILL_USABLE_MEMORY {
-        requirement_status = RequirementsStatus::StillUsable;
-    } else {
-        requirement_status = RequirementsStatus::Unmeet;
+    let gpu_memory = detect_gpu_memory(sys.total_memory());
+    let gpus = get_gpu_adapters(&adapters, &gpu_memory);
+    let cpu_model = sys
+        .cpus()
+        .first()
+        .map(|cpu| cpu.brand().trim().to_string())
+        .unwrap_or_default();
+    let storage = storage_path.and_then(|path| {
+        volume_space(path)
+            .inspect_err(|e| warn!("failed to read storage volume space: {}", e))
+            .ok()
+    });
+
+    Hardware {
+        cpus: sys.cpus().len(),
+        cpu_model,
+        cpu_features: get_cpu_features(),
+        // memory in GB
+        memory: sys.total_memory() / 1024 / 1024 / 1024,
+        available_memory_bytes: sys.available_memory(),
+        total_swap_bytes: sys.total_swap(),
+        free_swap_bytes: sys.free_swap(),
+        discrete_gpu,
+        gpu_memory: get_dedicated_gpu_memory(&gpu_memory) / 1024 / 1024 / 1024,
+        gpus,
+        storage,
     }
+}
 
+/// Hardware evaluated against the tiers of a requirement profile
+pub fn hardware_get_summary(
+    storage_path: Option<&Path>,
+    profile: &RequirementProfile,
+) -> HardwareSummary {
+    let hardware = hardware_get_hardware(storage_path);
+    let evaluation = evaluate_requirements(&hardware, profile);
     HardwareSummary {
-        hardware: Hardware {
-            cpus,
-            memory,
-            discrete_gpu,
-        },
-        requirements: REQUIREMENTS,
-        requirements_status: requirement_status,
+        hardware,
+        evaluation,
+    }
+}
+
+/// Memory figures in bytes used to decide which models fit this machine
+pub fn hardware_get_machine_resources() -> MachineResources {
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    let gpu_memory = detect_gpu_memory(sys.total_memory());
+
+    MachineResources {
+        cpus: sys.cpus().len(),
+        total_memory_bytes: sys.total_memory(),
+        available_memory_bytes: sys.available_memory(),
+        gpu_memory_bytes: get_dedicated_gpu_memory(&gpu_memory),
+        unified_memory: gpu_memory.iter().any(|memory| memory.unified_memory),
     }
 }
@@ -0,0 +1,351 @@
+use std::fs;
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::hardware::{Hardware, Requirement, Requirements, RequirementsStatus};
+
+const BUNDLED_PROFILES: &str = include_str!("../requirement-profiles.json");
+pub const PROFILES_OVERRIDE_FILE_NAME: &str = "requirement-profiles.json";
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequirementProfile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub tiers: Requirements,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequirementProfiles {
+    pub default_profile: String,
+    pub profiles: Vec<RequirementProfile>,
+}
+
+#[derive(Deserialize, Default)]
+struct RequirementProfilesOverride {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: Vec<RequirementProfile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequirementCriterion {
+    Cpu,
+    Ram,
+    Gpu,
+    Disk,
+    InstructionSet,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DowngradeReason {
+    /// Tier that wasn't reached because of this criterion
+    pub tier: RequirementsStatus,
+    pub criterion: RequirementCriterion,
+    pub required: String,
+    pub actual: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RequirementsEvaluation {
+    #[serde(default)]
+    pub requirements_profile: String,
+    pub requirements: Requirements,
+    pub requirements_status: RequirementsStatus,
+    /// Unmet criteria of every tier above the reached one
+    #[serde(default)]
+    pub downgrade_reasons: Vec<DowngradeReason>,
+}
+
+impl RequirementProfiles {
+    /// Bundled profiles, `override_path` can replace profiles with the same id, add new ones and
+    /// change the default one. An invalid override file is ignored.
+    pub fn load(override_path: Option<&Path>) -> Self {
+        let mut profiles: RequirementProfiles =
+            serde_json::from_str(BUNDLED_PROFILES).expect("invalid bundled requirement profiles");
+        let Some(override_path) = override_path.filter(|path| path.exists()) else {
+            return profiles;
+        };
+        let requirement_override = fs::read_to_string(override_path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                serde_json::from_str::<RequirementProfilesOverride>(&content)
+                    .map_err(|e| e.to_string())
+            });
+        match requirement_override {
+            Ok(requirement_override) => profiles.merge(requirement_override),
+            Err(e) => warn!(
+                "ignoring invalid requirement profiles {}: {}",
+                override_path.display(),
+                e
+            ),
+        }
+        profiles
+    }
+
+    fn merge(&mut self, requirement_override: RequirementProfilesOverride) {
+        for profile in requirement_override.profiles {
+            match self.profiles.iter_mut().find(|p| p.id == profile.id) {
+                Some(existing) => *existing = profile,
+                None => self.profiles.push(profile),
+            }
+        }
+        if let Some(default_profile) = requirement_override.default_profile {
+            if self.get(&default_profile).is_some() {
+                self.default_profile = default_profile;
+            } else {
+                warn!(
+                    "ignoring unknown default requirement profile {}",
+                    default_profile
+                );
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&RequirementProfile> {
+        self.profiles.iter().find(|profile| profile.id == id)
+    }
+
+    pub fn default_profile(&self) -> &RequirementProfile {
+        self.get(&self.default_profile)
+            .or(self.profiles.first())
+            .expect("requirement profiles can't be empty")
+    }
+}
+
+/// Memory the GPU can use, unified memory GPUs (Apple silicon) count as having a GPU
+fn gpu_memory_bytes(hardware: &Hardware) -> u64 {
+    hardware
+        .gpus
+        .iter()
+        .filter_map(|gpu| gpu.memory_bytes)
+        .max()
+        .unwrap_or_default()
+}
+
+fn has_gpu(hardware: &Hardware) -> bool {
+    hardware.discrete_gpu || hardware.gpus.iter().any(|gpu| gpu.unified_memory)
+}
+
+fn unmet_criteria(
+    hardware: &Hardware,
+    requirement: &Requirement,
+    tier: RequirementsStatus,
+) -> Vec<DowngradeReason> {
+    let mut reasons = Vec::new();
+    let mut unmet = |criterion, required: String, actual: String| {
+        reasons.push(DowngradeReason {
+            tier: tier.clone(),
+            criterion,
+            required,
+            actual,
+        })
+    };
+    if hardware.cpus < requirement.cpus {
+        unmet(
+            RequirementCriterion::Cpu,
+            format!("{} cpus", requirement.cpus),
+            format!("{} cpus", hardware.cpus),
+        );
+    }
+    if hardware.memory < requirement.memory {
+        unmet(
+            RequirementCriterion::Ram,
+            format!("{}GB RAM", requirement.memory),
+            format!("{}GB RAM", hardware.memory),
+        );
+    }
+    if requirement.discrete_gpu && !has_gpu(hardware) {
+        unmet(
+            RequirementCriterion::Gpu,
+            "discrete or unified memory GPU".to_string(),
+            "no GPU".to_string(),
+        );
+    }
+    if let Some(gpu_memory) = requirement.gpu_memory {
+        let actual_gpu_memory = gpu_memory_bytes(hardware) / GB;
+        if actual_gpu_memory < gpu_memory {
+            unmet(
+                RequirementCriterion::Gpu,
+                format!("{}GB GPU memory", gpu_memory),
+                format!("{}GB GPU memory", actual_gpu_memory),
+            );
+        }
+    }
+    // Unknown storage doesn't downgrade, it happens before the node storage is created
+    if let (Some(free_disk), Some(storage)) = (requirement.free_disk, &hardware.storage) {
+        let available = storage.available_bytes / GB;
+        if available < free_disk {
+            unmet(
+                RequirementCriterion::Disk,
+                format!("{}GB free disk", free_disk),
+                format!("{}GB free disk", available),
+            );
+        }
+    }
+    // Any of the listed instruction sets is enough, profiles list the x86 and arm alternatives
+    if !requirement.cpu_features.is_empty()
+        && !requirement
+            .cpu_features
+            .iter()
+            .any(|feature| hardware.cpu_features.contains(feature))
+    {
+        unmet(
+            RequirementCriterion::InstructionSet,
+            format!("any of {}", requirement.cpu_features.join(", ")),
+            if hardware.cpu_features.is_empty() {
+                "none".to_string()
+            } else {
+                hardware.cpu_features.join(", ")
+            },
+        );
+    }
+    reasons
+}
+
+/// The reached tier is the highest one whose criteria are all met
+pub fn evaluate_requirements(
+    hardware: &Hardware,
+    profile: &RequirementProfile,
+) -> RequirementsEvaluation {
+    let tiers = [
+        (RequirementsStatus::Optimal, &profile.tiers.optimal),
+        (RequirementsStatus::Recommended, &profile.tiers.recommended),
+        (RequirementsStatus::Minimum, &profile.tiers.minimum),
+        (RequirementsStatus::StillUsable, &profile.tiers.still_usable),
+    ];
+    let mut requirements_status = RequirementsStatus::Unmeet;
+    let mut downgrade_reasons = Vec::new();
+    for (tier, requirement) in tiers {
+        let reasons = unmet_criteria(hardware, requirement, tier.clone());
+        if reasons.is_empty() {
+            requirements_status = tier;
+            break;
+        }
+        downgrade_reasons.extend(reasons);
+    }
+    RequirementsEvaluation {
+        requirements_profile: profile.id.clone(),
+        requirements: profile.tiers.clone(),
+        requirements_status,
+        downgrade_reasons,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::GpuAdapter;
+
+    fn hardware(cpus: usize, memory: u64, cpu_features: &[&str]) -> Hardware {
+        Hardware {
+            cpus,
+            cpu_model: "test cpu".to_string(),
+            cpu_features: cpu_features.iter().map(|f| f.to_string()).collect(),
+            memory,
+            available_memory_bytes: memory * GB / 2,
+            total_swap_bytes: 0,
+            free_swap_bytes: 0,
+            discrete_gpu: false,
+            gpu_memory: 0,
+            gpus: vec![],
+            storage: None,
+        }
+    }
+
+    #[test]
+    fn test_bundled_profiles_are_valid() {
+        let profiles = RequirementProfiles::load(None);
+        assert_eq!(profiles.default_profile().id, "shinkai");
+        assert!(profiles.get("local-embeddings").is_some());
+        assert!(profiles.get("chat-7b").is_some());
+    }
+
+    #[test]
+    fn test_downgrade_reasons_explain_missing_gpu() {
+        let profiles = RequirementProfiles::load(None);
+        let evaluation =
+            evaluate_requirements(&hardware(12, 32, &["avx2"]), profiles.default_profile());
+        assert!(matches!(
+            evaluation.requirements_status,
+            RequirementsStatus::Recommended
+        ));
+        assert_eq!(evaluation.downgrade_reasons.len(), 1);
+        assert_eq!(
+            evaluation.downgrade_reasons[0].criterion,
+            RequirementCriterion::Gpu
+        );
+    }
+
+    #[test]
+    fn test_unified_memory_counts_as_gpu() {
+        let profiles = RequirementProfiles::load(None);
+        let mut apple_silicon = hardware(10, 32, &["neon"]);
+        apple_silicon.gpus.push(GpuAdapter {
+            name: "Apple M2 Pro".to_string(),
+            device_type: "IntegratedGpu".to_string(),
+            backend: "Metal".to_string(),
+            vendor_id: 0,
+            device_id: 0,
+            driver: String::new(),
+            driver_info: String::new(),
+            memory_bytes: Some(32 * GB),
+            unified_memory: true,
+            memory_source: None,
+        });
+        let evaluation = evaluate_requirements(&apple_silicon, profiles.get("chat-7b").unwrap());
+        assert!(matches!(
+            evaluation.requirements_status,
+            RequirementsStatus::Optimal
+        ));
+        assert!(evaluation.downgrade_reasons.is_empty());
+    }
+
+    #[test]
+    fn test_missing_instruction_set_downgrades() {
+        let profiles = RequirementProfiles::load(None);
+        let evaluation = evaluate_requirements(
+            &hardware(8, 16, &["sse4.2"]),
+            profiles.get("chat-7b").unwrap(),
+        );
+        assert!(matches!(
+            evaluation.requirements_status,
+            RequirementsStatus::StillUsable
+        ));
+        assert!(evaluation.downgrade_reasons.iter().any(|reason| matches!(
+            reason.tier,
+            RequirementsStatus::Minimum
+        ) && reason.criterion
+            == RequirementCriterion::InstructionSet));
+    }
+
+    #[test]
+    fn test_override_replaces_and_adds_profiles() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let override_path = temp_dir.path().join(PROFILES_OVERRIDE_FILE_NAME);
+        fs::write(
+            &override_path,
+            r#"{"default_profile":"team","profiles":[{"id":"team","name":"Team","tiers":{
+                "still_usable":{"cpus":1,"memory":1},"minimum":{"cpus":1,"memory":1},
+                "recommended":{"cpus":1,"memory":1},"optimal":{"cpus":64,"memory":1}}}]}"#,
+        )
+        .unwrap();
+        let profiles = RequirementProfiles::load(Some(&override_path));
+        assert_eq!(profiles.default_profile().id, "team");
+        assert!(profiles.get("shinkai").is_some());
+        let evaluation = evaluate_requirements(&hardware(8, 16, &[]), profiles.default_profile());
+        assert!(matches!(
+            evaluation.requirements_status,
+            RequirementsStatus::Recommended
+        ));
+        assert_eq!(
+            evaluation.downgrade_reasons[0].required,
+            "64 cpus".to_string()
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+
+/// Wraps a reader to compute its sha256 and report how many bytes went through it
+pub struct HashingReader<R: Read, F: FnMut(u64)> {
+    inner: R,
+    hasher: Sha256,
+    on_read: F,
+}
+
+impl<R: Read, F: FnMut(u64)> HashingReader<R, F> {
+    pub fn new(inner: R, on_read: F) -> Self {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+            on_read,
+        }
+    }
+
+    pub fn digest(self) -> String {
+        format!("sha256:{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for HashingReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        (self.on_read)(read as u64);
+        Ok(read)
+    }
+}
+
+/// Maps processed bytes to a 0..=100 percentage and only notifies when it changes
+pub struct ProgressTracker<F: FnMut(u32)> {
+    total: u64,
+    processed: u64,
+    last_progress: Option<u32>,
+    on_progress: F,
+}
+
+impl<F: FnMut(u32)> ProgressTracker<F> {
+    pub fn new(total: u64, on_progress: F) -> Self {
+        ProgressTracker {
+            total,
+            processed: 0,
+            last_progress: None,
+            on_progress,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.processed += bytes;
+        let progress = (self.processed.min(self.total) * 100)
+            .checked_div(self.total)
+            .unwrap_or(100) as u32;
+        if self.last_progress != Some(progress) {
+            self.last_progress = Some(progress);
+            (self.on_progress)(progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashing_reader_and_progress() {
+        let data = b"shinkai".repeat(10);
+        let mut progress = vec![];
+        let mut tracker = ProgressTracker::new(data.len() as u64, |p| progress.push(p));
+        let mut reader = HashingReader::new(&data[..], |read| tracker.advance(read));
+        let mut buf = [0u8; 7];
+        while reader.read(&mut buf).unwrap() > 0 {}
+        let digest = reader.digest();
+        assert_eq!(digest, format!("sha256:{:x}", Sha256::digest(&data)));
+        assert_eq!(progress, (1..=10).map(|step| step * 10).collect::<Vec<_>>());
+
+        let mut empty = vec![];
+        ProgressTracker::new(0, |p| empty.push(p)).advance(0);
+        assert_eq!(empty, vec![100]);
+    }
+}
@@ -1,4 +1,7 @@
+pub mod io_progress;
+pub mod node_storage;
 pub mod ollama_api;
 pub mod process_handlers;
+pub mod secrets_store;
 pub mod shinkai_node_manager;
 pub mod shinkai_node_options;
@@ -0,0 +1,292 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const BACKUP_FILE_PREFIX: &str = "shinkai-storage-";
+const BACKUP_FILE_EXTENSION: &str = ".tar.gz";
+const BACKUP_FILE_DATE_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackupFrequency {
+    Daily,
+    Weekly,
+}
+
+impl BackupFrequency {
+    fn period(&self) -> chrono::Duration {
+        match self {
+            BackupFrequency::Daily => chrono::Duration::days(1),
+            BackupFrequency::Weekly => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BackupSchedule {
+    pub frequency: BackupFrequency,
+    pub backups_path: String,
+    /// Newest backup of each of the last `keep_daily` days is kept
+    pub keep_daily: u32,
+    /// Newest backup of each of the last `keep_weekly` weeks is kept
+    pub keep_weekly: u32,
+}
+
+impl BackupSchedule {
+    pub fn validate(&self, storage_path: &Path) -> Result<(), String> {
+        let backups_path = Path::new(&self.backups_path);
+        if !backups_path.is_absolute() {
+            return Err("backups folder must be an absolute path".to_string());
+        }
+        if backups_path.starts_with(storage_path) {
+            return Err("backups can't be saved inside the storage folder".to_string());
+        }
+        if self.keep_daily == 0 && self.keep_weekly == 0 {
+            return Err("retention must keep at least one backup".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Reads the schedule saved in `path`, None when there isn't one or it can't be parsed
+pub fn load_schedule(path: &Path) -> Option<BackupSchedule> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content)
+        .map_err(|e| log::warn!("ignoring invalid backup schedule {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Saves `schedule` in `path`, the file is removed when scheduled backups are disabled
+pub fn save_schedule(path: &Path, schedule: Option<&BackupSchedule>) -> Result<(), String> {
+    match schedule {
+        Some(schedule) => {
+            let content = serde_json::to_string_pretty(schedule)
+                .map_err(|e| format!("failed to serialize backup schedule: {}", e))?;
+            fs::write(path, content).map_err(|e| format!("failed to save backup schedule: {}", e))
+        }
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("failed to remove backup schedule: {}", e))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status")]
+pub enum BackupIntegrity {
+    Unverified,
+    Valid,
+    Corrupted { error: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageBackupEntry {
+    pub path: String,
+    pub file_name: String,
+    pub created_at: String,
+    pub size: u64,
+    pub integrity: BackupIntegrity,
+}
+
+/// A backup file found in the backups folder
+#[derive(Clone, Debug)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+impl BackupFile {
+    pub fn entry(&self, integrity: BackupIntegrity) -> StorageBackupEntry {
+        StorageBackupEntry {
+            path: self.path.to_string_lossy().to_string(),
+            file_name: self
+                .path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            created_at: self.created_at.to_rfc3339(),
+            size: self.size,
+            integrity,
+        }
+    }
+}
+
+pub fn backup_file_name(created_at: DateTime<Utc>) -> String {
+    format!(
+        "{}{}{}",
+        BACKUP_FILE_PREFIX,
+        created_at.format(BACKUP_FILE_DATE_FORMAT),
+        BACKUP_FILE_EXTENSION
+    )
+}
+
+fn parse_backup_file_name(file_name: &str) -> Option<DateTime<Utc>> {
+    let date = file_name
+        .strip_prefix(BACKUP_FILE_PREFIX)?
+        .strip_suffix(BACKUP_FILE_EXTENSION)?;
+    NaiveDateTime::parse_from_str(date, BACKUP_FILE_DATE_FORMAT)
+        .ok()
+        .map(|date| date.and_utc())
+}
+
+/// Backups made by the scheduler in `backups_path`, newest first. Other files are ignored.
+pub fn list_backup_files(backups_path: &Path) -> Result<Vec<BackupFile>, String> {
+    let entries = match fs::read_dir(backups_path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("failed to read backups folder: {}", e)),
+    };
+    let mut backups: Vec<BackupFile> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let created_at = parse_backup_file_name(&entry.file_name().to_string_lossy())?;
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            Some(BackupFile {
+                path: entry.path(),
+                created_at,
+                size: metadata.len(),
+            })
+        })
+        .collect();
+    backups.sort_by_key(|backup| Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Deletes a backup that failed verification, left in the folder it would be taken for the last
+/// backup and the retention rules could keep it instead of a good one
+pub fn remove_corrupted_backup(backup: &BackupFile) -> Result<(), String> {
+    match fs::remove_file(&backup.path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!(
+            "failed to remove corrupted backup {}: {}",
+            backup.path.display(),
+            e
+        )),
+    }
+}
+
+/// An hour of slack so a backup that ran a bit late doesn't push every following one
+pub fn is_backup_due(
+    last_backup: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    frequency: BackupFrequency,
+) -> bool {
+    match last_backup {
+        Some(last_backup) => now - last_backup >= frequency.period() - chrono::Duration::hours(1),
+        None => true,
+    }
+}
+
+/// Indexes of `created_at` the retention rules don't keep. The newest backup is always kept.
+pub fn backups_to_prune(
+    created_at: &[DateTime<Utc>],
+    keep_daily: u32,
+    keep_weekly: u32,
+) -> Vec<usize> {
+    let mut newest_first: Vec<usize> = (0..created_at.len()).collect();
+    newest_first.sort_by(|a, b| created_at[*b].cmp(&created_at[*a]));
+
+    let mut kept: HashSet<usize> = newest_first.first().copied().into_iter().collect();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for index in newest_first.iter() {
+        let date = created_at[*index].date_naive();
+        if days.len() < keep_daily as usize && days.insert(date) {
+            kept.insert(*index);
+        }
+        let week = date.iso_week();
+        if weeks.len() < keep_weekly as usize && weeks.insert((week.year(), week.week())) {
+            kept.insert(*index);
+        }
+    }
+    (0..created_at.len())
+        .filter(|index| !kept.contains(index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_retention_keeps_daily_and_weekly_backups() {
+        // One backup per day for 30 days plus an extra one on the last day
+        let mut created_at: Vec<DateTime<Utc>> = (0..30)
+            .map(|day| {
+                Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap() + chrono::Duration::days(day)
+            })
+            .collect();
+        created_at.push(Utc.with_ymd_and_hms(2024, 6, 30, 1, 0, 0).unwrap());
+
+        let pruned = backups_to_prune(&created_at, 7, 4);
+        let kept: Vec<DateTime<Utc>> = (0..created_at.len())
+            .filter(|index| !pruned.contains(index))
+            .map(|index| created_at[index])
+            .collect();
+        // 7 days from June 24 to 30, the newest of each of the 4 last weeks overlaps with June 30
+        // and adds June 23, 16 and 9 (Sundays)
+        assert_eq!(kept.len(), 10);
+        assert!(kept.contains(&Utc.with_ymd_and_hms(2024, 6, 30, 3, 0, 0).unwrap()));
+        assert!(!kept.contains(&Utc.with_ymd_and_hms(2024, 6, 30, 1, 0, 0).unwrap()));
+        assert!(kept.contains(&Utc.with_ymd_and_hms(2024, 6, 9, 3, 0, 0).unwrap()));
+        assert!(!kept.contains(&Utc.with_ymd_and_hms(2024, 6, 8, 3, 0, 0).unwrap()));
+
+        assert!(backups_to_prune(&created_at[..1], 0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_backup_file_names_round_trip_and_due_dates() {
+        let created_at = Utc.with_ymd_and_hms(2024, 6, 1, 3, 4, 5).unwrap();
+        let file_name = backup_file_name(created_at);
+        assert_eq!(file_name, "shinkai-storage-20240601-030405.tar.gz");
+        assert_eq!(parse_backup_file_name(&file_name), Some(created_at));
+        assert_eq!(parse_backup_file_name("notes.tar.gz"), None);
+
+        let frequency = BackupFrequency::Daily;
+        assert!(is_backup_due(None, created_at, frequency));
+        let next_day = created_at + chrono::Duration::hours(23);
+        assert!(is_backup_due(Some(created_at), next_day, frequency));
+        let same_day = created_at + chrono::Duration::hours(12);
+        assert!(!is_backup_due(Some(created_at), same_day, frequency));
+    }
+
+    #[test]
+    fn test_corrupted_backup_is_not_the_last_backup() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let good_at = Utc.with_ymd_and_hms(2024, 6, 1, 3, 0, 0).unwrap();
+        let corrupted_at = good_at + chrono::Duration::days(1);
+        for created_at in [good_at, corrupted_at] {
+            fs::write(
+                temp_dir.path().join(backup_file_name(created_at)),
+                b"backup",
+            )
+            .unwrap();
+        }
+        let backups = list_backup_files(temp_dir.path()).unwrap();
+        assert_eq!(backups[0].created_at, corrupted_at);
+
+        remove_corrupted_backup(&backups[0]).unwrap();
+        remove_corrupted_backup(&backups[0]).unwrap();
+
+        let backups = list_backup_files(temp_dir.path()).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].created_at, good_at);
+        let now = corrupted_at + chrono::Duration::hours(1);
+        assert!(is_backup_due(
+            backups.first().map(|backup| backup.created_at),
+            now,
+            BackupFrequency::Daily
+        ));
+    }
+}
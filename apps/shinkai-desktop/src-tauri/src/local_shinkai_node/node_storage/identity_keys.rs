@@ -0,0 +1,432 @@
+use std::fs;
+use std::path::Path;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+const IDENTITY_EXPORT_FORMAT_VERSION: u32 = 1;
+const IDENTITY_NAME_PREFIX: &str = "GLOBAL_IDENTITY_NAME=";
+const MIN_PASSPHRASE_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// Argon2id cost, saved in the export so it can be raised without breaking older files
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KdfParams {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: String,
+}
+
+impl KdfParams {
+    pub(crate) fn new(memory_kib: u32, iterations: u32) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        KdfParams {
+            memory_kib,
+            iterations,
+            parallelism: 1,
+            salt: BASE64.encode(salt),
+        }
+    }
+
+    pub(crate) fn derive_key(&self, passphrase: &str) -> Result<[u8; 32], String> {
+        let salt = BASE64
+            .decode(&self.salt)
+            .map_err(|_| "invalid salt in identity export".to_string())?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| format!("invalid key derivation parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| format!("failed to derive key from passphrase: {}", e))?;
+        Ok(key)
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::new(64 * 1024, 3)
+    }
+}
+
+/// Everything outside `ciphertext`, it's authenticated along with it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct IdentityExportHeader {
+    format_version: u32,
+    created_at: String,
+    identity_name: Option<String>,
+    fingerprint: String,
+    kdf: KdfParams,
+    nonce: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct IdentityExport {
+    #[serde(flatten)]
+    header: IdentityExportHeader,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct IdentityKeyFile {
+    name: String,
+    content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IdentityKeys {
+    pub identity_name: Option<String>,
+    /// Hash of the key files, it's the same on every machine holding this identity
+    pub fingerprint: String,
+}
+
+/// Key files at the top of the storage, the same ones a storage reset preserves
+fn read_key_files(storage_path: &Path) -> Result<Vec<IdentityKeyFile>, String> {
+    let entries = match fs::read_dir(storage_path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("failed to read storage folder: {}", e)),
+    };
+    let mut key_files = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.ends_with(".secret") || !entry.path().is_file() {
+            continue;
+        }
+        let content = fs::read(entry.path())
+            .map_err(|e| format!("failed to read key file {}: {}", name, e))?;
+        key_files.push(IdentityKeyFile {
+            name,
+            content: BASE64.encode(content),
+        });
+    }
+    key_files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(key_files)
+}
+
+fn identity_keys(key_files: &[IdentityKeyFile]) -> IdentityKeys {
+    let mut hasher = blake3::Hasher::new();
+    for key_file in key_files {
+        hasher.update(key_file.name.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(key_file.content.as_bytes());
+        hasher.update(&[0]);
+    }
+    let fingerprint = hex::encode(&hasher.finalize().as_bytes()[..16]);
+    let identity_name = key_files
+        .iter()
+        .filter_map(|key_file| BASE64.decode(&key_file.content).ok())
+        .filter_map(|content| String::from_utf8(content).ok())
+        .find_map(|content| {
+            content.lines().find_map(|line| {
+                line.strip_prefix(IDENTITY_NAME_PREFIX)
+                    .map(|identity_name| identity_name.trim().to_string())
+            })
+        })
+        .filter(|identity_name| !identity_name.is_empty());
+    IdentityKeys {
+        identity_name,
+        fingerprint,
+    }
+}
+
+fn header_bytes(header: &IdentityExportHeader) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(header).map_err(|e| format!("failed to serialize identity export: {}", e))
+}
+
+fn encrypt_identity(
+    key_files: &[IdentityKeyFile],
+    passphrase: &str,
+    kdf: KdfParams,
+) -> Result<IdentityExport, String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!(
+            "passphrase must have at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    let keys = identity_keys(key_files);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let header = IdentityExportHeader {
+        format_version: IDENTITY_EXPORT_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        identity_name: keys.identity_name,
+        fingerprint: keys.fingerprint,
+        nonce: BASE64.encode(nonce),
+        kdf,
+    };
+    let plaintext = serde_json::to_vec(key_files)
+        .map_err(|e| format!("failed to serialize identity keys: {}", e))?;
+    let cipher = XChaCha20Poly1305::new(&header.kdf.derive_key(passphrase)?.into());
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: &plaintext,
+                aad: &header_bytes(&header)?,
+            },
+        )
+        .map_err(|_| "failed to encrypt identity keys".to_string())?;
+    Ok(IdentityExport {
+        header,
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt_identity(
+    export: &IdentityExport,
+    passphrase: &str,
+) -> Result<Vec<IdentityKeyFile>, String> {
+    let header = &export.header;
+    let nonce = BASE64
+        .decode(&header.nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == 24)
+        .ok_or_else(|| "invalid nonce in identity export".to_string())?;
+    let ciphertext = BASE64
+        .decode(&export.ciphertext)
+        .map_err(|_| "invalid identity export".to_string())?;
+    let cipher = XChaCha20Poly1305::new(&header.kdf.derive_key(passphrase)?.into());
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &header_bytes(header)?,
+            },
+        )
+        .map_err(|_| "wrong passphrase or the identity export was modified".to_string())?;
+    let key_files: Vec<IdentityKeyFile> = serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("invalid identity keys in export: {}", e))?;
+    if identity_keys(&key_files).fingerprint != header.fingerprint {
+        return Err("identity keys don't match the export fingerprint".to_string());
+    }
+    Ok(key_files)
+}
+
+fn read_export(export_path: &Path) -> Result<IdentityExport, String> {
+    let content = fs::read_to_string(export_path)
+        .map_err(|e| format!("failed to read identity export: {}", e))?;
+    let export: IdentityExport =
+        serde_json::from_str(&content).map_err(|e| format!("invalid identity export: {}", e))?;
+    // The parameters are read before the passphrase is checked, a crafted file could ask for
+    // more memory than the machine has
+    if export.header.kdf.memory_kib > MAX_KDF_MEMORY_KIB {
+        return Err("identity export asks for too much memory to derive its key".to_string());
+    }
+    if export.header.format_version > IDENTITY_EXPORT_FORMAT_VERSION {
+        return Err(format!(
+            "identity export format {} isn't supported, update the app to import it",
+            export.header.format_version
+        ));
+    }
+    Ok(export)
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(content).and_then(|_| file.sync_all()))
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+/// Saves the storage key files into `export_path` encrypted with `passphrase`
+pub fn export_identity(
+    storage_path: &Path,
+    export_path: &Path,
+    passphrase: &str,
+) -> Result<IdentityKeys, String> {
+    let key_files = read_key_files(storage_path)?;
+    if key_files.is_empty() {
+        return Err("node storage has no identity keys to export".to_string());
+    }
+    let export = encrypt_identity(&key_files, passphrase, KdfParams::default())?;
+    let content = serde_json::to_vec_pretty(&export)
+        .map_err(|e| format!("failed to serialize identity export: {}", e))?;
+    let partial_path = export_path.with_extension("partial");
+    write_private(&partial_path, &content)?;
+    fs::rename(&partial_path, export_path).map_err(|e| {
+        let _ = fs::remove_file(&partial_path);
+        format!("failed to save identity export: {}", e)
+    })?;
+    info!(
+        "exported identity {} to {}",
+        export.header.fingerprint,
+        export_path.display()
+    );
+    Ok(IdentityKeys {
+        identity_name: export.header.identity_name,
+        fingerprint: export.header.fingerprint,
+    })
+}
+
+/// Identity in an export, it's readable without the passphrase
+pub fn inspect_identity_export(export_path: &Path) -> Result<IdentityKeys, String> {
+    let header = read_export(export_path)?.header;
+    Ok(IdentityKeys {
+        identity_name: header.identity_name,
+        fingerprint: header.fingerprint,
+    })
+}
+
+/// Writes the exported key files into `storage_path`. A storage that already holds another
+/// identity or node data is only replaced with `overwrite`, its key files are kept aside.
+pub fn import_identity(
+    export_path: &Path,
+    storage_path: &Path,
+    passphrase: &str,
+    overwrite: bool,
+) -> Result<IdentityKeys, String> {
+    let export = read_export(export_path)?;
+    let key_files = decrypt_identity(&export, passphrase)?;
+    let imported = identity_keys(&key_files);
+
+    let existing_key_files = read_key_files(storage_path)?;
+    if !existing_key_files.is_empty() {
+        let existing = identity_keys(&existing_key_files);
+        if existing.fingerprint == imported.fingerprint {
+            return Ok(imported);
+        }
+        if !overwrite {
+            return Err(format!(
+                "node storage already has identity {}, confirm to replace it",
+                existing.fingerprint
+            ));
+        }
+    } else if !overwrite
+        && fs::read_dir(storage_path).is_ok_and(|mut entries| entries.next().is_some())
+    {
+        return Err("node storage already has data, confirm to import the identity".to_string());
+    }
+
+    let contents = key_files
+        .iter()
+        .map(|key_file| {
+            // Names come from the export, only plain file names are accepted
+            if key_file.name.contains(['/', '\\']) || !key_file.name.ends_with(".secret") {
+                return Err(format!("unsafe key file name {} in export", key_file.name));
+            }
+            BASE64
+                .decode(&key_file.content)
+                .map(|content| (key_file.name.as_str(), content))
+                .map_err(|_| format!("invalid content for {} in export", key_file.name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    fs::create_dir_all(storage_path)
+        .map_err(|e| format!("failed to create storage folder: {}", e))?;
+    let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let mut replaced = Vec::new();
+    let result = (|| {
+        for key_file in existing_key_files.iter() {
+            let key_path = storage_path.join(&key_file.name);
+            let replaced_path =
+                storage_path.join(format!("{}.replaced-{}", key_file.name, timestamp));
+            fs::rename(&key_path, &replaced_path)
+                .map_err(|e| format!("failed to keep {} aside: {}", key_file.name, e))?;
+            replaced.push((key_path, replaced_path));
+        }
+        for (name, content) in contents.iter() {
+            write_private(&storage_path.join(name), content)?;
+        }
+        if identity_keys(&read_key_files(storage_path)?).fingerprint != imported.fingerprint {
+            return Err("imported identity keys don't match the export fingerprint".to_string());
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        for (name, _) in contents.iter() {
+            let _ = fs::remove_file(storage_path.join(name));
+        }
+        for (key_path, replaced_path) in replaced.iter() {
+            let _ = fs::rename(replaced_path, key_path);
+        }
+        return Err(e);
+    }
+    info!(
+        "imported identity {} into {}",
+        imported.fingerprint,
+        storage_path.display()
+    );
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_export(storage_path: &Path, export_path: &Path, passphrase: &str) -> IdentityKeys {
+        // Cheap parameters so the test doesn't spend seconds deriving keys
+        let key_files = read_key_files(storage_path).unwrap();
+        let export = encrypt_identity(&key_files, passphrase, KdfParams::new(64, 1)).unwrap();
+        fs::write(export_path, serde_json::to_vec(&export).unwrap()).unwrap();
+        inspect_identity_export(export_path).unwrap()
+    }
+
+    #[test]
+    fn test_identity_round_trip_and_overwrite_confirmation() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let storage_path = root.join("node_storage");
+        fs::create_dir_all(&storage_path).unwrap();
+        fs::write(
+            storage_path.join(".secret"),
+            "IDENTITY_SECRET_KEY=abc\nGLOBAL_IDENTITY_NAME=@@node.sep-shinkai",
+        )
+        .unwrap();
+        let export_path = root.join("identity.json");
+        let exported = write_export(&storage_path, &export_path, "correct horse");
+        assert_eq!(
+            exported.identity_name.as_deref(),
+            Some("@@node.sep-shinkai")
+        );
+
+        let fresh_path = root.join("fresh");
+        assert!(import_identity(&export_path, &fresh_path, "wrong passphrase", false).is_err());
+        let imported = import_identity(&export_path, &fresh_path, "correct horse", false).unwrap();
+        assert_eq!(imported, exported);
+        assert_eq!(
+            fs::read(fresh_path.join(".secret")).unwrap(),
+            fs::read(storage_path.join(".secret")).unwrap()
+        );
+
+        fs::write(fresh_path.join(".secret"), "IDENTITY_SECRET_KEY=other").unwrap();
+        assert!(import_identity(&export_path, &fresh_path, "correct horse", false).is_err());
+        import_identity(&export_path, &fresh_path, "correct horse", true).unwrap();
+        assert_eq!(fs::read_dir(&fresh_path).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_modified_export_is_rejected() {
+        let key_files = vec![IdentityKeyFile {
+            name: ".secret".to_string(),
+            content: BASE64.encode("IDENTITY_SECRET_KEY=abc"),
+        }];
+        let mut export =
+            encrypt_identity(&key_files, "correct horse", KdfParams::new(64, 1)).unwrap();
+        assert!(decrypt_identity(&export, "correct horse").is_ok());
+        export.header.identity_name = Some("@@someone.else".to_string());
+        assert!(decrypt_identity(&export, "correct horse").is_err());
+        assert!(encrypt_identity(&key_files, "short", KdfParams::new(64, 1)).is_err());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod backup_schedule;
+pub mod identity_keys;
+pub mod storage_backup;
+pub mod storage_relocation;
+pub mod storage_reset;
+pub mod storage_usage;
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::local_shinkai_node::io_progress::{HashingReader, ProgressTracker};
+
+const BACKUP_FORMAT_VERSION: u32 = 1;
+const BACKUP_MANIFEST_ENTRY: &str = "shinkai-storage-backup.json";
+const STORAGE_ENTRY_PREFIX: &str = "storage/";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageBackupFile {
+    /// Relative to the storage folder, always `/` separated
+    pub path: String,
+    pub size: u64,
+    pub digest: String,
+}
+
+/// Who produced the backup, it's informative and isn't checked on restore
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct StorageBackupOrigin {
+    pub app_version: String,
+    pub node_version: Option<String>,
+    pub identity_name: Option<String>,
+}
+
+/// Describes the content of a storage backup, it's the last entry of the archive because the
+/// checksums are computed while the files are written
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageBackupManifest {
+    pub format_version: u32,
+    pub created_at: String,
+    #[serde(flatten)]
+    pub origin: StorageBackupOrigin,
+    pub total_size: u64,
+    pub files: Vec<StorageBackupFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageRestore {
+    pub manifest: StorageBackupManifest,
+    /// Where the storage that was replaced has been kept, None when there wasn't one
+    pub previous_storage_path: Option<String>,
+}
+
+/// Identity registered by the node, it's saved in the `.secret` file of the storage
+pub fn read_identity_name(storage_path: &Path) -> Option<String> {
+    fs::read_to_string(storage_path.join(".secret"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("GLOBAL_IDENTITY_NAME="))
+        .map(|identity_name| identity_name.trim().to_string())
+        .filter(|identity_name| !identity_name.is_empty())
+}
+
+pub(super) struct StorageEntry {
+    pub(super) relative_path: String,
+    pub(super) path: PathBuf,
+    pub(super) metadata: fs::Metadata,
+}
+
+fn relative_path(storage_path: &Path, path: &Path) -> Result<String, String> {
+    let relative = path
+        .strip_prefix(storage_path)
+        .map_err(|_| format!("{} is outside the storage folder", path.display()))?;
+    let components = relative
+        .components()
+        .map(|component| {
+            component
+                .as_os_str()
+                .to_str()
+                .ok_or_else(|| format!("{} isn't a valid UTF-8 path", path.display()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(components.join("/"))
+}
+
+/// Directories first so they are created before their files, symlinks are skipped
+pub(super) fn list_storage(storage_path: &Path) -> Result<Vec<StorageEntry>, String> {
+    let mut entries = Vec::new();
+    let mut pending = vec![storage_path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut children = fs::read_dir(&dir)
+            .map_err(|e| format!("failed to read {}: {}", dir.display(), e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            let path = child.path();
+            let metadata = fs::symlink_metadata(&path)
+                .map_err(|e| format!("failed to read metadata of {}: {}", path.display(), e))?;
+            if metadata.file_type().is_symlink() {
+                warn!("skipping symlink {} in storage", path.display());
+                continue;
+            }
+            if metadata.is_dir() {
+                pending.push(path.clone());
+            }
+            entries.push(StorageEntry {
+                relative_path: relative_path(storage_path, &path)?,
+                path,
+                metadata,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(unix)]
+pub(super) fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+pub(super) fn file_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.is_dir() {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+fn append_entry<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    entry_type: tar::EntryType,
+    size: u64,
+    mode: u32,
+    data: impl Read,
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_size(size);
+    header.set_mode(mode);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, data)
+        .map_err(|e| format!("failed to write {} into backup: {}", path, e))
+}
+
+/// Compresses every file of `storage_path` into `archive_path`. The node has to be stopped so the
+/// files don't change while they are read.
+pub fn backup_storage(
+    storage_path: &Path,
+    archive_path: &Path,
+    origin: StorageBackupOrigin,
+    on_progress: impl FnMut(u32),
+) -> Result<StorageBackupManifest, String> {
+    info!(
+        "backing up storage {} to {}",
+        storage_path.display(),
+        archive_path.display()
+    );
+    let storage_path = storage_path
+        .canonicalize()
+        .map_err(|e| format!("failed to resolve storage folder: {}", e))?;
+    if let Some(archive_dir) = archive_path
+        .parent()
+        .and_then(|dir| dir.canonicalize().ok())
+    {
+        if archive_dir.starts_with(&storage_path) {
+            return Err("backups can't be saved inside the storage folder".to_string());
+        }
+    }
+
+    let partial_archive_path = PathBuf::from(format!("{}.partial", archive_path.display()));
+    let result = write_backup(&storage_path, &partial_archive_path, origin, on_progress).and_then(
+        |manifest| {
+            fs::rename(&partial_archive_path, archive_path)
+                .map_err(|e| format!("failed to move backup into place: {}", e))?;
+            Ok(manifest)
+        },
+    );
+    if let Err(e) = &result {
+        error!("failed to back up storage: {}", e);
+        let _ = fs::remove_file(&partial_archive_path);
+    }
+    result
+}
+
+fn write_backup(
+    storage_path: &Path,
+    archive_path: &Path,
+    origin: StorageBackupOrigin,
+    on_progress: impl FnMut(u32),
+) -> Result<StorageBackupManifest, String> {
+    let entries = list_storage(storage_path)?;
+    let total_size = entries
+        .iter()
+        .filter(|entry| entry.metadata.is_file())
+        .map(|entry| entry.metadata.len())
+        .sum();
+
+    let archive_file =
+        File::create(archive_path).map_err(|e| format!("failed to create backup: {}", e))?;
+    let encoder = GzEncoder::new(BufWriter::new(archive_file), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    let mut progress = ProgressTracker::new(total_size, on_progress);
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry_path = format!("{}{}", STORAGE_ENTRY_PREFIX, entry.relative_path);
+        let mode = file_mode(&entry.metadata);
+        if entry.metadata.is_dir() {
+            append_entry(
+                &mut builder,
+                &entry_path,
+                tar::EntryType::Directory,
+                0,
+                mode,
+                io::empty(),
+            )?;
+            continue;
+        }
+        let file = File::open(&entry.path)
+            .map_err(|e| format!("failed to open {}: {}", entry.path.display(), e))?;
+        let size = entry.metadata.len();
+        let mut reader = HashingReader::new(BufReader::new(file), |read| progress.advance(read));
+        append_entry(
+            &mut builder,
+            &entry_path,
+            tar::EntryType::Regular,
+            size,
+            mode,
+            &mut reader,
+        )?;
+        files.push(StorageBackupFile {
+            path: entry.relative_path,
+            size,
+            digest: reader.digest(),
+        });
+    }
+
+    let manifest = StorageBackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        origin,
+        total_size,
+        files,
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("failed to serialize backup manifest: {}", e))?;
+    append_entry(
+        &mut builder,
+        BACKUP_MANIFEST_ENTRY,
+        tar::EntryType::Regular,
+        manifest_bytes.len() as u64,
+        0o644,
+        manifest_bytes.as_slice(),
+    )?;
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|writer| writer.into_inner().map_err(|e| e.into_error()))
+        .and_then(|file| file.sync_all())
+        .map_err(|e| format!("failed to finish backup: {}", e))?;
+    Ok(manifest)
+}
+
+/// Counts the compressed bytes read so progress can be reported before the manifest is reached
+struct CountingReader<R: Read, F: FnMut(u64)> {
+    inner: R,
+    on_read: F,
+}
+
+impl<R: Read, F: FnMut(u64)> Read for CountingReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        (self.on_read)(read as u64);
+        Ok(read)
+    }
+}
+
+/// Maps an archive entry inside `staging_path` refusing absolute paths and `..` components
+fn staged_path(staging_path: &Path, relative_path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative_path);
+    if relative_path.is_empty()
+        || !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(format!("unsafe path {} in backup", relative_path));
+    }
+    Ok(staging_path.join(relative))
+}
+
+#[cfg(unix)]
+pub(super) fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+        .map_err(|e| format!("failed to set permissions of {}: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+pub(super) fn set_mode(_path: &Path, _mode: u32) -> Result<(), String> {
+    Ok(())
+}
+
+/// Reads the whole backup checking every file against the manifest. Files are extracted into
+/// `staging_path` when it's set, otherwise they are only hashed.
+fn read_backup(
+    archive_path: &Path,
+    staging_path: Option<&Path>,
+    on_progress: impl FnMut(u32),
+) -> Result<StorageBackupManifest, String> {
+    let archive_file =
+        File::open(archive_path).map_err(|e| format!("failed to open backup: {}", e))?;
+    let archive_size = archive_file
+        .metadata()
+        .map_err(|e| format!("failed to read backup metadata: {}", e))?
+        .len();
+    let mut progress = ProgressTracker::new(archive_size, on_progress);
+    let reader = CountingReader {
+        inner: BufReader::new(archive_file),
+        on_read: |read| progress.advance(read),
+    };
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    let entries = archive
+        .entries()
+        .map_err(|e| format!("failed to read backup: {}", e))?;
+
+    let mut manifest: Option<StorageBackupManifest> = None;
+    let mut extracted: HashMap<String, (u64, String)> = HashMap::new();
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("failed to read backup entry: {}", e))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| format!("invalid backup entry path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+        if manifest.is_some() {
+            return Err(format!(
+                "unexpected backup entry {} after the manifest",
+                entry_path
+            ));
+        }
+        if entry_path == BACKUP_MANIFEST_ENTRY {
+            let mut bytes = Vec::new();
+            entry
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("failed to read backup manifest: {}", e))?;
+            manifest = Some(
+                serde_json::from_slice(&bytes)
+                    .map_err(|e| format!("invalid backup manifest: {}", e))?,
+            );
+            continue;
+        }
+        let relative_path = entry_path
+            .strip_prefix(STORAGE_ENTRY_PREFIX)
+            .map(|path| path.trim_end_matches('/').to_string())
+            .ok_or_else(|| format!("unexpected backup entry {}", entry_path))?;
+        let mode = entry.header().mode().unwrap_or(0o644);
+        let target_path = staging_path
+            .map(|staging_path| staged_path(staging_path, &relative_path))
+            .transpose()?;
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => {
+                if let Some(target_path) = &target_path {
+                    fs::create_dir_all(target_path).map_err(|e| {
+                        format!("failed to create {}: {}", target_path.display(), e)
+                    })?;
+                    set_mode(target_path, mode)?;
+                }
+            }
+            tar::EntryType::Regular => {
+                let mut reader = HashingReader::new(&mut entry, |_| {});
+                let size = match &target_path {
+                    Some(target_path) => {
+                        let mut file = BufWriter::new(File::create(target_path).map_err(|e| {
+                            format!("failed to create {}: {}", target_path.display(), e)
+                        })?);
+                        io::copy(&mut reader, &mut file).and_then(|size| file.flush().map(|_| size))
+                    }
+                    None => io::copy(&mut reader, &mut io::sink()),
+                }
+                .map_err(|e| format!("failed to extract {}: {}", relative_path, e))?;
+                if let Some(target_path) = &target_path {
+                    set_mode(target_path, mode)?;
+                }
+                extracted.insert(relative_path, (size, reader.digest()));
+            }
+            entry_type => {
+                return Err(format!(
+                    "unsupported entry {} of type {:?} in backup",
+                    entry_path, entry_type
+                ))
+            }
+        }
+    }
+
+    let manifest =
+        manifest.ok_or_else(|| "backup has no manifest, it's probably truncated".to_string())?;
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(format!(
+            "unsupported backup format version {}",
+            manifest.format_version
+        ));
+    }
+    for file in manifest.files.iter() {
+        match extracted.get(&file.path) {
+            Some((size, digest)) if *size == file.size && *digest == file.digest => {}
+            Some((_, digest)) => {
+                return Err(format!(
+                    "integrity check failed for {}: content hashes to {}",
+                    file.path, digest
+                ))
+            }
+            None => return Err(format!("backup is missing {}", file.path)),
+        }
+    }
+    if extracted.len() != manifest.files.len() {
+        return Err("backup contains files that aren't listed in its manifest".to_string());
+    }
+    Ok(manifest)
+}
+
+/// Checks every file of the backup against its manifest without extracting anything
+pub fn verify_backup(
+    archive_path: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<StorageBackupManifest, String> {
+    read_backup(archive_path, None, on_progress)
+}
+
+/// Replaces `storage_path` with the content of the backup. The backup is extracted and verified
+/// next to the storage folder first, then folders are swapped with renames so a failure at any
+/// point leaves either the previous storage or the restored one in place, never a mix of both.
+/// The previous storage is kept aside so it can be recovered.
+pub fn restore_storage(
+    archive_path: &Path,
+    storage_path: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<StorageRestore, String> {
+    info!(
+        "restoring storage {} from {}",
+        storage_path.display(),
+        archive_path.display()
+    );
+    let parent_path = storage_path
+        .parent()
+        .ok_or_else(|| "storage folder has no parent folder".to_string())?;
+    let storage_name = storage_path
+        .file_name()
+        .ok_or_else(|| "storage folder has no name".to_string())?
+        .to_string_lossy()
+        .to_string();
+    let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    // Staging next to the storage keeps it in the same volume so the swap is a rename
+    let staging_path = parent_path.join(format!(".{}.restore-{}", storage_name, timestamp));
+    let previous_storage_path =
+        parent_path.join(format!("{}.pre-restore-{}", storage_name, timestamp));
+
+    fs::create_dir_all(&staging_path)
+        .map_err(|e| format!("failed to create staging folder: {}", e))?;
+    let manifest = match read_backup(archive_path, Some(&staging_path), on_progress) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            error!("failed to restore storage: {}", e);
+            let _ = fs::remove_dir_all(&staging_path);
+            return Err(e);
+        }
+    };
+
+    let had_storage = storage_path.exists();
+    if had_storage {
+        if let Err(e) = fs::rename(storage_path, &previous_storage_path) {
+            let _ = fs::remove_dir_all(&staging_path);
+            return Err(format!("failed to move current storage aside: {}", e));
+        }
+    }
+    if let Err(e) = fs::rename(&staging_path, storage_path) {
+        if had_storage {
+            if let Err(rollback_error) = fs::rename(&previous_storage_path, storage_path) {
+                error!(
+                    "failed to put previous storage back from {}: {}",
+                    previous_storage_path.display(),
+                    rollback_error
+                );
+            }
+        }
+        let _ = fs::remove_dir_all(&staging_path);
+        return Err(format!("failed to move restored storage into place: {}", e));
+    }
+    Ok(StorageRestore {
+        manifest,
+        previous_storage_path: had_storage
+            .then(|| previous_storage_path.to_string_lossy().to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_and_restore_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let storage_path = root.join("node_storage");
+        fs::create_dir_all(storage_path.join("db").join("empty")).unwrap();
+        fs::write(storage_path.join(".secret"), "IDENTITY_SECRET_KEY=abc").unwrap();
+        fs::write(storage_path.join("db").join("data"), vec![7u8; 4096]).unwrap();
+
+        let archive_path = root.join("backup.tar.gz");
+        let origin = StorageBackupOrigin {
+            app_version: "1.0.0".to_string(),
+            node_version: None,
+            identity_name: Some("@@test.shinkai".to_string()),
+        };
+        let manifest = backup_storage(&storage_path, &archive_path, origin, |_| {}).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.total_size, 4096 + 23);
+        assert!(verify_backup(&archive_path, |_| {}).is_ok());
+
+        fs::write(storage_path.join("db").join("data"), "changed").unwrap();
+        let restore = restore_storage(&archive_path, &storage_path, |_| {}).unwrap();
+        assert_eq!(
+            fs::read(storage_path.join("db").join("data")).unwrap(),
+            vec![7u8; 4096]
+        );
+        assert!(storage_path.join("db").join("empty").is_dir());
+        let previous_storage_path = PathBuf::from(restore.previous_storage_path.unwrap());
+        assert_eq!(
+            fs::read_to_string(previous_storage_path.join("db").join("data")).unwrap(),
+            "changed"
+        );
+
+        // A truncated archive must be rejected before the storage is touched
+        let bytes = fs::read(&archive_path).unwrap();
+        fs::write(&archive_path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(restore_storage(&archive_path, &storage_path, |_| {}).is_err());
+        assert_eq!(
+            fs::read(storage_path.join("db").join("data")).unwrap(),
+            vec![7u8; 4096]
+        );
+    }
+
+    #[test]
+    fn test_staged_path_rejects_escaping_entries() {
+        let staging_path = Path::new("/tmp/staging");
+        assert!(staged_path(staging_path, "db/data").is_ok());
+        assert!(staged_path(staging_path, "../data").is_err());
+        assert!(staged_path(staging_path, "/etc/passwd").is_err());
+        assert!(staged_path(staging_path, "").is_err());
+    }
+}
@@ -0,0 +1,317 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Component, Path, PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::storage_backup::{file_mode, list_storage, set_mode};
+use crate::disk_space::volume_space;
+use crate::local_shinkai_node::io_progress::{HashingReader, ProgressTracker};
+
+/// Space left free on the destination volume on top of the storage size
+const FREE_SPACE_MARGIN_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageRelocation {
+    pub previous_storage_path: String,
+    pub storage_path: String,
+    pub files: u64,
+    pub total_size: u64,
+    /// False when the old folder couldn't be removed, the node already uses the new one
+    pub source_removed: bool,
+}
+
+/// Storage folder chosen by the last relocation, it overrides the default one
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StorageLocation {
+    node_storage_path: String,
+}
+
+pub fn load_storage_location(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str::<StorageLocation>(&content)
+        .map_err(|e| {
+            warn!(
+                "ignoring invalid storage location {}: {}",
+                path.display(),
+                e
+            )
+        })
+        .ok()
+        .map(|location| location.node_storage_path)
+}
+
+/// Saves the storage folder in `path`, the file is removed when it's None
+pub fn save_storage_location(path: &Path, node_storage_path: Option<&str>) -> Result<(), String> {
+    match node_storage_path {
+        Some(node_storage_path) => {
+            let content = serde_json::to_string_pretty(&StorageLocation {
+                node_storage_path: node_storage_path.to_string(),
+            })
+            .map_err(|e| format!("failed to serialize storage location: {}", e))?;
+            fs::write(path, content).map_err(|e| format!("failed to save storage location: {}", e))
+        }
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(format!("failed to remove storage location: {}", e))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Canonical form of `path` when its last components don't exist yet
+fn resolve_path(path: &Path) -> PathBuf {
+    let mut existing = path.to_path_buf();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match existing.file_name() {
+            Some(name) => missing.push(name.to_os_string()),
+            None => break,
+        }
+        existing.pop();
+    }
+    let mut resolved = existing.canonicalize().unwrap_or(existing);
+    for name in missing.iter().rev() {
+        resolved.push(name);
+    }
+    resolved
+}
+
+/// Checks `destination` can receive the storage, returns it resolved
+pub fn validate_destination(storage_path: &Path, destination: &Path) -> Result<PathBuf, String> {
+    if !destination.is_absolute() {
+        return Err("destination must be an absolute path".to_string());
+    }
+    if destination
+        .components()
+        .any(|component| matches!(component, Component::CurDir | Component::ParentDir))
+    {
+        return Err("destination can't contain . or .. components".to_string());
+    }
+    let storage_path = storage_path
+        .canonicalize()
+        .map_err(|e| format!("failed to read storage folder: {}", e))?;
+    let destination = resolve_path(destination);
+    if destination == storage_path {
+        return Err("destination is the current storage folder".to_string());
+    }
+    // Copying into itself would never end and the source can't be removed afterwards
+    if destination.starts_with(&storage_path) {
+        return Err("destination can't be inside the current storage folder".to_string());
+    }
+    if storage_path.starts_with(&destination) {
+        return Err("destination can't contain the current storage folder".to_string());
+    }
+    if destination.exists() {
+        let is_empty = fs::read_dir(&destination)
+            .map_err(|e| format!("failed to read destination: {}", e))?
+            .next()
+            .is_none();
+        if !destination.is_dir() || !is_empty {
+            return Err("destination must be an empty folder".to_string());
+        }
+    }
+    if destination.parent().is_none() {
+        return Err("destination can't be a root folder".to_string());
+    }
+    Ok(destination)
+}
+
+fn write_error(path: &Path, e: io::Error) -> String {
+    if e.kind() == io::ErrorKind::PermissionDenied {
+        format!("no permission to write in {}", path.display())
+    } else {
+        format!("failed to write {}: {}", path.display(), e)
+    }
+}
+
+fn copy_file(
+    source: &Path,
+    destination: &Path,
+    on_read: impl FnMut(u64),
+) -> Result<String, String> {
+    let source_file =
+        File::open(source).map_err(|e| format!("failed to open {}: {}", source.display(), e))?;
+    let mut reader = HashingReader::new(BufReader::new(source_file), on_read);
+    let destination_file = File::create(destination).map_err(|e| write_error(destination, e))?;
+    let mut writer = BufWriter::new(destination_file);
+    io::copy(&mut reader, &mut writer)
+        .map_err(|e| format!("failed to copy {}: {}", source.display(), e))?;
+    writer
+        .into_inner()
+        .map_err(|e| write_error(destination, e.into_error()))?
+        .sync_all()
+        .map_err(|e| write_error(destination, e))?;
+    Ok(reader.digest())
+}
+
+fn file_digest(path: &Path, on_read: impl FnMut(u64)) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+    let mut reader = HashingReader::new(BufReader::new(file), on_read);
+    io::copy(&mut reader, &mut io::sink())
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    Ok(reader.digest())
+}
+
+/// Copies `storage_path` into `destination` and reads the copy back checking every file hash.
+/// The copy is staged next to `destination` so nothing is left behind on failure.
+pub fn copy_storage(
+    storage_path: &Path,
+    destination: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<StorageRelocation, String> {
+    let destination = validate_destination(storage_path, destination)?;
+    let entries = list_storage(storage_path)?;
+    let total_size: u64 = entries
+        .iter()
+        .filter(|entry| entry.metadata.is_file())
+        .map(|entry| entry.metadata.len())
+        .sum();
+    match volume_space(&destination) {
+        Ok(space) if space.available_bytes < total_size + FREE_SPACE_MARGIN_BYTES => {
+            return Err(format!(
+                "not enough space in {}: {} bytes are needed and {} are available",
+                space.mount_point,
+                total_size + FREE_SPACE_MARGIN_BYTES,
+                space.available_bytes
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("couldn't check destination free space: {}", e),
+    }
+
+    let parent_path = destination.parent().unwrap();
+    fs::create_dir_all(parent_path).map_err(|e| write_error(parent_path, e))?;
+    let destination_name = destination
+        .file_name()
+        .ok_or_else(|| "destination has no name".to_string())?
+        .to_string_lossy()
+        .to_string();
+    let staging_path = parent_path.join(format!(
+        ".{}.relocating-{}",
+        destination_name,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    fs::create_dir(&staging_path).map_err(|e| write_error(parent_path, e))?;
+    info!(
+        "copying storage {} to {}",
+        storage_path.display(),
+        destination.display()
+    );
+
+    let result = (|| {
+        // Every byte is read twice, once to copy it and once to verify the copy
+        let mut progress = ProgressTracker::new(total_size * 2, on_progress);
+        let mut digests = Vec::new();
+        for entry in entries.iter() {
+            let staged_path = staging_path.join(&entry.relative_path);
+            if entry.metadata.is_dir() {
+                fs::create_dir_all(&staged_path).map_err(|e| write_error(&staged_path, e))?;
+                continue;
+            }
+            let digest = copy_file(&entry.path, &staged_path, |read| progress.advance(read))?;
+            set_mode(&staged_path, file_mode(&entry.metadata))?;
+            digests.push((entry, staged_path, digest));
+        }
+        for (entry, staged_path, digest) in digests.iter() {
+            if file_digest(staged_path, |read| progress.advance(read))? != *digest {
+                return Err(format!(
+                    "{} doesn't match the original after copying it",
+                    entry.relative_path
+                ));
+            }
+        }
+        // Folders last, a read only folder would have refused its files
+        for entry in entries.iter().filter(|entry| entry.metadata.is_dir()) {
+            set_mode(
+                &staging_path.join(&entry.relative_path),
+                file_mode(&entry.metadata),
+            )?;
+        }
+        if destination.exists() {
+            fs::remove_dir(&destination).map_err(|e| write_error(&destination, e))?;
+        }
+        fs::rename(&staging_path, &destination).map_err(|e| write_error(&destination, e))?;
+        Ok(digests.len() as u64)
+    })();
+
+    match result {
+        Ok(files) => Ok(StorageRelocation {
+            previous_storage_path: storage_path.to_string_lossy().to_string(),
+            storage_path: destination.to_string_lossy().to_string(),
+            files,
+            total_size,
+            source_removed: false,
+        }),
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging_path);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_destination_must_be_outside_the_storage_and_empty() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let storage_path = root.join("node_storage");
+        fs::create_dir_all(storage_path.join("db_sqlite")).unwrap();
+        fs::create_dir_all(root.join("not-empty")).unwrap();
+        fs::write(root.join("not-empty").join("file"), "x").unwrap();
+
+        assert!(validate_destination(&storage_path, &storage_path).is_err());
+        assert!(validate_destination(&storage_path, &storage_path.join("moved")).is_err());
+        assert!(validate_destination(&storage_path, root).is_err());
+        assert!(validate_destination(&storage_path, &root.join("not-empty")).is_err());
+        assert!(validate_destination(&storage_path, &root.join("x").join("..")).is_err());
+        assert!(validate_destination(&storage_path, Path::new("relative")).is_err());
+        let destination = root.join("new").join("node_storage");
+        assert_eq!(
+            validate_destination(&storage_path, &destination).unwrap(),
+            resolve_path(&destination)
+        );
+    }
+
+    #[test]
+    fn test_copy_storage_copies_every_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let storage_path = root.join("node_storage");
+        fs::create_dir_all(storage_path.join("filesystem").join("chat")).unwrap();
+        fs::create_dir_all(storage_path.join("empty")).unwrap();
+        fs::write(
+            storage_path.join(".secret"),
+            "GLOBAL_IDENTITY_NAME=@@node.sep",
+        )
+        .unwrap();
+        fs::write(
+            storage_path.join("filesystem").join("chat").join("a.txt"),
+            "hello",
+        )
+        .unwrap();
+        let destination = root.join("moved");
+        fs::create_dir_all(&destination).unwrap();
+
+        let mut last_progress = 0;
+        let relocation = copy_storage(&storage_path, &destination, |progress| {
+            last_progress = progress
+        })
+        .unwrap();
+        assert_eq!(last_progress, 100);
+        assert_eq!((relocation.files, relocation.total_size), (2, 36));
+        assert_eq!(
+            fs::read_to_string(destination.join("filesystem").join("chat").join("a.txt")).unwrap(),
+            "hello"
+        );
+        assert!(destination.join("empty").is_dir());
+        // The source is left untouched and no staging folder remains
+        assert!(storage_path.join(".secret").exists());
+        assert_eq!(fs::read_dir(root).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,433 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::storage_usage::StorageCategory;
+
+const QUARANTINE_MANIFEST_FILE_NAME: &str = "reset.json";
+const QUARANTINE_ENTRIES_FOLDER: &str = "entries";
+const IDENTITY_NAME_PREFIX: &str = "GLOBAL_IDENTITY_NAME=";
+/// Days the last reset can be undone
+const QUARANTINE_DAYS: i64 = 7;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageResetScope {
+    Database,
+    /// Files uploaded to the node filesystem and their vector data
+    Files,
+    Tools,
+    Caches,
+    Everything,
+}
+
+impl StorageResetScope {
+    /// Storage usage categories the scope removes, their top level entries are shared with the
+    /// usage scan
+    fn categories(&self) -> &'static [StorageCategory] {
+        match self {
+            StorageResetScope::Database => &[StorageCategory::Database],
+            StorageResetScope::Files => &[StorageCategory::Filesystem, StorageCategory::Embeddings],
+            StorageResetScope::Tools => &[StorageCategory::Tools],
+            StorageResetScope::Caches => &[StorageCategory::ToolsRunnerCache],
+            StorageResetScope::Everything => &[],
+        }
+    }
+
+    fn covers(&self, entry_name: &str) -> bool {
+        *self == StorageResetScope::Everything
+            || self
+                .categories()
+                .iter()
+                .any(|category| category.entry_names().contains(&entry_name))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageResetAction {
+    Remove,
+    /// Key files kept by `preserve_keys` forget the identity so the node registers again
+    ClearIdentityName,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageResetEntry {
+    /// Relative to the storage folder
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub action: StorageResetAction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageReset {
+    pub scopes: Vec<StorageResetScope>,
+    pub preserve_keys: bool,
+    pub dry_run: bool,
+    pub reset_at: String,
+    pub entries: Vec<StorageResetEntry>,
+    pub total_size: u64,
+    /// Where the removed entries are kept, None on dry runs and purges
+    pub quarantine_path: Option<String>,
+    /// Disk space the quarantine takes until the undo window ends, on dry runs the space it
+    /// would take if the reset isn't purged
+    pub quarantine_size: u64,
+    pub undo_until: Option<String>,
+}
+
+/// Files like `.secret` that hold the node keys
+fn is_key_file(entry_name: &str) -> bool {
+    entry_name.ends_with(".secret")
+}
+
+fn entry_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+fn quarantine_path(storage_path: &Path) -> Result<PathBuf, String> {
+    let storage_name = storage_path
+        .file_name()
+        .ok_or_else(|| "storage folder has no name".to_string())?
+        .to_string_lossy();
+    let parent_path = storage_path
+        .parent()
+        .ok_or_else(|| "storage folder has no parent folder".to_string())?;
+    // Next to the storage so entries are moved with renames and the node never sees them
+    Ok(parent_path.join(format!(".{}.quarantine", storage_name)))
+}
+
+/// Entries of `storage_path` the reset would touch
+fn plan_entries(
+    storage_path: &Path,
+    scopes: &[StorageResetScope],
+    preserve_keys: bool,
+) -> Result<Vec<StorageResetEntry>, String> {
+    let read_dir = match fs::read_dir(storage_path) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("failed to read storage folder: {}", e)),
+    };
+    let mut entries = Vec::new();
+    for entry in read_dir {
+        let entry = entry.map_err(|e| format!("failed to read storage entry: {}", e))?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !scopes.iter().any(|scope| scope.covers(&name)) {
+            continue;
+        }
+        let path = entry.path();
+        let action = if is_key_file(&name) {
+            // Keys only go away when everything is reset on purpose
+            if !preserve_keys {
+                StorageResetAction::Remove
+            } else if read_identity_line(&path).is_some() {
+                StorageResetAction::ClearIdentityName
+            } else {
+                continue;
+            }
+        } else {
+            StorageResetAction::Remove
+        };
+        entries.push(StorageResetEntry {
+            is_dir: path.is_dir(),
+            size: entry_size(&path),
+            path: name,
+            action,
+        });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+fn read_identity_line(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with(IDENTITY_NAME_PREFIX))
+        .map(|line| line.to_string())
+}
+
+fn clear_identity_name(path: &Path) -> Result<(), String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let new_content = content
+        .lines()
+        .filter(|line| !line.starts_with(IDENTITY_NAME_PREFIX))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    fs::write(path, new_content).map_err(|e| format!("failed to write {}: {}", path.display(), e))
+}
+
+/// Lists what resetting `scopes` would remove without touching anything
+pub fn plan_reset(
+    storage_path: &Path,
+    scopes: &[StorageResetScope],
+    preserve_keys: bool,
+) -> Result<StorageReset, String> {
+    let entries = plan_entries(storage_path, scopes, preserve_keys)?;
+    Ok(StorageReset {
+        scopes: scopes.to_vec(),
+        preserve_keys,
+        dry_run: true,
+        reset_at: chrono::Utc::now().to_rfc3339(),
+        total_size: entries
+            .iter()
+            .filter(|entry| entry.action == StorageResetAction::Remove)
+            .map(|entry| entry.size)
+            .sum(),
+        quarantine_size: entries.iter().map(|entry| entry.size).sum(),
+        entries,
+        quarantine_path: None,
+        undo_until: None,
+    })
+}
+
+/// Moves the entries covered by `scopes` into the quarantine folder, replacing the previous
+/// reset. If any entry can't be moved the ones already moved are put back. `purge` deletes the
+/// quarantine right away so the disk space is freed but the reset can't be undone.
+pub fn reset_storage(
+    storage_path: &Path,
+    scopes: &[StorageResetScope],
+    preserve_keys: bool,
+    purge: bool,
+) -> Result<StorageReset, String> {
+    let mut reset = plan_reset(storage_path, scopes, preserve_keys)?;
+    info!(
+        "resetting {:?} of storage {}, {} entries",
+        scopes,
+        storage_path.display(),
+        reset.entries.len()
+    );
+    let quarantine_path = quarantine_path(storage_path)?;
+    if quarantine_path.exists() {
+        fs::remove_dir_all(&quarantine_path)
+            .map_err(|e| format!("failed to remove previous reset: {}", e))?;
+    }
+    let quarantine_entries_path = quarantine_path.join(QUARANTINE_ENTRIES_FOLDER);
+    fs::create_dir_all(&quarantine_entries_path)
+        .map_err(|e| format!("failed to create quarantine folder: {}", e))?;
+
+    let mut done: Vec<&StorageResetEntry> = Vec::new();
+    let mut result = Ok(());
+    for entry in reset.entries.iter() {
+        let path = storage_path.join(&entry.path);
+        let quarantined_path = quarantine_entries_path.join(&entry.path);
+        result = match entry.action {
+            StorageResetAction::Remove => fs::rename(&path, &quarantined_path)
+                .map_err(|e| format!("failed to remove {}: {}", entry.path, e)),
+            StorageResetAction::ClearIdentityName => fs::copy(&path, &quarantined_path)
+                .map_err(|e| format!("failed to keep a copy of {}: {}", entry.path, e))
+                .and_then(|_| clear_identity_name(&path)),
+        };
+        if result.is_err() {
+            break;
+        }
+        done.push(entry);
+    }
+    if let Err(e) = result {
+        error!("storage reset failed, putting removed entries back: {}", e);
+        for entry in done {
+            if let Err(e) = restore_entry(storage_path, &quarantine_entries_path, entry) {
+                error!("failed to put {} back: {}", entry.path, e);
+            }
+        }
+        let _ = fs::remove_dir_all(&quarantine_path);
+        return Err(e);
+    }
+
+    let reset_at = chrono::Utc::now();
+    reset.dry_run = false;
+    reset.reset_at = reset_at.to_rfc3339();
+    if purge {
+        match fs::remove_dir_all(&quarantine_path) {
+            Ok(_) => {
+                reset.quarantine_size = 0;
+                return Ok(reset);
+            }
+            // The reset already happened, what's left stays undoable like a regular reset
+            Err(e) => warn!("failed to purge removed entries, keeping them: {}", e),
+        }
+    }
+    reset.quarantine_size = entry_size(&quarantine_path);
+    reset.quarantine_path = Some(quarantine_path.to_string_lossy().to_string());
+    reset.undo_until = Some((reset_at + chrono::Duration::days(QUARANTINE_DAYS)).to_rfc3339());
+    let manifest = serde_json::to_vec_pretty(&reset)
+        .map_err(|e| format!("failed to serialize reset manifest: {}", e))?;
+    if let Err(e) = fs::write(
+        quarantine_path.join(QUARANTINE_MANIFEST_FILE_NAME),
+        manifest,
+    ) {
+        // The reset already happened, it just can't be undone
+        warn!(
+            "failed to save reset manifest, it won't be possible to undo it: {}",
+            e
+        );
+    }
+    Ok(reset)
+}
+
+fn restore_entry(
+    storage_path: &Path,
+    quarantine_entries_path: &Path,
+    entry: &StorageResetEntry,
+) -> Result<(), String> {
+    let path = storage_path.join(&entry.path);
+    let quarantined_path = quarantine_entries_path.join(&entry.path);
+    match entry.action {
+        StorageResetAction::Remove => {
+            // The node recreates what it misses when it starts after a reset
+            if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else if path.exists() {
+                fs::remove_file(&path)
+            } else {
+                Ok(())
+            }
+            .and_then(|_| fs::rename(&quarantined_path, &path))
+        }
+        StorageResetAction::ClearIdentityName => fs::copy(&quarantined_path, &path).map(|_| ()),
+    }
+    .map_err(|e| format!("failed to restore {}: {}", entry.path, e))
+}
+
+/// The reset that can still be undone, quarantines older than the undo window are removed
+pub fn last_reset(storage_path: &Path) -> Result<Option<StorageReset>, String> {
+    let quarantine_path = quarantine_path(storage_path)?;
+    let Ok(manifest) = fs::read(quarantine_path.join(QUARANTINE_MANIFEST_FILE_NAME)) else {
+        return Ok(None);
+    };
+    let reset: StorageReset =
+        serde_json::from_slice(&manifest).map_err(|e| format!("invalid reset manifest: {}", e))?;
+    let expired = reset
+        .undo_until
+        .as_deref()
+        .and_then(|undo_until| chrono::DateTime::parse_from_rfc3339(undo_until).ok())
+        .is_none_or(|undo_until| undo_until < chrono::Utc::now());
+    if expired {
+        info!("removing expired storage reset quarantine");
+        fs::remove_dir_all(&quarantine_path)
+            .map_err(|e| format!("failed to remove expired reset: {}", e))?;
+        return Ok(None);
+    }
+    Ok(Some(reset))
+}
+
+/// Puts back everything the last reset removed, entries the node created since are replaced
+pub fn undo_last_reset(storage_path: &Path) -> Result<StorageReset, String> {
+    let reset =
+        last_reset(storage_path)?.ok_or_else(|| "there isn't a reset to undo".to_string())?;
+    let quarantine_path = quarantine_path(storage_path)?;
+    let quarantine_entries_path = quarantine_path.join(QUARANTINE_ENTRIES_FOLDER);
+    fs::create_dir_all(storage_path)
+        .map_err(|e| format!("failed to create storage folder: {}", e))?;
+    for entry in reset.entries.iter() {
+        restore_entry(storage_path, &quarantine_entries_path, entry)?;
+    }
+    fs::remove_dir_all(&quarantine_path)
+        .map_err(|e| format!("failed to remove quarantine folder: {}", e))?;
+    info!("undid storage reset of {}", reset.reset_at);
+    Ok(reset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The quarantine goes next to the storage so it's nested in its own temp folder
+    fn storage_fixture() -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage_path = temp_dir.path().join("node_storage");
+        fs::create_dir_all(storage_path.join("db_sqlite")).unwrap();
+        fs::create_dir_all(storage_path.join("filesystem")).unwrap();
+        fs::write(storage_path.join("db_sqlite").join("shinkai.db"), "db").unwrap();
+        fs::write(storage_path.join("filesystem").join("notes.txt"), "notes").unwrap();
+        fs::write(
+            storage_path.join(".secret"),
+            "IDENTITY_SECRET_KEY=abc\nGLOBAL_IDENTITY_NAME=@@test.shinkai",
+        )
+        .unwrap();
+        fs::write(
+            storage_path.join("node.secret"),
+            "ENCRYPTION_SECRET_KEY=def",
+        )
+        .unwrap();
+        (temp_dir, storage_path)
+    }
+
+    #[test]
+    fn test_plan_covers_scopes_and_preserves_every_key_file() {
+        let (_temp_dir, storage_path) = storage_fixture();
+        let plan = plan_reset(&storage_path, &[StorageResetScope::Database], true).unwrap();
+        let paths: Vec<&str> = plan.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["db_sqlite"]);
+        assert_eq!(plan.total_size, 2);
+
+        fs::create_dir_all(storage_path.join("lancedb")).unwrap();
+        let plan = plan_reset(&storage_path, &[StorageResetScope::Files], true).unwrap();
+        let paths: Vec<&str> = plan.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["filesystem", "lancedb"]);
+
+        let plan = plan_reset(&storage_path, &[StorageResetScope::Everything], true).unwrap();
+        let secret = plan.entries.iter().find(|e| e.path == ".secret").unwrap();
+        assert_eq!(secret.action, StorageResetAction::ClearIdentityName);
+        assert!(!plan.entries.iter().any(|e| e.path == "node.secret"));
+
+        let plan = plan_reset(&storage_path, &[StorageResetScope::Everything], false).unwrap();
+        assert_eq!(plan.entries.len(), 5);
+        assert!(storage_path.join("db_sqlite").exists());
+    }
+
+    #[test]
+    fn test_reset_can_be_undone() {
+        let (_temp_dir, storage_path) = storage_fixture();
+        let reset =
+            reset_storage(&storage_path, &[StorageResetScope::Everything], true, false).unwrap();
+        assert!(!reset.dry_run);
+        assert!(reset.quarantine_size > 0);
+        assert!(!storage_path.join("db_sqlite").exists());
+        assert!(!storage_path.join("filesystem").exists());
+        let secret = fs::read_to_string(storage_path.join(".secret")).unwrap();
+        assert_eq!(secret, "IDENTITY_SECRET_KEY=abc");
+        assert!(storage_path.join("node.secret").exists());
+
+        // The node recreates its database when it starts again
+        fs::create_dir_all(storage_path.join("db_sqlite")).unwrap();
+        assert!(last_reset(&storage_path).unwrap().is_some());
+        undo_last_reset(&storage_path).unwrap();
+        assert_eq!(
+            fs::read_to_string(storage_path.join("db_sqlite").join("shinkai.db")).unwrap(),
+            "db"
+        );
+        assert!(fs::read_to_string(storage_path.join(".secret"))
+            .unwrap()
+            .contains("GLOBAL_IDENTITY_NAME=@@test.shinkai"));
+        assert!(last_reset(&storage_path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_purged_reset_frees_the_quarantine() {
+        let (_temp_dir, storage_path) = storage_fixture();
+        reset_storage(&storage_path, &[StorageResetScope::Database], true, false).unwrap();
+        let reset =
+            reset_storage(&storage_path, &[StorageResetScope::Everything], true, true).unwrap();
+        assert_eq!(reset.quarantine_size, 0);
+        assert!(reset.quarantine_path.is_none());
+        assert!(reset.undo_until.is_none());
+        assert!(!storage_path.join("filesystem").exists());
+        assert!(!quarantine_path(&storage_path).unwrap().exists());
+        assert!(last_reset(&storage_path).unwrap().is_none());
+    }
+}
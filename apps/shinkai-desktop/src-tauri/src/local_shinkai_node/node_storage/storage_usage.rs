@@ -0,0 +1,343 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// Scans kept in the history file
+const HISTORY_LEN: usize = 30;
+const PROGRESS_EVERY_FILES: u64 = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum StorageCategory {
+    Database,
+    /// Chat folders and files uploaded to the node
+    Filesystem,
+    Embeddings,
+    Tools,
+    ToolsRunnerCache,
+    Logs,
+    Other,
+}
+
+impl StorageCategory {
+    const ALL: [StorageCategory; 7] = [
+        StorageCategory::Database,
+        StorageCategory::Filesystem,
+        StorageCategory::Embeddings,
+        StorageCategory::Tools,
+        StorageCategory::ToolsRunnerCache,
+        StorageCategory::Logs,
+        StorageCategory::Other,
+    ];
+
+    /// Top level entries of the storage folder holding this category, resets remove them too
+    pub fn entry_names(&self) -> &'static [&'static str] {
+        match self {
+            StorageCategory::Database => &["db_sqlite", "db"],
+            StorageCategory::Filesystem => &["filesystem"],
+            StorageCategory::Embeddings => &["vector_fs", "lancedb", "embeddings"],
+            StorageCategory::Tools => &["tools_storage", "internal_tools_storage", "tools"],
+            StorageCategory::ToolsRunnerCache => &["deno_cache", "uv_cache", "cache", "tmp"],
+            StorageCategory::Logs => &["logs"],
+            StorageCategory::Other => &[],
+        }
+    }
+
+    fn of_entry(entry_name: &str) -> Option<StorageCategory> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.entry_names().contains(&entry_name))
+    }
+}
+
+/// Category of a `/` separated path relative to the storage folder
+fn classify(relative_path: &str) -> StorageCategory {
+    let top = relative_path.split('/').next().unwrap_or_default();
+    let file_name = relative_path.rsplit('/').next().unwrap_or_default();
+    let extension = file_name.rsplit_once('.').map(|(_, extension)| extension);
+    if extension == Some("log") {
+        return StorageCategory::Logs;
+    }
+    match StorageCategory::of_entry(top) {
+        // Vector tables live in their own database files next to the main one
+        Some(StorageCategory::Database)
+            if file_name.contains("vec") || file_name.contains("embedding") =>
+        {
+            StorageCategory::Embeddings
+        }
+        Some(category) => category,
+        None if extension == Some("lance") => StorageCategory::Embeddings,
+        None if matches!(
+            extension,
+            Some("db" | "sqlite" | "sqlite3" | "db-wal" | "db-shm")
+        ) =>
+        {
+            StorageCategory::Database
+        }
+        None => StorageCategory::Other,
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageCategoryUsage {
+    pub category: StorageCategory,
+    pub size: u64,
+    pub files: u64,
+    /// Bytes gained since the previous scan, None on the first one
+    pub growth: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StorageItem {
+    pub size: u64,
+    /// Relative to the storage folder
+    pub path: String,
+    pub category: StorageCategory,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageUsage {
+    pub storage_path: String,
+    pub scanned_at: String,
+    pub total_size: u64,
+    pub total_files: u64,
+    pub total_growth: Option<i64>,
+    pub previous_scanned_at: Option<String>,
+    pub categories: Vec<StorageCategoryUsage>,
+    /// Largest files, biggest first
+    pub largest_items: Vec<StorageItem>,
+    /// Entries that couldn't be read, they are missing from the totals
+    pub unreadable: Vec<String>,
+}
+
+/// What's kept of every scan to compute growth
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageUsageSnapshot {
+    pub storage_path: String,
+    pub scanned_at: String,
+    pub total_size: u64,
+    pub categories: BTreeMap<StorageCategory, u64>,
+}
+
+impl StorageUsage {
+    pub fn snapshot(&self) -> StorageUsageSnapshot {
+        StorageUsageSnapshot {
+            storage_path: self.storage_path.clone(),
+            scanned_at: self.scanned_at.clone(),
+            total_size: self.total_size,
+            categories: self
+                .categories
+                .iter()
+                .map(|usage| (usage.category, usage.size))
+                .collect(),
+        }
+    }
+}
+
+pub fn load_history(path: &Path) -> Vec<StorageUsageSnapshot> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return vec![];
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        warn!(
+            "ignoring invalid storage usage history {}: {}",
+            path.display(),
+            e
+        );
+        vec![]
+    })
+}
+
+/// Appends `snapshot` to the history keeping only the latest scans
+pub fn save_history(path: &Path, snapshot: StorageUsageSnapshot) -> Result<(), String> {
+    let mut history = load_history(path);
+    history.push(snapshot);
+    if history.len() > HISTORY_LEN {
+        history.drain(..history.len() - HISTORY_LEN);
+    }
+    let content = serde_json::to_string(&history)
+        .map_err(|e| format!("failed to serialize storage usage history: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("failed to save storage usage history: {}", e))
+}
+
+/// Walks `storage_path` adding up every file by category. `previous` is the last scan of the same
+/// folder, `on_progress` receives the files and bytes scanned so far.
+pub fn scan_storage(
+    storage_path: &Path,
+    top: usize,
+    previous: Option<&StorageUsageSnapshot>,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<StorageUsage, String> {
+    if !storage_path.is_dir() {
+        return Err(format!(
+            "storage folder {} doesn't exist",
+            storage_path.display()
+        ));
+    }
+    let mut categories: BTreeMap<StorageCategory, (u64, u64)> = BTreeMap::new();
+    // Min heap of the largest files seen so far
+    let mut largest: BinaryHeap<Reverse<StorageItem>> = BinaryHeap::with_capacity(top + 1);
+    let mut unreadable = Vec::new();
+    let (mut total_files, mut total_size) = (0u64, 0u64);
+    let mut pending: Vec<(PathBuf, String)> = vec![(storage_path.to_path_buf(), String::new())];
+    while let Some((dir, relative_dir)) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("failed to read {}: {}", dir.display(), e);
+                unreadable.push(relative_dir);
+                continue;
+            }
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = if relative_dir.is_empty() {
+                name
+            } else {
+                format!("{}/{}", relative_dir, name)
+            };
+            // Symlinks aren't followed, their target may live anywhere
+            let metadata = match fs::symlink_metadata(entry.path()) {
+                Ok(metadata) => metadata,
+                Err(_) => {
+                    unreadable.push(relative_path);
+                    continue;
+                }
+            };
+            if metadata.is_dir() {
+                pending.push((entry.path(), relative_path));
+                continue;
+            }
+            let size = metadata.len();
+            let category = classify(&relative_path);
+            let usage = categories.entry(category).or_default();
+            usage.0 += size;
+            usage.1 += 1;
+            total_size += size;
+            total_files += 1;
+            largest.push(Reverse(StorageItem {
+                size,
+                path: relative_path,
+                category,
+            }));
+            if largest.len() > top {
+                largest.pop();
+            }
+            if total_files % PROGRESS_EVERY_FILES == 0 {
+                on_progress(total_files, total_size);
+            }
+        }
+    }
+    on_progress(total_files, total_size);
+
+    let growth = |category: StorageCategory, size: u64| {
+        previous.map(|previous| {
+            size as i64
+                - previous
+                    .categories
+                    .get(&category)
+                    .copied()
+                    .unwrap_or_default() as i64
+        })
+    };
+    let mut largest_items: Vec<StorageItem> = largest.into_iter().map(|item| item.0).collect();
+    largest_items.sort_by(|a, b| b.cmp(a));
+    Ok(StorageUsage {
+        storage_path: storage_path.to_string_lossy().to_string(),
+        scanned_at: chrono::Utc::now().to_rfc3339(),
+        total_size,
+        total_files,
+        total_growth: previous.map(|previous| total_size as i64 - previous.total_size as i64),
+        previous_scanned_at: previous.map(|previous| previous.scanned_at.clone()),
+        categories: categories
+            .into_iter()
+            .map(|(category, (size, files))| StorageCategoryUsage {
+                category,
+                size,
+                files,
+                growth: growth(category, size),
+            })
+            .collect(),
+        largest_items,
+        unreadable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_storage_paths() {
+        assert_eq!(classify("db_sqlite/shinkai.db"), StorageCategory::Database);
+        assert_eq!(
+            classify("db_sqlite/shinkai_vec.db"),
+            StorageCategory::Embeddings
+        );
+        assert_eq!(
+            classify("filesystem/chat/notes.pdf"),
+            StorageCategory::Filesystem
+        );
+        assert_eq!(
+            classify("deno_cache/deps/mod.ts"),
+            StorageCategory::ToolsRunnerCache
+        );
+        assert_eq!(
+            classify("tools_storage/run/output.json"),
+            StorageCategory::Tools
+        );
+        assert_eq!(classify("filesystem/node.log"), StorageCategory::Logs);
+        assert_eq!(classify("lancedb/table.lance"), StorageCategory::Embeddings);
+        assert_eq!(classify(".secret"), StorageCategory::Other);
+    }
+
+    #[test]
+    fn test_scan_reports_largest_items_and_growth() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage_path = temp_dir.path();
+        fs::create_dir_all(storage_path.join("db_sqlite")).unwrap();
+        fs::create_dir_all(storage_path.join("filesystem").join("chat")).unwrap();
+        fs::write(
+            storage_path.join("db_sqlite").join("shinkai.db"),
+            vec![0u8; 300],
+        )
+        .unwrap();
+        fs::write(
+            storage_path.join("filesystem").join("chat").join("a.pdf"),
+            vec![0u8; 200],
+        )
+        .unwrap();
+        fs::write(storage_path.join("filesystem").join("b.txt"), vec![0u8; 10]).unwrap();
+
+        let previous = StorageUsageSnapshot {
+            storage_path: storage_path.to_string_lossy().to_string(),
+            scanned_at: "2024-06-01T00:00:00+00:00".to_string(),
+            total_size: 400,
+            categories: BTreeMap::from([(StorageCategory::Database, 100)]),
+        };
+        let usage = scan_storage(storage_path, 2, Some(&previous), |_, _| {}).unwrap();
+        assert_eq!(usage.total_size, 510);
+        assert_eq!(usage.total_files, 3);
+        assert_eq!(usage.total_growth, Some(110));
+        let paths: Vec<&str> = usage
+            .largest_items
+            .iter()
+            .map(|i| i.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["db_sqlite/shinkai.db", "filesystem/chat/a.pdf"]);
+        let filesystem = usage
+            .categories
+            .iter()
+            .find(|usage| usage.category == StorageCategory::Filesystem)
+            .unwrap();
+        assert_eq!(
+            (filesystem.size, filesystem.files, filesystem.growth),
+            (210, 2, Some(210))
+        );
+        let database = &usage.categories[0];
+        assert_eq!(database.growth, Some(200));
+    }
+}
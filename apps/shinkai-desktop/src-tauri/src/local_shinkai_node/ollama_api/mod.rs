@@ -1,2 +1,9 @@
 pub mod ollama_api_client;
 pub mod ollama_api_types;
+pub mod ollama_disk_preflight;
+pub mod ollama_model_archive;
+pub mod ollama_model_benchmark;
+pub mod ollama_model_memory;
+pub mod ollama_model_store;
+pub mod ollama_model_usage;
+pub mod ollama_registry_client;
@@ -5,12 +5,18 @@ use reqwest::header::HeaderValue;
 use semver::{Version, VersionReq};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::path::Path;
 
 use super::ollama_api_types::{
-    OllamaApiBlobResponse, OllamaApiCreateRequest, OllamaApiCreateResponse, OllamaApiPullRequest,
-    OllamaApiPullResponse, OllamaApiTagsResponse, OllamaApiVersionResponse,
+    OllamaApiBlobResponse, OllamaApiCreateRequest, OllamaApiCreateResponse, OllamaApiEmbedRequest,
+    OllamaApiEmbedResponse, OllamaApiGenerateOptions, OllamaApiGenerateRequest,
+    OllamaApiGenerateResponse, OllamaApiPsResponse, OllamaApiPullRequest, OllamaApiPullResponse,
+    OllamaApiTagsResponse, OllamaApiVersionResponse,
 };
 
+/// Oldest ollama able to create models from GGUF blobs
+pub const OLLAMA_VERSION_REQUIREMENT: &str = ">=0.5.7";
+
 pub struct OllamaApiClient {
     base_url: String,
 }
@@ -44,8 +50,171 @@ impl OllamaApiClient {
         Ok(response)
     }
 
-    pub async fn pull(&self, model_name: &str) -> Result<(), String> {
-        match self.pull_stream(model_name).await {
+    /// Models currently loaded in memory
+    pub async fn ps(&self) -> Result<OllamaApiPsResponse, String> {
+        let url = format!("{}/api/ps", self.base_url);
+        let client = reqwest::Client::new();
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("failed to get running models from api: {}", e))?
+            .json::<OllamaApiPsResponse>()
+            .await
+            .map_err(|e| format!("failed to parse running models from api: {}", e))?;
+        Ok(response)
+    }
+
+    /// Loads `model` without generating anything, `keep_alive` overrides how many seconds it stays loaded
+    pub async fn load_model(
+        &self,
+        model_name: &str,
+        keep_alive: Option<i64>,
+        options: Option<OllamaApiGenerateOptions>,
+    ) -> Result<(), String> {
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/api/generate", self.base_url))
+            .json(&OllamaApiGenerateRequest {
+                model: model_name.to_string(),
+                prompt: None,
+                stream: false,
+                keep_alive,
+                options: options.clone(),
+            })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        // Embedding models can't generate, an embed request without input loads them instead
+        if !text.contains("does not support generate") {
+            return Err(format!(
+                "failed to load model {}: {} - {}",
+                model_name, status, text
+            ));
+        }
+        let response = client
+            .post(format!("{}/api/embed", self.base_url))
+            .json(&OllamaApiEmbedRequest {
+                model: model_name.to_string(),
+                input: vec![],
+                keep_alive,
+                options,
+            })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "failed to load model {}: {} - {}",
+                model_name, status, text
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn unload_model(&self, model_name: &str) -> Result<(), String> {
+        let url = format!("{}/api/generate", self.base_url);
+        let client = reqwest::Client::new();
+        let response = client
+            .post(&url)
+            .json(&OllamaApiGenerateRequest {
+                model: model_name.to_string(),
+                prompt: None,
+                stream: false,
+                keep_alive: Some(0),
+                options: None,
+            })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "failed to unload model {}: {} - {}",
+                model_name, status, text
+            ));
+        }
+        Ok(())
+    }
+
+    /// Non streamed completion, the response includes ollama load and evaluation timings
+    pub async fn generate(
+        &self,
+        model_name: &str,
+        prompt: &str,
+        options: Option<OllamaApiGenerateOptions>,
+    ) -> Result<OllamaApiGenerateResponse, String> {
+        let url = format!("{}/api/generate", self.base_url);
+        let client = reqwest::Client::new();
+        let response = client
+            .post(&url)
+            .json(&OllamaApiGenerateRequest {
+                model: model_name.to_string(),
+                prompt: Some(prompt.to_string()),
+                stream: false,
+                keep_alive: None,
+                options,
+            })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "failed to generate with model {}: {} - {}",
+                model_name, status, text
+            ));
+        }
+        response
+            .json::<OllamaApiGenerateResponse>()
+            .await
+            .map_err(|e| format!("failed to parse generate response: {}", e))
+    }
+
+    pub async fn embed(
+        &self,
+        model_name: &str,
+        input: Vec<String>,
+        options: Option<OllamaApiGenerateOptions>,
+    ) -> Result<OllamaApiEmbedResponse, String> {
+        let url = format!("{}/api/embed", self.base_url);
+        let client = reqwest::Client::new();
+        let response = client
+            .post(&url)
+            .json(&OllamaApiEmbedRequest {
+                model: model_name.to_string(),
+                input,
+                keep_alive: None,
+                options,
+            })
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!(
+                "failed to embed with model {}: {} - {}",
+                model_name, status, text
+            ));
+        }
+        response
+            .json::<OllamaApiEmbedResponse>()
+            .await
+            .map_err(|e| format!("failed to parse embed response: {}", e))
+    }
+
+    pub async fn pull(&self, model_name: &str, insecure: bool) -> Result<(), String> {
+        match self.pull_stream(model_name, insecure).await {
             Ok(mut stream) => {
                 while let Some(stream_value) = stream.next().await {
                     log::debug!("ollama pull stream value {:?}", stream_value);
@@ -58,9 +227,12 @@ impl OllamaApiClient {
         Ok(())
     }
 
+    /// `model_name` can be registry qualified (host[:port]/namespace/model:tag) to pull from a mirror,
+    /// `insecure` allows registries served over plain http or with self signed certificates
     pub async fn pull_stream(
         &self,
         model_name: &str,
+        insecure: bool,
     ) -> Result<Box<dyn Stream<Item = Result<OllamaApiPullResponse, String>> + Send + Unpin>, String>
     {
         let url = format!("{}/api/pull", self.base_url);
@@ -68,6 +240,7 @@ impl OllamaApiClient {
         let body: OllamaApiPullRequest = OllamaApiPullRequest {
             stream: true,
             model: model_name.to_string(),
+            insecure,
         };
         let response = client
             .post(&url)
@@ -166,6 +339,37 @@ impl OllamaApiClient {
         Ok(digest)
     }
 
+    pub async fn blob_exists(&self, digest: &str) -> Result<bool, String> {
+        let url = format!("{}/api/blobs/{}", self.base_url, digest);
+        let client = reqwest::Client::new();
+        let response = client.head(&url).send().await.map_err(|e| e.to_string())?;
+        Ok(response.status().is_success())
+    }
+
+    /// Streams a blob from disk, the server verifies `digest` against the received content
+    pub async fn upload_blob_file(&self, path: &Path, digest: &str) -> Result<(), String> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| format!("failed to open blob {}: {}", path.display(), e))?;
+        let url = format!("{}/api/blobs/{}", self.base_url, digest);
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(&url)
+            .body(reqwest::Body::from(file))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let message = format!("failed to upload blob {}: {} - {}", digest, status, text);
+            error!("{}", message);
+            return Err(message);
+        }
+        Ok(())
+    }
+
     pub async fn create_model_from_gguf(
         &self,
         model_name: &str,
@@ -179,7 +383,7 @@ impl OllamaApiClient {
             error!("{}", message);
             message
         })?;
-        let requirement = VersionReq::parse(">=0.5.7").map_err(|e| {
+        let requirement = VersionReq::parse(OLLAMA_VERSION_REQUIREMENT).map_err(|e| {
             let message = format!("failed to parse version requirement: {}", e);
             error!("{}", message);
             message
@@ -206,16 +410,7 @@ impl OllamaApiClient {
         // First upload the GGUF file as a blob
         let digest = self.upload_blob(gguf_data).await?;
         // Check if blob exists on server before creating model
-        let check_url = format!("{}/api/blobs/{}", self.base_url, digest);
-        let client = reqwest::Client::new();
-
-        let head_response = client
-            .head(&check_url)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-
-        if !head_response.status().is_success() {
+        if !self.blob_exists(&digest).await? {
             let message = format!("blob {} not found on server", digest);
             error!("{}", message);
             return Err(message);
@@ -226,17 +421,25 @@ impl OllamaApiClient {
         files.insert("arctic.gguf".to_string(), digest);
 
         // Create the model using the uploaded blob
-        let url = format!("{}/api/create", self.base_url);
-        let client = reqwest::Client::new();
-
-        let create_request = OllamaApiCreateRequest {
+        self.create_model(&OllamaApiCreateRequest {
             model: model_name.to_string(),
             files,
-        };
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Creates a model from blobs already uploaded through `/api/blobs/<digest>`
+    pub async fn create_model(
+        &self,
+        create_request: &OllamaApiCreateRequest,
+    ) -> Result<(), String> {
+        let url = format!("{}/api/create", self.base_url);
+        let client = reqwest::Client::new();
 
         let response = client
             .post(&url)
-            .json(&create_request)
+            .json(create_request)
             .send()
             .await
             .map_err(|e| e.to_string())?;
@@ -267,6 +470,9 @@ impl OllamaApiClient {
                 if let Some(status) = json_value.get("status").and_then(|s| s.as_str()) {
                     final_status = status.to_string();
                 }
+                if let Some(error) = json_value.get("error").and_then(|e| e.as_str()) {
+                    final_status = error.to_string();
+                }
             }
         }
 
@@ -29,6 +29,7 @@ pub struct OllamaApiTagsResponse {
 pub struct OllamaApiPullRequest {
     pub stream: bool,
     pub model: String,
+    pub insecure: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -56,10 +57,23 @@ pub enum OllamaApiPullResponse {
     },
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct OllamaApiCreateRequest {
     pub model: String,
+    /// File name to blob digest of the GGUF files of the model
     pub files: std::collections::HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adapters: Option<std::collections::HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -76,3 +90,80 @@ pub struct OllamaApiBlobResponse {
 pub struct OllamaApiVersionResponse {
     pub version: String,
 }
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RunningModel {
+    pub name: String,
+    pub model: String,
+    pub size: u64,
+    pub digest: String,
+    pub details: ModelDetails,
+    pub expires_at: String,
+    pub size_vram: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaApiPsResponse {
+    pub models: Vec<RunningModel>,
+}
+
+/// Per request options, eg: pinned sampling for benchmarks or the threads background mode allows
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct OllamaApiGenerateOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_thread: Option<u32>,
+}
+
+/// A request without prompt only loads or unloads the model, depending on `keep_alive`
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaApiGenerateRequest {
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    pub stream: bool,
+    /// Seconds to keep the model loaded, 0 unloads it and negative values keep it loaded forever
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<OllamaApiGenerateOptions>,
+}
+
+/// Durations are in nanoseconds
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OllamaApiGenerateResponse {
+    pub model: String,
+    pub response: String,
+    pub done: bool,
+    pub total_duration: Option<u64>,
+    pub load_duration: Option<u64>,
+    pub prompt_eval_count: Option<u64>,
+    pub prompt_eval_duration: Option<u64>,
+    pub eval_count: Option<u64>,
+    pub eval_duration: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaApiEmbedRequest {
+    pub model: String,
+    pub input: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<OllamaApiGenerateOptions>,
+}
+
+/// Durations are in nanoseconds
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OllamaApiEmbedResponse {
+    pub model: String,
+    pub embeddings: Vec<Vec<f32>>,
+    pub total_duration: Option<u64>,
+    pub load_duration: Option<u64>,
+    pub prompt_eval_count: Option<u64>,
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::disk_space::{volume_space, VolumeSpace};
+
+/// Free space that must remain available after a download so the node database keeps working
+pub const FREE_SPACE_RESERVE_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VolumeRequirement {
+    pub volume: VolumeSpace,
+    pub hosts_models: bool,
+    pub hosts_node_storage: bool,
+    pub required_bytes: u64,
+    pub sufficient: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DiskSpacePreflight {
+    pub model: String,
+    /// Bytes still missing from the local store, None when the registry manifest couldn't be read
+    pub download_bytes: Option<u64>,
+    pub volumes: Vec<VolumeRequirement>,
+    pub sufficient: bool,
+}
+
+/// Checks the volumes holding the models store and the node storage can take `download_bytes`
+/// while keeping `FREE_SPACE_RESERVE_BYTES` free, both paths are usually in the same volume.
+pub fn evaluate_disk_space(
+    model: &str,
+    download_bytes: Option<u64>,
+    models_path: &Path,
+    node_storage_path: &Path,
+) -> Result<DiskSpacePreflight, String> {
+    Ok(evaluate_volumes(
+        model,
+        download_bytes,
+        volume_space(models_path)?,
+        volume_space(node_storage_path)?,
+    ))
+}
+
+fn evaluate_volumes(
+    model: &str,
+    download_bytes: Option<u64>,
+    models_volume: VolumeSpace,
+    node_storage_volume: VolumeSpace,
+) -> DiskSpacePreflight {
+    let mut volumes: Vec<VolumeRequirement> = Vec::new();
+    for (volume, is_models) in [(models_volume, true), (node_storage_volume, false)] {
+        let index = match volumes
+            .iter()
+            .position(|v| v.volume.mount_point == volume.mount_point)
+        {
+            Some(index) => index,
+            None => {
+                volumes.push(VolumeRequirement {
+                    volume,
+                    hosts_models: false,
+                    hosts_node_storage: false,
+                    required_bytes: FREE_SPACE_RESERVE_BYTES,
+                    sufficient: false,
+                });
+                volumes.len() - 1
+            }
+        };
+        let requirement = &mut volumes[index];
+        if is_models {
+            requirement.hosts_models = true;
+            requirement.required_bytes += download_bytes.unwrap_or_default();
+        } else {
+            requirement.hosts_node_storage = true;
+        }
+    }
+    for requirement in volumes.iter_mut() {
+        requirement.sufficient = requirement.volume.available_bytes >= requirement.required_bytes;
+    }
+
+    DiskSpacePreflight {
+        model: model.to_string(),
+        download_bytes,
+        sufficient: volumes.iter().all(|v| v.sufficient),
+        volumes,
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind")]
+pub enum PullModelError {
+    InsufficientDiskSpace { preflight: DiskSpacePreflight },
+    Failed { error: String },
+}
+
+impl fmt::Display for PullModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PullModelError::InsufficientDiskSpace { preflight } => {
+                write!(f, "not enough disk space for model {}", preflight.model)?;
+                for requirement in preflight.volumes.iter().filter(|v| !v.sufficient) {
+                    write!(
+                        f,
+                        ", {} requires {} bytes but only {} are available",
+                        requirement.volume.mount_point,
+                        requirement.required_bytes,
+                        requirement.volume.available_bytes
+                    )?;
+                }
+                Ok(())
+            }
+            PullModelError::Failed { error } => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<String> for PullModelError {
+    fn from(error: String) -> Self {
+        PullModelError::Failed { error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn volume(mount_point: &str, available_bytes: u64) -> VolumeSpace {
+        VolumeSpace {
+            path: format!("{}/shinkai", mount_point),
+            mount_point: mount_point.to_string(),
+            total_bytes: 100 * GIB,
+            available_bytes,
+        }
+    }
+
+    #[test]
+    fn test_shared_volume_needs_download_and_reserve() {
+        let preflight = evaluate_volumes(
+            "llama3.2:1b",
+            Some(2 * GIB),
+            volume("/", 3 * GIB),
+            volume("/", 3 * GIB),
+        );
+        assert_eq!(preflight.volumes.len(), 1);
+        let requirement = &preflight.volumes[0];
+        assert!(requirement.hosts_models && requirement.hosts_node_storage);
+        assert_eq!(
+            requirement.required_bytes,
+            2 * GIB + FREE_SPACE_RESERVE_BYTES
+        );
+        assert!(preflight.sufficient);
+
+        let preflight = evaluate_volumes(
+            "llama3.2:1b",
+            Some(2 * GIB + 1),
+            volume("/", 3 * GIB),
+            volume("/", 3 * GIB),
+        );
+        assert!(!preflight.sufficient);
+    }
+
+    #[test]
+    fn test_separate_volumes_are_checked_independently() {
+        // Only the models volume takes the download, the node storage one keeps the reserve
+        let preflight = evaluate_volumes(
+            "llama3.2:1b",
+            Some(10 * GIB),
+            volume("/models", 20 * GIB),
+            volume("/", GIB / 2),
+        );
+        assert_eq!(preflight.volumes.len(), 2);
+        let models = preflight.volumes.iter().find(|v| v.hosts_models).unwrap();
+        let node_storage = preflight
+            .volumes
+            .iter()
+            .find(|v| v.hosts_node_storage)
+            .unwrap();
+        assert!(models.sufficient);
+        assert_eq!(node_storage.required_bytes, FREE_SPACE_RESERVE_BYTES);
+        assert!(!node_storage.sufficient);
+        assert!(!preflight.sufficient);
+    }
+
+    #[test]
+    fn test_unknown_download_size_only_requires_reserve() {
+        let preflight = evaluate_volumes(
+            "llama3.2:1b",
+            None,
+            volume("/", 2 * GIB),
+            volume("/", 2 * GIB),
+        );
+        assert_eq!(
+            preflight.volumes[0].required_bytes,
+            FREE_SPACE_RESERVE_BYTES
+        );
+        assert!(preflight.sufficient);
+    }
+}
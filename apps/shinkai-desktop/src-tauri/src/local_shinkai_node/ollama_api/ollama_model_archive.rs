@@ -0,0 +1,625 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use super::ollama_api_client::OllamaApiClient;
+use super::ollama_api_types::OllamaApiCreateRequest;
+use super::ollama_model_store::{
+    OllamaManifest, OllamaManifestLayer, OllamaModelName, OllamaModelStore,
+};
+use crate::local_shinkai_node::io_progress::{HashingReader, ProgressTracker};
+
+const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const ARCHIVE_MANIFEST_ENTRY: &str = "shinkai-model-archive.json";
+const OLLAMA_MANIFEST_ENTRY: &str = "manifest.json";
+const BLOBS_ENTRY_PREFIX: &str = "blobs/";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelArchiveBlob {
+    pub digest: String,
+    pub media_type: String,
+    pub size: u64,
+}
+
+/// Describes the content of a portable model archive, it's always the first entry of the tar
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelArchiveManifest {
+    pub format_version: u32,
+    pub model: String,
+    pub created_at: String,
+    pub total_size: u64,
+    pub blobs: Vec<ModelArchiveBlob>,
+}
+
+fn integrity_error(digest: &str, actual_digest: &str) -> String {
+    format!(
+        "integrity check failed for blob {}: content hashes to {}",
+        digest, actual_digest
+    )
+}
+
+fn append_entry(
+    builder: &mut tar::Builder<BufWriter<File>>,
+    path: &str,
+    size: u64,
+    data: impl Read,
+) -> Result<(), String> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, data)
+        .map_err(|e| format!("failed to write {} into archive: {}", path, e))
+}
+
+/// Bundles the manifest of `model` and every blob it references into a tar file at `archive_path`.
+/// Blobs are hashed while they are copied so a corrupted model store never produces an archive.
+pub fn export_model(
+    store: &OllamaModelStore,
+    model: &str,
+    archive_path: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<ModelArchiveManifest, String> {
+    info!("exporting model {} to {}", model, archive_path.display());
+    let manifest_bytes = store.read_manifest_bytes(model)?;
+    let manifest: OllamaManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|e| format!("failed to parse manifest for model {}: {}", model, e))?;
+
+    let blobs: Vec<ModelArchiveBlob> = manifest
+        .blobs()
+        .into_iter()
+        .map(|layer| ModelArchiveBlob {
+            digest: layer.digest,
+            media_type: layer.media_type,
+            size: layer.size,
+        })
+        .collect();
+    let archive_manifest = ModelArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        model: OllamaModelName::parse(model)?.display_name(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        total_size: manifest.total_size(),
+        blobs,
+    };
+
+    let partial_archive_path = PathBuf::from(format!("{}.partial", archive_path.display()));
+    let result = write_archive(
+        store,
+        &archive_manifest,
+        &manifest_bytes,
+        &partial_archive_path,
+        on_progress,
+    )
+    .and_then(|_| {
+        fs::rename(&partial_archive_path, archive_path)
+            .map_err(|e| format!("failed to move archive into place: {}", e))
+    });
+    if let Err(e) = result {
+        error!("failed to export model {}: {}", model, e);
+        let _ = fs::remove_file(&partial_archive_path);
+        return Err(e);
+    }
+    Ok(archive_manifest)
+}
+
+fn write_archive(
+    store: &OllamaModelStore,
+    archive_manifest: &ModelArchiveManifest,
+    manifest_bytes: &[u8],
+    archive_path: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<(), String> {
+    let archive_file =
+        File::create(archive_path).map_err(|e| format!("failed to create archive: {}", e))?;
+    let mut builder = tar::Builder::new(BufWriter::new(archive_file));
+
+    let archive_manifest_bytes = serde_json::to_vec_pretty(archive_manifest)
+        .map_err(|e| format!("failed to serialize archive manifest: {}", e))?;
+    append_entry(
+        &mut builder,
+        ARCHIVE_MANIFEST_ENTRY,
+        archive_manifest_bytes.len() as u64,
+        archive_manifest_bytes.as_slice(),
+    )?;
+    append_entry(
+        &mut builder,
+        OLLAMA_MANIFEST_ENTRY,
+        manifest_bytes.len() as u64,
+        manifest_bytes,
+    )?;
+
+    let mut progress = ProgressTracker::new(archive_manifest.total_size, on_progress);
+    for blob in archive_manifest.blobs.iter() {
+        let blob_path = store.blob_path(&blob.digest)?;
+        let blob_file = File::open(&blob_path)
+            .map_err(|e| format!("failed to open blob {}: {}", blob_path.display(), e))?;
+        let size = blob_file
+            .metadata()
+            .map_err(|e| format!("failed to read blob metadata {}: {}", blob.digest, e))?
+            .len();
+        if size != blob.size {
+            return Err(format!(
+                "integrity check failed for blob {}: expected {} bytes, found {}",
+                blob.digest, blob.size, size
+            ));
+        }
+        let mut reader =
+            HashingReader::new(BufReader::new(blob_file), |read| progress.advance(read));
+        append_entry(
+            &mut builder,
+            &format!("{}{}", BLOBS_ENTRY_PREFIX, blob.digest.replace(':', "-")),
+            size,
+            &mut reader,
+        )?;
+        let actual_digest = reader.digest();
+        if actual_digest != blob.digest {
+            return Err(integrity_error(&blob.digest, &actual_digest));
+        }
+    }
+
+    builder
+        .into_inner()
+        .and_then(|mut writer| writer.flush())
+        .map_err(|e| format!("failed to finish archive: {}", e))
+}
+
+/// Reads only the archive manifest, which is the first entry of every model archive
+pub fn read_archive_manifest(archive_path: &Path) -> Result<ModelArchiveManifest, String> {
+    let archive_file =
+        File::open(archive_path).map_err(|e| format!("failed to open archive: {}", e))?;
+    let mut archive = tar::Archive::new(BufReader::new(archive_file));
+    let mut entry = archive
+        .entries()
+        .map_err(|e| format!("failed to read archive: {}", e))?
+        .next()
+        .ok_or_else(|| "archive is empty".to_string())?
+        .map_err(|e| format!("failed to read archive entry: {}", e))?;
+    let mut bytes = Vec::new();
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("failed to read archive manifest: {}", e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("invalid archive manifest: {}", e))
+}
+
+/// Content of an archive once it has been unpacked and verified in a staging folder.
+/// The staging folder is removed when this value is dropped.
+pub struct StagedModelArchive {
+    pub archive_manifest: ModelArchiveManifest,
+    pub manifest_bytes: Vec<u8>,
+    pub staging_path: PathBuf,
+}
+
+impl StagedModelArchive {
+    pub fn blob_path(&self, digest: &str) -> PathBuf {
+        staged_blob_path(&self.staging_path, digest)
+    }
+}
+
+impl Drop for StagedModelArchive {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.staging_path);
+    }
+}
+
+fn staged_blob_path(staging_path: &Path, digest: &str) -> PathBuf {
+    staging_path.join(digest.replace(':', "-"))
+}
+
+/// Unpacks `archive_path` into `staging_path` verifying the sha256 of every blob against the
+/// digests declared by both the archive manifest and the Ollama manifest.
+pub fn stage_archive(
+    archive_path: &Path,
+    staging_path: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<StagedModelArchive, String> {
+    fs::create_dir_all(staging_path)
+        .map_err(|e| format!("failed to create staging directory: {}", e))?;
+    match unpack_archive(archive_path, staging_path, on_progress) {
+        Ok((archive_manifest, manifest_bytes)) => Ok(StagedModelArchive {
+            archive_manifest,
+            manifest_bytes,
+            staging_path: staging_path.to_path_buf(),
+        }),
+        Err(e) => {
+            error!(
+                "failed to stage model archive {}: {}",
+                archive_path.display(),
+                e
+            );
+            let _ = fs::remove_dir_all(staging_path);
+            Err(e)
+        }
+    }
+}
+
+fn unpack_archive(
+    archive_path: &Path,
+    staging_path: &Path,
+    on_progress: impl FnMut(u32),
+) -> Result<(ModelArchiveManifest, Vec<u8>), String> {
+    let archive_file =
+        File::open(archive_path).map_err(|e| format!("failed to open archive: {}", e))?;
+    let mut archive = tar::Archive::new(BufReader::new(archive_file));
+    let mut entries = archive
+        .entries()
+        .map_err(|e| format!("failed to read archive: {}", e))?;
+
+    let mut read_small_entry = |expected_path: &str| -> Result<Vec<u8>, String> {
+        let mut entry = entries
+            .next()
+            .ok_or_else(|| format!("archive is missing {}", expected_path))?
+            .map_err(|e| format!("failed to read archive entry: {}", e))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| format!("invalid archive entry path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+        if entry_path != expected_path {
+            return Err(format!(
+                "unexpected archive entry {}, expected {}",
+                entry_path, expected_path
+            ));
+        }
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("failed to read {}: {}", expected_path, e))?;
+        Ok(bytes)
+    };
+    let archive_manifest: ModelArchiveManifest =
+        serde_json::from_slice(&read_small_entry(ARCHIVE_MANIFEST_ENTRY)?)
+            .map_err(|e| format!("invalid archive manifest: {}", e))?;
+    if archive_manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(format!(
+            "unsupported archive format version {}",
+            archive_manifest.format_version
+        ));
+    }
+    let manifest_bytes = read_small_entry(OLLAMA_MANIFEST_ENTRY)?;
+    let manifest: OllamaManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|e| format!("invalid model manifest in archive: {}", e))?;
+    for layer in manifest.blobs() {
+        if !archive_manifest
+            .blobs
+            .iter()
+            .any(|blob| blob.digest == layer.digest && blob.size == layer.size)
+        {
+            return Err(format!(
+                "model manifest references blob {} which is not part of the archive",
+                layer.digest
+            ));
+        }
+    }
+
+    let mut progress = ProgressTracker::new(archive_manifest.total_size, on_progress);
+    let mut verified_digests: Vec<String> = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("failed to read archive entry: {}", e))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| format!("invalid archive entry path: {}", e))?
+            .to_string_lossy()
+            .to_string();
+        let blob = entry_path
+            .strip_prefix(BLOBS_ENTRY_PREFIX)
+            .and_then(|name| {
+                archive_manifest
+                    .blobs
+                    .iter()
+                    .find(|blob| blob.digest.replace(':', "-") == name)
+            })
+            .ok_or_else(|| format!("unexpected archive entry {}", entry_path))?;
+
+        let mut staged_blob = BufWriter::new(
+            File::create(staged_blob_path(staging_path, &blob.digest))
+                .map_err(|e| format!("failed to create staged blob: {}", e))?,
+        );
+        let mut reader = HashingReader::new(entry, |read| progress.advance(read));
+        let size = io::copy(&mut reader, &mut staged_blob)
+            .and_then(|size| staged_blob.flush().map(|_| size))
+            .map_err(|e| format!("failed to extract blob {}: {}", blob.digest, e))?;
+        let actual_digest = reader.digest();
+        if actual_digest != blob.digest || size != blob.size {
+            return Err(integrity_error(&blob.digest, &actual_digest));
+        }
+        verified_digests.push(blob.digest.clone());
+    }
+
+    if let Some(missing) = archive_manifest
+        .blobs
+        .iter()
+        .find(|blob| !verified_digests.contains(&blob.digest))
+    {
+        return Err(format!(
+            "archive is truncated, blob {} is missing",
+            missing.digest
+        ));
+    }
+
+    Ok((archive_manifest, manifest_bytes))
+}
+
+fn read_staged_text(
+    staged: &StagedModelArchive,
+    layer: &OllamaManifestLayer,
+) -> Result<String, String> {
+    fs::read_to_string(staged.blob_path(&layer.digest))
+        .map_err(|e| format!("failed to read {} layer: {}", layer.media_type, e))
+}
+
+/// Request that recreates the model from its staged layers. Weights, projectors and adapters
+/// are referenced by digest so they have to be uploaded first, the small text layers are sent
+/// inline. The config blob isn't reused, Ollama writes a new one for the created model.
+pub fn create_request(
+    staged: &StagedModelArchive,
+    model: &str,
+) -> Result<OllamaApiCreateRequest, String> {
+    let manifest: OllamaManifest = serde_json::from_slice(&staged.manifest_bytes)
+        .map_err(|e| format!("invalid model manifest: {}", e))?;
+    let mut create_request = OllamaApiCreateRequest {
+        model: model.to_string(),
+        ..Default::default()
+    };
+    for layer in manifest.layers.iter() {
+        let file_name = format!("{}.gguf", layer.digest.replace(':', "-"));
+        match layer.media_type.as_str() {
+            "application/vnd.ollama.image.model" | "application/vnd.ollama.image.projector" => {
+                create_request.files.insert(file_name, layer.digest.clone());
+            }
+            "application/vnd.ollama.image.adapter" => {
+                create_request
+                    .adapters
+                    .get_or_insert_with(Default::default)
+                    .insert(file_name, layer.digest.clone());
+            }
+            "application/vnd.ollama.image.template" => {
+                create_request.template = Some(read_staged_text(staged, layer)?);
+            }
+            "application/vnd.ollama.image.system" => {
+                create_request.system = Some(read_staged_text(staged, layer)?);
+            }
+            "application/vnd.ollama.image.license" => {
+                create_request
+                    .license
+                    .get_or_insert_with(Vec::new)
+                    .push(read_staged_text(staged, layer)?);
+            }
+            "application/vnd.ollama.image.params" => {
+                create_request.parameters = Some(
+                    serde_json::from_str(&read_staged_text(staged, layer)?)
+                        .map_err(|e| format!("invalid params layer: {}", e))?,
+                );
+            }
+            "application/vnd.ollama.image.messages" => {
+                create_request.messages = Some(
+                    serde_json::from_str(&read_staged_text(staged, layer)?)
+                        .map_err(|e| format!("invalid messages layer: {}", e))?,
+                );
+            }
+            media_type => return Err(format!("unsupported model layer {}", media_type)),
+        }
+    }
+    if create_request.files.is_empty() {
+        return Err("model archive has no model weights".to_string());
+    }
+    Ok(create_request)
+}
+
+/// Uploads the staged blobs through `/api/blobs/<digest>` and recreates the model from them
+pub async fn import_staged_model(
+    ollama_api: &OllamaApiClient,
+    staged: &StagedModelArchive,
+    model: &str,
+    on_progress: impl FnMut(u32),
+) -> Result<(), String> {
+    let create_request = create_request(staged, model)?;
+    let blobs: Vec<&ModelArchiveBlob> = staged
+        .archive_manifest
+        .blobs
+        .iter()
+        .filter(|blob| {
+            create_request
+                .files
+                .values()
+                .any(|digest| *digest == blob.digest)
+                || create_request
+                    .adapters
+                    .iter()
+                    .flat_map(|adapters| adapters.values())
+                    .any(|digest| *digest == blob.digest)
+        })
+        .collect();
+    let mut progress = ProgressTracker::new(blobs.iter().map(|blob| blob.size).sum(), on_progress);
+    for blob in blobs {
+        if ollama_api.blob_exists(&blob.digest).await? {
+            info!("blob {} already exists, skipping upload", blob.digest);
+        } else {
+            ollama_api
+                .upload_blob_file(&staged.blob_path(&blob.digest), &blob.digest)
+                .await?;
+        }
+        progress.advance(blob.size);
+    }
+    ollama_api.create_model(&create_request).await?;
+    verify_imported_model(ollama_api, model).await
+}
+
+/// Checks Ollama lists `model` once it was created
+async fn verify_imported_model(ollama_api: &OllamaApiClient, model: &str) -> Result<(), String> {
+    let model_name = OllamaModelName::parse(model)?.display_name();
+    let installed = ollama_api
+        .tags()
+        .await
+        .map_err(|e| format!("failed to list models after import: {}", e))?;
+    if !installed.models.iter().any(|m| m.model == model_name) {
+        return Err(format!(
+            "model {} is not listed by ollama after import",
+            model_name
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::local_shinkai_node::ollama_api::ollama_model_store::IMPORT_STAGING_PREFIX;
+    use sha2::{Digest, Sha256};
+
+    const MODEL: &str = "llama3.2:1b";
+
+    fn digest_of(data: &[u8]) -> String {
+        format!("sha256:{:x}", Sha256::digest(data))
+    }
+
+    fn layer(media_type: &str, data: &[u8]) -> serde_json::Value {
+        serde_json::json!({
+            "mediaType": media_type,
+            "digest": digest_of(data),
+            "size": data.len(),
+        })
+    }
+
+    const PARAMS: &[u8] = b"{\"temperature\":0.5}";
+
+    /// Writes the blobs and a manifest referencing them the way Ollama lays them out
+    fn write_model(store: &OllamaModelStore, config: &[u8], weights: &[u8]) {
+        fs::create_dir_all(store.blobs_path()).unwrap();
+        for data in [config, weights, PARAMS] {
+            fs::write(store.blob_path(&digest_of(data)).unwrap(), data).unwrap();
+        }
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.docker.distribution.manifest.v2+json",
+            "config": layer("application/vnd.docker.container.image.v1+json", config),
+            "layers": [
+                layer("application/vnd.ollama.image.model", weights),
+                layer("application/vnd.ollama.image.params", PARAMS),
+            ],
+        });
+        store
+            .write_manifest_bytes(MODEL, &serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_export_stage_and_create_request_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = OllamaModelStore::new(dir.path().join("source"));
+        let weights = b"GGUF model weights";
+        write_model(&source, b"{\"model_format\":\"gguf\"}", weights);
+        let archive_path = dir.path().join("model.tar");
+
+        let mut progress = vec![];
+        let archive_manifest =
+            export_model(&source, MODEL, &archive_path, |p| progress.push(p)).unwrap();
+        assert_eq!(archive_manifest.blobs.len(), 3);
+        assert_eq!(progress.last(), Some(&100));
+        assert_eq!(
+            read_archive_manifest(&archive_path).unwrap().model,
+            archive_manifest.model
+        );
+
+        let staging_path = dir.path().join(format!("{}test", IMPORT_STAGING_PREFIX));
+        let staged = stage_archive(&archive_path, &staging_path, |_| {}).unwrap();
+        for blob in archive_manifest.blobs.iter() {
+            assert_eq!(
+                fs::read(staged.blob_path(&blob.digest)).unwrap(),
+                fs::read(source.blob_path(&blob.digest).unwrap()).unwrap()
+            );
+        }
+
+        let create_request = create_request(&staged, "imported:latest").unwrap();
+        assert_eq!(create_request.model, "imported:latest");
+        // Only the weights are uploaded, Ollama writes a new config blob
+        assert_eq!(
+            create_request.files.values().collect::<Vec<_>>(),
+            vec![&digest_of(weights)]
+        );
+        assert_eq!(
+            create_request.parameters,
+            Some(serde_json::json!({ "temperature": 0.5 }))
+        );
+        assert!(create_request.adapters.is_none());
+
+        drop(staged);
+        assert!(!staging_path.exists());
+    }
+
+    #[test]
+    fn test_create_request_rejects_unknown_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "config": layer("application/vnd.docker.container.image.v1+json", b"{}"),
+            "layers": [layer("application/vnd.ollama.image.tensor", b"tensor")],
+        });
+        let staged = StagedModelArchive {
+            archive_manifest: ModelArchiveManifest {
+                format_version: ARCHIVE_FORMAT_VERSION,
+                model: MODEL.to_string(),
+                created_at: String::new(),
+                total_size: 0,
+                blobs: vec![],
+            },
+            manifest_bytes: serde_json::to_vec(&manifest).unwrap(),
+            staging_path: dir.path().join("staging"),
+        };
+
+        let error = create_request(&staged, MODEL).unwrap_err();
+
+        assert!(error.contains("unsupported model layer"), "{}", error);
+    }
+
+    #[test]
+    fn test_export_refuses_corrupted_blob() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = OllamaModelStore::new(dir.path().join("models"));
+        let weights = b"GGUF model weights";
+        write_model(&store, b"{}", weights);
+        // Same size, different content
+        fs::write(
+            store.blob_path(&digest_of(weights)).unwrap(),
+            b"GGUF corrupt weight",
+        )
+        .unwrap();
+        let archive_path = dir.path().join("model.tar");
+
+        let error = export_model(&store, MODEL, &archive_path, |_| {}).unwrap_err();
+
+        assert!(error.contains("integrity check failed"), "{}", error);
+        assert!(!archive_path.exists());
+        assert!(!dir.path().join("model.tar.partial").exists());
+    }
+
+    #[test]
+    fn test_stage_rejects_digest_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = OllamaModelStore::new(dir.path().join("models"));
+        write_model(&store, b"{}", b"GGUF model weights");
+        let archive_path = dir.path().join("model.tar");
+        export_model(&store, MODEL, &archive_path, |_| {}).unwrap();
+
+        // Tamper with the blob content inside the archive, tar has no checksum for it
+        let mut archive = fs::read(&archive_path).unwrap();
+        let offset = archive
+            .windows(b"model weights".len())
+            .position(|window| window == b"model weights")
+            .unwrap();
+        archive[offset] = b'M';
+        fs::write(&archive_path, archive).unwrap();
+
+        let staging_path = dir.path().join("staging");
+        let error = stage_archive(&archive_path, &staging_path, |_| {})
+            .err()
+            .unwrap();
+
+        assert!(error.contains("integrity check failed"), "{}", error);
+        assert!(!staging_path.exists());
+    }
+}
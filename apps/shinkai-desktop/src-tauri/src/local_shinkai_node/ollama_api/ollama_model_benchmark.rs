@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::ollama_api_client::OllamaApiClient;
+use super::ollama_api_types::{OllamaApiGenerateOptions, OllamaApiGenerateResponse};
+use crate::hardware::HardwareSummary;
+
+/// Prompt and embedding inputs never change so results are comparable between machines and models
+const BENCHMARK_PROMPT: &str = "Explain in three short paragraphs how a rainbow forms, \
+    mentioning refraction, reflection and dispersion of sunlight inside water droplets.";
+const BENCHMARK_NUM_PREDICT: i64 = 128;
+const BENCHMARK_SEED: i64 = 42;
+const BENCHMARK_EMBEDDING_INPUTS: [&str; 8] = [
+    "The quick brown fox jumps over the lazy dog.",
+    "Shinkai runs AI agents locally on your own hardware.",
+    "A rainbow is caused by light being refracted when entering a droplet of water.",
+    "Vector databases store embeddings to search documents by meaning.",
+    "The meeting was moved to Thursday afternoon because of the holidays.",
+    "Photosynthesis converts light energy into chemical energy stored in glucose.",
+    "Rust guarantees memory safety without a garbage collector.",
+    "Please remember to back up your node storage before upgrading.",
+];
+/// Older entries are dropped so the history file doesn't grow forever
+const BENCHMARK_HISTORY_MAX_RECORDS: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GenerationBenchmark {
+    pub load_ms: u64,
+    pub prompt_tokens: u64,
+    pub prompt_eval_tokens_per_second: f64,
+    pub generated_tokens: u64,
+    pub generation_tokens_per_second: f64,
+    pub total_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EmbeddingBenchmark {
+    pub model: String,
+    pub load_ms: u64,
+    pub inputs: usize,
+    pub tokens: u64,
+    pub inputs_per_second: f64,
+    pub tokens_per_second: f64,
+    pub total_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelBenchmark {
+    pub model: String,
+    pub ollama_version: Option<String>,
+    /// None for embedding only models
+    pub generation: Option<GenerationBenchmark>,
+    pub embedding: EmbeddingBenchmark,
+    pub duration_ms: u64,
+    /// Inference threads requested, None lets Ollama decide
+    #[serde(default)]
+    pub num_thread: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchmarkRecord {
+    pub created_at: String,
+    pub hardware: HardwareSummary,
+    pub benchmark: ModelBenchmark,
+}
+
+fn nanos_to_millis(nanos: Option<u64>) -> u64 {
+    nanos.unwrap_or_default() / 1_000_000
+}
+
+fn per_second(count: u64, duration_nanos: Option<u64>) -> f64 {
+    match duration_nanos {
+        Some(duration_nanos) if duration_nanos > 0 => {
+            count as f64 * 1_000_000_000.0 / duration_nanos as f64
+        }
+        _ => 0.0,
+    }
+}
+
+impl From<&OllamaApiGenerateResponse> for GenerationBenchmark {
+    fn from(response: &OllamaApiGenerateResponse) -> Self {
+        let prompt_tokens = response.prompt_eval_count.unwrap_or_default();
+        let generated_tokens = response.eval_count.unwrap_or_default();
+        GenerationBenchmark {
+            load_ms: nanos_to_millis(response.load_duration),
+            prompt_tokens,
+            prompt_eval_tokens_per_second: per_second(prompt_tokens, response.prompt_eval_duration),
+            generated_tokens,
+            generation_tokens_per_second: per_second(generated_tokens, response.eval_duration),
+            total_ms: nanos_to_millis(response.total_duration),
+        }
+    }
+}
+
+/// Models are unloaded first so load times are measured from a cold start
+async fn benchmark_generation(
+    ollama_api: &OllamaApiClient,
+    model: &str,
+    num_thread: Option<u32>,
+) -> Result<GenerationBenchmark, String> {
+    ollama_api.unload_model(model).await?;
+    let response = ollama_api
+        .generate(
+            model,
+            BENCHMARK_PROMPT,
+            Some(OllamaApiGenerateOptions {
+                temperature: Some(0.0),
+                seed: Some(BENCHMARK_SEED),
+                num_predict: Some(BENCHMARK_NUM_PREDICT),
+                num_thread,
+            }),
+        )
+        .await?;
+    Ok(GenerationBenchmark::from(&response))
+}
+
+async fn benchmark_embedding(
+    ollama_api: &OllamaApiClient,
+    model: &str,
+    num_thread: Option<u32>,
+) -> Result<EmbeddingBenchmark, String> {
+    // Requests with different options reload the model, load and embed share them
+    let options = num_thread.map(|num_thread| OllamaApiGenerateOptions {
+        num_thread: Some(num_thread),
+        ..Default::default()
+    });
+    ollama_api.unload_model(model).await?;
+    let load_start_time = Instant::now();
+    ollama_api.load_model(model, None, options.clone()).await?;
+    let load_ms = load_start_time.elapsed().as_millis() as u64;
+
+    let inputs: Vec<String> = BENCHMARK_EMBEDDING_INPUTS
+        .iter()
+        .map(|input| input.to_string())
+        .collect();
+    let start_time = Instant::now();
+    let response = ollama_api.embed(model, inputs, options).await?;
+    let elapsed_nanos = start_time.elapsed().as_nanos() as u64;
+    if response.embeddings.len() != BENCHMARK_EMBEDDING_INPUTS.len() {
+        return Err(format!(
+            "model {} returned {} embeddings for {} inputs",
+            model,
+            response.embeddings.len(),
+            BENCHMARK_EMBEDDING_INPUTS.len()
+        ));
+    }
+    let duration_nanos = response.total_duration.or(Some(elapsed_nanos));
+    let tokens = response.prompt_eval_count.unwrap_or_default();
+    Ok(EmbeddingBenchmark {
+        model: model.to_string(),
+        load_ms,
+        inputs: BENCHMARK_EMBEDDING_INPUTS.len(),
+        tokens,
+        inputs_per_second: per_second(BENCHMARK_EMBEDDING_INPUTS.len() as u64, duration_nanos),
+        tokens_per_second: per_second(tokens, duration_nanos),
+        total_ms: nanos_to_millis(duration_nanos),
+    })
+}
+
+/// Runs the fixed prompt against `model` and the fixed inputs against `embedding_model`.
+/// When `model` can't generate it's considered an embedding model and only that part runs.
+pub async fn run_benchmark(
+    ollama_api: &OllamaApiClient,
+    model: &str,
+    embedding_model: &str,
+    num_thread: Option<u32>,
+) -> Result<ModelBenchmark, String> {
+    let start_time = Instant::now();
+    let ollama_version = ollama_api.get_ollama_version().await.ok();
+    let (generation, embedding_model) =
+        match benchmark_generation(ollama_api, model, num_thread).await {
+            Ok(generation) => (Some(generation), embedding_model),
+            Err(e) if e.contains("does not support generate") => (None, model),
+            Err(e) => return Err(e),
+        };
+    let embedding = benchmark_embedding(ollama_api, embedding_model, num_thread).await?;
+    let benchmark = ModelBenchmark {
+        model: model.to_string(),
+        ollama_version,
+        generation,
+        embedding,
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        num_thread,
+    };
+    info!("benchmark results {:?}", benchmark);
+    Ok(benchmark)
+}
+
+/// Records ordered from the oldest to the newest, a missing file is an empty history
+pub fn read_benchmark_history(path: &Path) -> Result<Vec<BenchmarkRecord>, String> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read benchmark history {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| {
+        format!(
+            "failed to parse benchmark history {}: {}",
+            path.display(),
+            e
+        )
+    })
+}
+
+pub fn append_benchmark_history(path: &Path, record: BenchmarkRecord) -> Result<(), String> {
+    let mut records = read_benchmark_history(path)?;
+    records.push(record);
+    if records.len() > BENCHMARK_HISTORY_MAX_RECORDS {
+        records.drain(..records.len() - BENCHMARK_HISTORY_MAX_RECORDS);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(&records).map_err(|e| e.to_string())?;
+    let partial_path = path.with_extension("json.partial");
+    fs::write(&partial_path, content)
+        .map_err(|e| format!("failed to write benchmark history: {}", e))?;
+    fs::rename(&partial_path, path).map_err(|e| format!("failed to write benchmark history: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_benchmark_rates() {
+        let response: OllamaApiGenerateResponse = serde_json::from_str(
+            r#"{"model":"llama3.2:3b","response":"...","done":true,"total_duration":5500000000,
+            "load_duration":2000000000,"prompt_eval_count":40,"prompt_eval_duration":500000000,
+            "eval_count":128,"eval_duration":3200000000}"#,
+        )
+        .unwrap();
+        let generation = GenerationBenchmark::from(&response);
+        assert_eq!(generation.load_ms, 2000);
+        assert_eq!(generation.total_ms, 5500);
+        assert_eq!(generation.prompt_eval_tokens_per_second, 80.0);
+        assert_eq!(generation.generation_tokens_per_second, 40.0);
+    }
+
+    #[test]
+    fn test_missing_durations_do_not_divide_by_zero() {
+        assert_eq!(per_second(10, None), 0.0);
+        assert_eq!(per_second(10, Some(0)), 0.0);
+    }
+}
@@ -0,0 +1,425 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+use super::ollama_api_client::OllamaApiClient;
+use super::ollama_api_types::RunningModel;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LoadedModel {
+    pub model: String,
+    pub parameter_size: String,
+    pub quantization_level: String,
+    pub size_bytes: u64,
+    pub vram_bytes: u64,
+    pub ram_bytes: u64,
+    pub expires_at: String,
+}
+
+impl From<&RunningModel> for LoadedModel {
+    fn from(running_model: &RunningModel) -> Self {
+        LoadedModel {
+            model: running_model.model.clone(),
+            parameter_size: running_model.details.parameter_size.clone(),
+            quantization_level: running_model.details.quantization_level.clone(),
+            size_bytes: running_model.size,
+            vram_bytes: running_model.size_vram,
+            ram_bytes: running_model.size.saturating_sub(running_model.size_vram),
+            expires_at: running_model.expires_at.clone(),
+        }
+    }
+}
+
+/// Unloads every loaded model, models serving a request are released by ollama once it finishes.
+/// `on_unloaded` is called once per model with the result of its unload request.
+pub async fn unload_all_models(
+    ollama_api: &OllamaApiClient,
+    on_unloaded: impl Fn(&str, &Result<(), String>),
+) -> Result<Vec<String>, String> {
+    let running_models = ollama_api.ps().await?;
+    let mut unloaded_models = Vec::new();
+    for running_model in running_models.models {
+        let result = ollama_api.unload_model(&running_model.model).await;
+        on_unloaded(&running_model.model, &result);
+        match result {
+            Ok(_) => unloaded_models.push(running_model.model),
+            Err(e) => warn!("failed to unload model {}: {}", running_model.model, e),
+        }
+    }
+    Ok(unloaded_models)
+}
+
+/// Loaded models along with their `expires_at`, it changes whenever a request is served
+#[derive(Default)]
+struct LoadedModelsFingerprint(Vec<(String, String)>);
+
+impl LoadedModelsFingerprint {
+    /// Returns true when `running_models` shows activity since the previous update. Nothing loaded
+    /// counts as activity too, there is nothing to unload so the countdown starts over.
+    fn update(&mut self, mut running_models: Vec<(String, String)>) -> bool {
+        running_models.sort();
+        let changed = running_models != self.0 || running_models.is_empty();
+        self.0 = running_models;
+        changed
+    }
+}
+
+/// Unloads every model after `idle_timeout` without requests. Requests don't go through the app
+/// so activity is detected polling `/api/ps`, ollama moves `expires_at` forward every time a
+/// request finishes.
+pub struct IdleUnloadMonitor {
+    idle_timeout: Duration,
+    last_activity: Arc<Mutex<Instant>>,
+    handle: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl IdleUnloadMonitor {
+    const POLL_INTERVAL_MS: u64 = 30000;
+
+    pub fn start(
+        ollama_api_base_url: String,
+        idle_timeout: Duration,
+        on_unloaded: impl Fn(&str, &Result<(), String>) + Send + Sync + 'static,
+    ) -> Self {
+        let last_activity = Arc::new(Mutex::new(Instant::now()));
+        let task_last_activity = last_activity.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            let ollama_api = OllamaApiClient::new(ollama_api_base_url);
+            let mut loaded_models = LoadedModelsFingerprint::default();
+            loop {
+                tokio::time::sleep(Duration::from_millis(Self::POLL_INTERVAL_MS)).await;
+                let running_models = match ollama_api.ps().await {
+                    Ok(response) => response
+                        .models
+                        .into_iter()
+                        .map(|m| (m.model, m.expires_at))
+                        .collect(),
+                    // Ollama is stopped so there is nothing loaded
+                    Err(_) => vec![],
+                };
+                if loaded_models.update(running_models) {
+                    *task_last_activity.lock().unwrap() = Instant::now();
+                    continue;
+                }
+                let idle_time = task_last_activity.lock().unwrap().elapsed();
+                if idle_time < idle_timeout {
+                    continue;
+                }
+                info!(
+                    "unloading models after {}s without requests",
+                    idle_time.as_secs()
+                );
+                if let Err(e) = unload_all_models(&ollama_api, &on_unloaded).await {
+                    warn!("failed to unload idle models: {}", e);
+                }
+                loaded_models = LoadedModelsFingerprint::default();
+                *task_last_activity.lock().unwrap() = Instant::now();
+            }
+        });
+        IdleUnloadMonitor {
+            idle_timeout,
+            last_activity,
+            handle,
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Restarts the idle countdown, used for requests the app sends by itself
+    pub fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+}
+
+impl Drop for IdleUnloadMonitor {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryPressureOptions {
+    /// Memory is under pressure when the available system memory drops below this
+    pub min_available_memory_bytes: u64,
+    /// Refuse the model loads the app requests while under pressure, loads the node requests go
+    /// straight to ollama
+    #[serde(default)]
+    pub block_model_loads: bool,
+}
+
+impl MemoryPressureOptions {
+    const MIN_AVAILABLE_MEMORY_BYTES: u64 = 1024 * 1024 * 1024;
+
+    /// 10% of the system memory with at least 1GiB
+    pub fn for_total_memory(total_memory_bytes: u64) -> Self {
+        MemoryPressureOptions {
+            min_available_memory_bytes: (total_memory_bytes / 10)
+                .max(Self::MIN_AVAILABLE_MEMORY_BYTES),
+            block_model_loads: false,
+        }
+    }
+}
+
+/// Memory settings the user opted into, both are off until enabled
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OllamaMemorySettings {
+    /// Minutes without requests before every model is unloaded
+    pub idle_unload_timeout_minutes: Option<u64>,
+    pub memory_pressure: Option<MemoryPressureOptions>,
+}
+
+impl OllamaMemorySettings {
+    /// Settings saved by the last `save`, None when there are none or they are invalid
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content)
+            .map_err(|e| warn!("ignoring invalid memory settings {}: {}", path.display(), e))
+            .ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize memory settings: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("failed to save memory settings: {}", e))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MemoryPressure {
+    pub under_pressure: bool,
+    pub available_memory_bytes: u64,
+    pub total_memory_bytes: u64,
+    pub min_available_memory_bytes: u64,
+    /// Models unloaded on this check
+    pub unloaded_models: Vec<String>,
+}
+
+/// Pressure ends once memory gets a quarter above the threshold so it doesn't flap around it
+fn is_under_pressure(
+    was_under_pressure: bool,
+    available_memory_bytes: u64,
+    threshold: u64,
+) -> bool {
+    if was_under_pressure {
+        available_memory_bytes < threshold + threshold / 4
+    } else {
+        available_memory_bytes < threshold
+    }
+}
+
+/// Biggest model without requests since the previous check, ollama moves `expires_at` forward
+/// every time a request finishes
+fn largest_idle_model(previous: &[LoadedModel], current: &[LoadedModel]) -> Option<String> {
+    current
+        .iter()
+        .filter(|model| {
+            previous
+                .iter()
+                .any(|p| p.model == model.model && p.expires_at == model.expires_at)
+        })
+        // Total size, GPUs with unified memory report the whole model as vram
+        .max_by_key(|model| model.size_bytes)
+        .map(|model| model.model.clone())
+}
+
+/// Watches the available system memory and unloads idle models, one per check and the biggest
+/// first, while it's below the threshold so the OS doesn't start swapping
+pub struct MemoryPressureMonitor {
+    options: MemoryPressureOptions,
+    under_pressure: Arc<AtomicBool>,
+    handle: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl MemoryPressureMonitor {
+    const POLL_INTERVAL_MS: u64 = 5000;
+
+    /// `on_pressure` is called when pressure starts, ends and every time a model is unloaded
+    pub fn start(
+        ollama_api_base_url: String,
+        options: MemoryPressureOptions,
+        on_pressure: impl Fn(&MemoryPressure) + Send + Sync + 'static,
+        on_unloaded: impl Fn(&str, &Result<(), String>) + Send + Sync + 'static,
+    ) -> Self {
+        let under_pressure = Arc::new(AtomicBool::new(false));
+        let task_under_pressure = under_pressure.clone();
+        let threshold = options.min_available_memory_bytes;
+        let handle = tauri::async_runtime::spawn(async move {
+            let ollama_api = OllamaApiClient::new(ollama_api_base_url);
+            let mut system = System::new();
+            let mut previous_models: Vec<LoadedModel> = Vec::new();
+            loop {
+                tokio::time::sleep(Duration::from_millis(Self::POLL_INTERVAL_MS)).await;
+                system.refresh_memory();
+                let available_memory_bytes = system.available_memory();
+                let was_under_pressure = task_under_pressure.load(Ordering::Relaxed);
+                let under_pressure =
+                    is_under_pressure(was_under_pressure, available_memory_bytes, threshold);
+                task_under_pressure.store(under_pressure, Ordering::Relaxed);
+
+                let mut unloaded_models = Vec::new();
+                if under_pressure {
+                    let models: Vec<LoadedModel> = match ollama_api.ps().await {
+                        Ok(response) => response.models.iter().map(LoadedModel::from).collect(),
+                        Err(_) => vec![],
+                    };
+                    if let Some(model) = largest_idle_model(&previous_models, &models) {
+                        let result = ollama_api.unload_model(&model).await;
+                        on_unloaded(&model, &result);
+                        match result {
+                            Ok(_) => unloaded_models.push(model),
+                            Err(e) => warn!("failed to unload model {}: {}", model, e),
+                        }
+                    }
+                    previous_models = models;
+                } else {
+                    previous_models.clear();
+                }
+
+                if under_pressure != was_under_pressure || !unloaded_models.is_empty() {
+                    if under_pressure != was_under_pressure {
+                        warn!(
+                            "memory pressure {}, {} bytes available with a threshold of {} bytes",
+                            if under_pressure { "started" } else { "ended" },
+                            available_memory_bytes,
+                            threshold
+                        );
+                    }
+                    on_pressure(&MemoryPressure {
+                        under_pressure,
+                        available_memory_bytes,
+                        total_memory_bytes: system.total_memory(),
+                        min_available_memory_bytes: threshold,
+                        unloaded_models,
+                    });
+                }
+            }
+        });
+        MemoryPressureMonitor {
+            options,
+            under_pressure,
+            handle,
+        }
+    }
+
+    pub fn options(&self) -> &MemoryPressureOptions {
+        &self.options
+    }
+
+    pub fn is_under_pressure(&self) -> bool {
+        self.under_pressure.load(Ordering::Relaxed)
+    }
+
+    /// Whether the app should refuse to load models right now
+    pub fn blocks_model_loads(&self) -> bool {
+        self.options.block_model_loads && self.is_under_pressure()
+    }
+}
+
+impl Drop for MemoryPressureMonitor {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loaded_model(model: &str, expires_at: &str, ram_bytes: u64) -> LoadedModel {
+        LoadedModel {
+            model: model.to_string(),
+            parameter_size: String::new(),
+            quantization_level: String::new(),
+            size_bytes: ram_bytes,
+            vram_bytes: 0,
+            ram_bytes,
+            expires_at: expires_at.to_string(),
+        }
+    }
+
+    fn running(models: &[(&str, &str)]) -> Vec<(String, String)> {
+        models
+            .iter()
+            .map(|(model, expires_at)| (model.to_string(), expires_at.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fingerprint_detects_served_requests() {
+        let mut loaded_models = LoadedModelsFingerprint::default();
+        assert!(loaded_models.update(running(&[("llama3.1:8b", "10:00")])));
+        assert!(!loaded_models.update(running(&[("llama3.1:8b", "10:00")])));
+        // A request finished and moved expires_at forward
+        assert!(loaded_models.update(running(&[("llama3.1:8b", "10:05")])));
+        assert!(!loaded_models.update(running(&[("llama3.1:8b", "10:05")])));
+        // A new model was loaded
+        assert!(loaded_models.update(running(&[
+            ("llama3.1:8b", "10:05"),
+            ("snowflake-arctic-embed:xs", "10:06"),
+        ])));
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_order_and_never_idles_empty() {
+        let mut loaded_models = LoadedModelsFingerprint::default();
+        loaded_models.update(running(&[("a:1b", "10:00"), ("b:1b", "10:00")]));
+        assert!(!loaded_models.update(running(&[("b:1b", "10:00"), ("a:1b", "10:00")])));
+        assert!(loaded_models.update(vec![]));
+        assert!(loaded_models.update(vec![]));
+    }
+
+    #[test]
+    fn test_pressure_recovers_above_threshold_margin() {
+        assert!(is_under_pressure(false, 900, 1000));
+        assert!(!is_under_pressure(false, 1100, 1000));
+        assert!(is_under_pressure(true, 1100, 1000));
+        assert!(!is_under_pressure(true, 1300, 1000));
+    }
+
+    #[test]
+    fn test_largest_idle_model_skips_models_in_use() {
+        let previous = vec![
+            loaded_model("llama3.1:8b", "10:00", 5000),
+            loaded_model("qwen2.5:14b", "10:00", 9000),
+            loaded_model("snowflake-arctic-embed:xs", "10:00", 100),
+        ];
+        let current = vec![
+            loaded_model("llama3.1:8b", "10:00", 5000),
+            // Served a request since the previous check
+            loaded_model("qwen2.5:14b", "10:05", 9000),
+            loaded_model("snowflake-arctic-embed:xs", "10:00", 100),
+        ];
+        assert_eq!(
+            largest_idle_model(&previous, &current).as_deref(),
+            Some("llama3.1:8b")
+        );
+        assert_eq!(largest_idle_model(&[], &current), None);
+    }
+
+    #[test]
+    fn test_memory_settings_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("ollama-memory-settings.json");
+        assert_eq!(OllamaMemorySettings::load(&path), None);
+
+        let settings = OllamaMemorySettings {
+            idle_unload_timeout_minutes: Some(15),
+            memory_pressure: Some(MemoryPressureOptions::for_total_memory(
+                32 * 1024 * 1024 * 1024,
+            )),
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(OllamaMemorySettings::load(&path), Some(settings));
+
+        fs::write(&path, "{\"idle_unload_timeout_minutes\": \"15\"}").unwrap();
+        assert_eq!(OllamaMemorySettings::load(&path), None);
+    }
+}
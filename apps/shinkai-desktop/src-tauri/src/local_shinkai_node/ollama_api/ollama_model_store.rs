@@ -0,0 +1,461 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_REGISTRY_HOST: &str = "registry.ollama.ai";
+pub const DEFAULT_NAMESPACE: &str = "library";
+pub const DEFAULT_TAG: &str = "latest";
+/// Prefix of the folders where model archives are unpacked before being uploaded
+pub const IMPORT_STAGING_PREFIX: &str = ".shinkai-import-";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaManifestLayer {
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+}
+
+/// Subset of the OCI-like manifest Ollama writes for every local model
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaManifest {
+    pub schema_version: u32,
+    pub media_type: Option<String>,
+    pub config: OllamaManifestLayer,
+    pub layers: Vec<OllamaManifestLayer>,
+}
+
+impl OllamaManifest {
+    /// Config and layers, deduplicated by digest
+    pub fn blobs(&self) -> Vec<OllamaManifestLayer> {
+        let mut blobs: Vec<OllamaManifestLayer> = Vec::new();
+        for layer in std::iter::once(&self.config).chain(self.layers.iter()) {
+            if !blobs.iter().any(|b| b.digest == layer.digest) {
+                blobs.push(layer.clone());
+            }
+        }
+        blobs
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.blobs().iter().map(|b| b.size).sum()
+    }
+}
+
+/// Fully qualified model name as Ollama lays it out on disk: host/namespace/model:tag
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OllamaModelName {
+    pub host: String,
+    pub namespace: String,
+    pub model: String,
+    pub tag: String,
+}
+
+impl OllamaModelName {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Self::parse_with_default_host(name, DEFAULT_REGISTRY_HOST)
+    }
+
+    /// Names without an explicit registry host resolve to `default_host`, eg: an internal mirror
+    pub fn parse_with_default_host(name: &str, default_host: &str) -> Result<Self, String> {
+        let name = name.trim();
+        // Digests (model@sha256:...) are not addressable through the manifests folder
+        let name = name.split('@').next().unwrap_or_default();
+        let (path, tag) = match name.rfind(':') {
+            Some(index) if index > name.rfind('/').unwrap_or(0) => {
+                (&name[..index], &name[index + 1..])
+            }
+            _ => (name, DEFAULT_TAG),
+        };
+        let parts: Vec<&str> = path.split('/').collect();
+        let (host, namespace, model) = match parts.as_slice() {
+            [model] => (default_host, DEFAULT_NAMESPACE, *model),
+            [namespace, model] => (default_host, *namespace, *model),
+            [host, namespace, model] => (*host, *namespace, *model),
+            _ => return Err(format!("invalid model name {}", name)),
+        };
+        if [host, namespace, model, tag]
+            .iter()
+            .any(|part| part.is_empty() || *part == "." || *part == "..")
+        {
+            return Err(format!("invalid model name {}", name));
+        }
+        Ok(OllamaModelName {
+            host: host.to_string(),
+            namespace: namespace.to_string(),
+            model: model.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+
+    /// Short form used by `/api/tags`, eg: library models are listed without host and namespace
+    pub fn display_name(&self) -> String {
+        if self.host == DEFAULT_REGISTRY_HOST && self.namespace == DEFAULT_NAMESPACE {
+            format!("{}:{}", self.model, self.tag)
+        } else if self.host == DEFAULT_REGISTRY_HOST {
+            format!("{}/{}:{}", self.namespace, self.model, self.tag)
+        } else {
+            format!(
+                "{}/{}/{}:{}",
+                self.host, self.namespace, self.model, self.tag
+            )
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoreEntry {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoreEntryError {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PartialDownloadsCleanup {
+    pub removed: Vec<StoreEntry>,
+    /// Recently modified partial files, most likely a download still in progress
+    pub skipped: Vec<StoreEntry>,
+    /// Partial files that couldn't be inspected or removed, the rest are cleaned up anyway
+    pub failed: Vec<StoreEntryError>,
+    pub freed_bytes: u64,
+}
+
+/// Size and last modification of a file, or of the files directly inside a folder
+fn entry_size_and_modified(path: &Path) -> Result<(u64, SystemTime), String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let mut size = if metadata.is_file() {
+        metadata.len()
+    } else {
+        0
+    };
+    let mut modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    if metadata.is_dir() {
+        let entries =
+            fs::read_dir(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        for entry_metadata in entries.flatten().filter_map(|entry| entry.metadata().ok()) {
+            size += entry_metadata.len();
+            if let Ok(entry_modified) = entry_metadata.modified() {
+                modified = modified.max(entry_modified);
+            }
+        }
+    }
+    Ok((size, modified))
+}
+
+/// Model display name with its manifest, or the reason it couldn't be read
+pub type ManifestEntry = (String, Result<OllamaManifest, String>);
+
+/// Read/write access to the folder where Ollama keeps manifests and blobs (OLLAMA_MODELS)
+pub struct OllamaModelStore {
+    models_path: PathBuf,
+}
+
+impl OllamaModelStore {
+    pub fn new(models_path: PathBuf) -> Self {
+        OllamaModelStore { models_path }
+    }
+
+    pub fn models_path(&self) -> &Path {
+        &self.models_path
+    }
+
+    pub fn manifests_path(&self) -> PathBuf {
+        self.models_path.join("manifests")
+    }
+
+    pub fn blobs_path(&self) -> PathBuf {
+        self.models_path.join("blobs")
+    }
+
+    pub fn manifest_path(&self, model_name: &OllamaModelName) -> PathBuf {
+        self.manifests_path()
+            .join(&model_name.host)
+            .join(&model_name.namespace)
+            .join(&model_name.model)
+            .join(&model_name.tag)
+    }
+
+    /// Blobs are stored as sha256-<hex> because ':' is not a valid file name character on windows
+    pub fn blob_path(&self, digest: &str) -> Result<PathBuf, String> {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .ok_or_else(|| format!("unsupported digest {}", digest))?;
+        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid digest {}", digest));
+        }
+        Ok(self.blobs_path().join(format!("sha256-{}", hex)))
+    }
+
+    /// Every manifest in the store as (model name, parsed manifest), following the
+    /// manifests/<host>/<namespace>/<model>/<tag> layout
+    pub fn list_manifests(&self) -> Result<Vec<ManifestEntry>, String> {
+        fn sub_entries(path: &Path) -> Result<Vec<fs::DirEntry>, String> {
+            match fs::read_dir(path) {
+                Ok(entries) => Ok(entries.flatten().collect()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+                Err(e) => Err(format!("failed to read {}: {}", path.display(), e)),
+            }
+        }
+
+        let mut manifests = Vec::new();
+        for host in sub_entries(&self.manifests_path())? {
+            for namespace in sub_entries(&host.path())? {
+                for model in sub_entries(&namespace.path())? {
+                    for tag in sub_entries(&model.path())? {
+                        let tag_name = tag.file_name().to_string_lossy().to_string();
+                        if !tag.path().is_file() || tag_name.ends_with(".partial") {
+                            continue;
+                        }
+                        let model_name = OllamaModelName {
+                            host: host.file_name().to_string_lossy().to_string(),
+                            namespace: namespace.file_name().to_string_lossy().to_string(),
+                            model: model.file_name().to_string_lossy().to_string(),
+                            tag: tag_name,
+                        };
+                        let manifest =
+                            fs::read(tag.path())
+                                .map_err(|e| e.to_string())
+                                .and_then(|bytes| {
+                                    serde_json::from_slice::<OllamaManifest>(&bytes)
+                                        .map_err(|e| e.to_string())
+                                });
+                        manifests.push((model_name.display_name(), manifest));
+                    }
+                }
+            }
+        }
+        Ok(manifests)
+    }
+
+    /// Complete blobs in the store as (digest, size, last modification), partial downloads excluded
+    pub fn list_blobs(&self) -> Result<Vec<(String, u64, SystemTime)>, String> {
+        let entries = match fs::read_dir(self.blobs_path()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(format!("failed to read blobs folder: {}", e)),
+        };
+        let mut blobs = Vec::new();
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(hex) = file_name.strip_prefix("sha256-") else {
+                continue;
+            };
+            if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            blobs.push((
+                format!("sha256:{}", hex),
+                metadata.len(),
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            ));
+        }
+        Ok(blobs)
+    }
+
+    pub fn has_blob(&self, digest: &str) -> bool {
+        self.blob_path(digest)
+            .map(|blob_path| blob_path.is_file())
+            .unwrap_or(false)
+    }
+
+    /// Ollama downloads blobs as sha256-<hex>-partial(-N) files and model imports stage blobs in
+    /// `IMPORT_STAGING_PREFIX` folders, both are left behind when a process is interrupted
+    fn partial_downloads(&self) -> Vec<PathBuf> {
+        let partial_blobs = fs::read_dir(self.blobs_path())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().contains("-partial"));
+        let import_staging_folders = fs::read_dir(&self.models_path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(IMPORT_STAGING_PREFIX)
+            });
+        partial_blobs
+            .chain(import_staging_folders)
+            .map(|entry| entry.path())
+            .collect()
+    }
+
+    /// Removes partial downloads not modified during the last `stale_after`, a file that can't be
+    /// removed is reported in `failed` without stopping the cleanup
+    pub fn remove_stale_partial_downloads(&self, stale_after: Duration) -> PartialDownloadsCleanup {
+        let mut cleanup = PartialDownloadsCleanup::default();
+        let now = SystemTime::now();
+        for path in self.partial_downloads() {
+            let (size, modified) = match entry_size_and_modified(&path) {
+                Ok(size_and_modified) => size_and_modified,
+                Err(error) => {
+                    log::warn!("failed to inspect partial download: {}", error);
+                    cleanup.failed.push(StoreEntryError {
+                        path: path.to_string_lossy().to_string(),
+                        error,
+                    });
+                    continue;
+                }
+            };
+            let entry = StoreEntry {
+                path: path.to_string_lossy().to_string(),
+                size,
+            };
+            let age = now.duration_since(modified).unwrap_or_default();
+            if age < stale_after {
+                log::info!(
+                    "skipping partial download {} modified {}s ago",
+                    entry.path,
+                    age.as_secs()
+                );
+                cleanup.skipped.push(entry);
+                continue;
+            }
+            let result = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            };
+            if let Err(e) = result {
+                let error = format!("failed to remove {}: {}", entry.path, e);
+                log::warn!("{}", error);
+                cleanup.failed.push(StoreEntryError {
+                    path: entry.path,
+                    error,
+                });
+                continue;
+            }
+            log::info!("removed partial download {} ({} bytes)", entry.path, size);
+            cleanup.freed_bytes += size;
+            cleanup.removed.push(entry);
+        }
+        cleanup
+    }
+
+    pub fn read_manifest_bytes(&self, model: &str) -> Result<Vec<u8>, String> {
+        let model_name = OllamaModelName::parse(model)?;
+        let manifest_path = self.manifest_path(&model_name);
+        fs::read(&manifest_path).map_err(|e| {
+            format!(
+                "failed to read manifest for model {} at {}: {}",
+                model,
+                manifest_path.display(),
+                e
+            )
+        })
+    }
+
+    pub fn read_manifest(&self, model: &str) -> Result<OllamaManifest, String> {
+        let manifest_bytes = self.read_manifest_bytes(model)?;
+        serde_json::from_slice(&manifest_bytes)
+            .map_err(|e| format!("failed to parse manifest for model {}: {}", model, e))
+    }
+
+    /// Writes the manifest verbatim so its digests stay identical to the source machine
+    pub fn write_manifest_bytes(&self, model: &str, manifest_bytes: &[u8]) -> Result<(), String> {
+        let model_name = OllamaModelName::parse(model)?;
+        let manifest_path = self.manifest_path(&model_name);
+        if let Some(parent) = manifest_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create manifest directory: {}", e))?;
+        }
+        let partial_path = manifest_path.with_file_name(format!("{}.partial", model_name.tag));
+        fs::write(&partial_path, manifest_bytes)
+            .map_err(|e| format!("failed to write manifest: {}", e))?;
+        fs::rename(&partial_path, &manifest_path)
+            .map_err(|e| format!("failed to move manifest into place: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARTIAL_BLOB: &str =
+        "sha256-6a0746a1ec1aef3e7ec53868f220ff6e389f6f8ef87a01d77c96807de94ca2aa-partial";
+    const STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        fs::File::open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_removes_only_stale_partial_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = OllamaModelStore::new(dir.path().to_path_buf());
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(60 * 60);
+        fs::create_dir_all(store.blobs_path()).unwrap();
+
+        let stale_blob = store.blobs_path().join(PARTIAL_BLOB);
+        fs::write(&stale_blob, b"stale").unwrap();
+        set_modified(&stale_blob, an_hour_ago);
+        let fresh_blob = store.blobs_path().join(format!("{}-1", PARTIAL_BLOB));
+        fs::write(&fresh_blob, b"in progress").unwrap();
+        let stale_staging = dir.path().join(format!("{}1", IMPORT_STAGING_PREFIX));
+        fs::create_dir_all(&stale_staging).unwrap();
+        let staged_blob = stale_staging.join("sha256-staged");
+        fs::write(&staged_blob, b"staged").unwrap();
+        set_modified(&staged_blob, an_hour_ago);
+        set_modified(&stale_staging, an_hour_ago);
+        let complete_blob = store
+            .blobs_path()
+            .join(PARTIAL_BLOB.trim_end_matches("-partial"));
+        fs::write(&complete_blob, b"complete").unwrap();
+        set_modified(&complete_blob, an_hour_ago);
+
+        let cleanup = store.remove_stale_partial_downloads(STALE_AFTER);
+
+        assert_eq!(cleanup.removed.len(), 2);
+        assert_eq!(cleanup.freed_bytes, 11);
+        assert_eq!(cleanup.skipped.len(), 1);
+        assert!(cleanup.failed.is_empty());
+        assert!(!stale_blob.exists());
+        assert!(!stale_staging.exists());
+        assert!(fresh_blob.exists());
+        assert!(complete_blob.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cleanup_continues_after_a_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = OllamaModelStore::new(dir.path().to_path_buf());
+        fs::create_dir_all(store.blobs_path()).unwrap();
+        // A dangling link can't be inspected
+        let broken_blob = store.blobs_path().join(format!("{}-1", PARTIAL_BLOB));
+        std::os::unix::fs::symlink(dir.path().join("missing"), &broken_blob).unwrap();
+        let stale_blob = store.blobs_path().join(PARTIAL_BLOB);
+        fs::write(&stale_blob, b"stale").unwrap();
+        set_modified(
+            &stale_blob,
+            SystemTime::now() - Duration::from_secs(60 * 60),
+        );
+
+        let cleanup = store.remove_stale_partial_downloads(STALE_AFTER);
+
+        assert_eq!(cleanup.failed.len(), 1);
+        assert_eq!(cleanup.failed[0].path, broken_blob.to_string_lossy());
+        assert_eq!(cleanup.removed.len(), 1);
+        assert!(!stale_blob.exists());
+    }
+}
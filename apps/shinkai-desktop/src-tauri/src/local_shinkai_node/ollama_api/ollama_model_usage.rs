@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::ollama_api_types::OllamaApiTagsResponse;
+use super::ollama_model_store::{OllamaModelStore, StoreEntry};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelUsage {
+    pub model: String,
+    /// Size reported by `/api/tags`, None when ollama isn't running
+    pub size: Option<u64>,
+    pub parameter_size: Option<String>,
+    pub quantization_level: Option<String>,
+    /// Bytes of every blob referenced by the model manifest
+    pub disk_bytes: u64,
+    /// Bytes that would be freed by removing only this model
+    pub unique_bytes: u64,
+    pub shared_bytes: u64,
+    pub missing_blobs: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SharedBlob {
+    pub digest: String,
+    pub size: u64,
+    pub models: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelStoreUsage {
+    pub models_path: String,
+    pub models: Vec<ModelUsage>,
+    pub shared_blobs: Vec<SharedBlob>,
+    pub unreferenced_blobs: Vec<StoreEntry>,
+    /// Manifests that couldn't be parsed, garbage collection refuses to run while there are any
+    pub invalid_manifests: Vec<String>,
+    pub total_bytes: u64,
+    pub unreferenced_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelGarbageCollection {
+    pub dry_run: bool,
+    pub removed: Vec<StoreEntry>,
+    /// Unreferenced blobs modified recently, they may belong to a pull that didn't write its manifest yet
+    pub skipped: Vec<StoreEntry>,
+    pub freed_bytes: u64,
+}
+
+/// Cross references every manifest in the store with the blobs folder, `tags` enriches models with
+/// the metadata ollama reports when it's running
+pub fn analyze_model_store(
+    store: &OllamaModelStore,
+    tags: Option<&OllamaApiTagsResponse>,
+) -> Result<ModelStoreUsage, String> {
+    let blobs = store.list_blobs()?;
+    let blob_sizes: HashMap<&str, u64> = blobs
+        .iter()
+        .map(|(digest, size, _)| (digest.as_str(), *size))
+        .collect();
+
+    let mut invalid_manifests = Vec::new();
+    let mut model_blobs: Vec<(String, Vec<String>)> = Vec::new();
+    for (model, manifest) in store.list_manifests()? {
+        match manifest {
+            Ok(manifest) => model_blobs.push((
+                model,
+                manifest
+                    .blobs()
+                    .into_iter()
+                    .map(|blob| blob.digest)
+                    .collect(),
+            )),
+            Err(e) => {
+                warn!("invalid manifest for model {}: {}", model, e);
+                invalid_manifests.push(model);
+            }
+        }
+    }
+
+    let mut blob_models: HashMap<&str, Vec<String>> = HashMap::new();
+    for (model, digests) in model_blobs.iter() {
+        for digest in digests {
+            blob_models
+                .entry(digest.as_str())
+                .or_default()
+                .push(model.clone());
+        }
+    }
+
+    let models = model_blobs
+        .iter()
+        .map(|(model, digests)| {
+            let tag = tags.and_then(|tags| tags.models.iter().find(|m| &m.model == model));
+            let mut usage = ModelUsage {
+                model: model.clone(),
+                size: tag.map(|tag| tag.size),
+                parameter_size: tag.map(|tag| tag.details.parameter_size.clone()),
+                quantization_level: tag.map(|tag| tag.details.quantization_level.clone()),
+                disk_bytes: 0,
+                unique_bytes: 0,
+                shared_bytes: 0,
+                missing_blobs: vec![],
+            };
+            for digest in digests {
+                let Some(size) = blob_sizes.get(digest.as_str()) else {
+                    usage.missing_blobs.push(digest.clone());
+                    continue;
+                };
+                usage.disk_bytes += size;
+                if blob_models.get(digest.as_str()).map_or(0, |m| m.len()) > 1 {
+                    usage.shared_bytes += size;
+                } else {
+                    usage.unique_bytes += size;
+                }
+            }
+            usage
+        })
+        .collect();
+
+    let shared_blobs = blob_models
+        .iter()
+        .filter(|(_, models)| models.len() > 1)
+        .map(|(digest, models)| SharedBlob {
+            digest: digest.to_string(),
+            size: blob_sizes.get(digest).copied().unwrap_or_default(),
+            models: models.clone(),
+        })
+        .collect();
+
+    let unreferenced_blobs: Vec<StoreEntry> = blobs
+        .iter()
+        .filter(|(digest, _, _)| !blob_models.contains_key(digest.as_str()))
+        .filter_map(|(digest, size, _)| {
+            Some(StoreEntry {
+                path: store.blob_path(digest).ok()?.to_string_lossy().to_string(),
+                size: *size,
+            })
+        })
+        .collect();
+
+    Ok(ModelStoreUsage {
+        models_path: store.models_path().to_string_lossy().to_string(),
+        models,
+        shared_blobs,
+        total_bytes: blobs.iter().map(|(_, size, _)| size).sum(),
+        unreferenced_bytes: unreferenced_blobs.iter().map(|blob| blob.size).sum(),
+        unreferenced_blobs,
+        invalid_manifests,
+    })
+}
+
+/// Removes blobs no manifest references. Blobs modified during the last `stale_after` are kept
+/// because pulls write blobs before their manifest.
+pub fn collect_garbage(
+    store: &OllamaModelStore,
+    dry_run: bool,
+    stale_after: Duration,
+) -> Result<ModelGarbageCollection, String> {
+    let usage = analyze_model_store(store, None)?;
+    if !usage.invalid_manifests.is_empty() {
+        return Err(format!(
+            "refusing to collect garbage while some manifests can't be read: {}",
+            usage.invalid_manifests.join(", ")
+        ));
+    }
+
+    let mut garbage_collection = ModelGarbageCollection {
+        dry_run,
+        removed: vec![],
+        skipped: vec![],
+        freed_bytes: 0,
+    };
+    let now = SystemTime::now();
+    for blob in usage.unreferenced_blobs {
+        let modified = fs::metadata(&blob.path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(now);
+        if now.duration_since(modified).unwrap_or_default() < stale_after {
+            garbage_collection.skipped.push(blob);
+            continue;
+        }
+        if !dry_run {
+            fs::remove_file(&blob.path)
+                .map_err(|e| format!("failed to remove blob {}: {}", blob.path, e))?;
+            info!(
+                "removed unreferenced blob {} ({} bytes)",
+                blob.path, blob.size
+            );
+        }
+        garbage_collection.freed_bytes += blob.size;
+        garbage_collection.removed.push(blob);
+    }
+    Ok(garbage_collection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::path::PathBuf;
+
+    const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+    /// Writes a blob into the store, `age` back-dates its modification time
+    fn write_blob(store: &OllamaModelStore, data: &[u8], age: Duration) -> (String, PathBuf) {
+        let digest = format!("sha256:{:x}", Sha256::digest(data));
+        let path = store.blob_path(&digest).unwrap();
+        fs::create_dir_all(store.blobs_path()).unwrap();
+        fs::write(&path, data).unwrap();
+        fs::File::open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+        (digest, path)
+    }
+
+    fn write_manifest(store: &OllamaModelStore, model: &str, config: &str, layer_digest: &str) {
+        let layer = |media_type: &str, digest: &str| {
+            serde_json::json!({
+                "mediaType": media_type,
+                "digest": digest,
+                "size": 6,
+            })
+        };
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "config": layer("application/vnd.docker.container.image.v1+json", config),
+            "layers": [layer("application/vnd.ollama.image.model", layer_digest)],
+        });
+        store
+            .write_manifest_bytes(model, &serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_referenced_and_recent_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = OllamaModelStore::new(dir.path().to_path_buf());
+        let (config, config_path) = write_blob(&store, b"config", 2 * STALE_AFTER);
+        let (layer, layer_path) = write_blob(&store, b"layers", 2 * STALE_AFTER);
+        write_manifest(&store, "llama3.2:1b", &config, &layer);
+        let (_, old_path) = write_blob(&store, b"orphan", 2 * STALE_AFTER);
+        // Most likely written by a pull that didn't write its manifest yet
+        let (_, recent_path) = write_blob(&store, b"pulled", Duration::ZERO);
+
+        let dry_run = collect_garbage(&store, true, STALE_AFTER).unwrap();
+        assert_eq!(dry_run.removed.len(), 1);
+        assert_eq!(dry_run.freed_bytes, 6);
+        assert!(old_path.exists());
+
+        let garbage_collection = collect_garbage(&store, false, STALE_AFTER).unwrap();
+        assert_eq!(
+            garbage_collection.removed[0].path,
+            old_path.to_string_lossy()
+        );
+        assert_eq!(
+            garbage_collection.skipped[0].path,
+            recent_path.to_string_lossy()
+        );
+        assert!(!old_path.exists());
+        assert!(recent_path.exists());
+        assert!(config_path.exists() && layer_path.exists());
+    }
+
+    #[test]
+    fn test_collect_garbage_refuses_invalid_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = OllamaModelStore::new(dir.path().to_path_buf());
+        // The blob may belong to the model whose manifest can't be read
+        let (_, blob_path) = write_blob(&store, b"layers", 2 * STALE_AFTER);
+        store
+            .write_manifest_bytes("llama3.2:1b", b"{ truncated")
+            .unwrap();
+
+        let usage = analyze_model_store(&store, None).unwrap();
+        assert_eq!(usage.invalid_manifests, vec!["llama3.2:1b".to_string()]);
+        let error = collect_garbage(&store, false, STALE_AFTER).unwrap_err();
+        assert!(error.contains("llama3.2:1b"), "{}", error);
+        assert!(blob_path.exists());
+    }
+}
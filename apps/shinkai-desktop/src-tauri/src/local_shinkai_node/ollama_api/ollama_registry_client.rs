@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use reqwest::{NoProxy, Proxy, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use super::ollama_model_store::{OllamaManifest, OllamaModelName};
+
+const MANIFEST_ACCEPT_HEADER: &str = "application/vnd.docker.distribution.manifest.v2+json";
+
+/// Same proxy variables the ollama sidecar receives, so checks go through the same network path
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RegistryProxyOptions {
+    pub https_proxy: Option<String>,
+    pub http_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RegistryReachability {
+    pub registry: String,
+    pub url: String,
+    pub reachable: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub model: Option<String>,
+    pub model_found: Option<bool>,
+}
+
+/// Minimal client for the OCI distribution API exposed by ollama compatible registries
+pub struct OllamaRegistryClient {
+    registry: String,
+    insecure: bool,
+    client: reqwest::Client,
+}
+
+impl OllamaRegistryClient {
+    const REQUEST_TIMEOUT_MS: u64 = 10000;
+
+    pub fn new(
+        registry: &str,
+        insecure: bool,
+        proxy_options: &RegistryProxyOptions,
+    ) -> Result<Self, String> {
+        let no_proxy = proxy_options
+            .no_proxy
+            .as_deref()
+            .and_then(NoProxy::from_string);
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_millis(Self::REQUEST_TIMEOUT_MS))
+            // Like ollama, insecure registries are allowed to use self signed certificates
+            .danger_accept_invalid_certs(insecure);
+        if let Some(https_proxy) = proxy_options
+            .https_proxy
+            .as_deref()
+            .filter(|p| !p.is_empty())
+        {
+            let proxy = Proxy::https(https_proxy)
+                .map_err(|e| format!("invalid https proxy {}: {}", https_proxy, e))?
+                .no_proxy(no_proxy.clone());
+            builder = builder.proxy(proxy);
+        }
+        if let Some(http_proxy) = proxy_options
+            .http_proxy
+            .as_deref()
+            .filter(|p| !p.is_empty())
+        {
+            let proxy = Proxy::http(http_proxy)
+                .map_err(|e| format!("invalid http proxy {}: {}", http_proxy, e))?
+                .no_proxy(no_proxy);
+            builder = builder.proxy(proxy);
+        }
+        let client = builder
+            .build()
+            .map_err(|e| format!("failed to build registry client: {}", e))?;
+        Ok(OllamaRegistryClient {
+            registry: registry.trim_end_matches('/').to_string(),
+            insecure,
+            client,
+        })
+    }
+
+    fn url(&self, scheme: &str, path: &str) -> String {
+        format!("{}://{}{}", scheme, self.registry, path)
+    }
+
+    /// Secure registries are only reachable through https, insecure ones fall back to plain http
+    /// when the https connection can't be established, the same way `ollama pull --insecure` does
+    async fn get(&self, path: &str) -> Result<(String, reqwest::Response), String> {
+        let url = self.url("https", path);
+        let https_result = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, MANIFEST_ACCEPT_HEADER)
+            .send()
+            .await;
+        match https_result {
+            Ok(response) => Ok((url, response)),
+            Err(e) if self.insecure && e.is_connect() => {
+                warn!("https request to {} failed, retrying over http: {}", url, e);
+                let url = self.url("http", path);
+                self.client
+                    .get(&url)
+                    .header(reqwest::header::ACCEPT, MANIFEST_ACCEPT_HEADER)
+                    .send()
+                    .await
+                    .map(|response| (url, response))
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub async fn fetch_manifest(
+        &self,
+        model_name: &OllamaModelName,
+    ) -> Result<OllamaManifest, String> {
+        let path = format!(
+            "/v2/{}/{}/manifests/{}",
+            model_name.namespace, model_name.model, model_name.tag
+        );
+        let (url, response) = self.get(&path).await?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "failed to fetch manifest from {}: {}",
+                url,
+                response.status()
+            ));
+        }
+        response
+            .json::<OllamaManifest>()
+            .await
+            .map_err(|e| format!("failed to parse manifest from {}: {}", url, e))
+    }
+
+    /// Any http answer from `/v2/` means the registry is reachable, registries requiring
+    /// authentication answer 401 there.
+    pub async fn check(&self, model: Option<&str>) -> RegistryReachability {
+        let start_time = Instant::now();
+        let mut reachability = RegistryReachability {
+            registry: self.registry.clone(),
+            url: self.url(if self.insecure { "http" } else { "https" }, "/v2/"),
+            reachable: false,
+            status: None,
+            latency_ms: 0,
+            error: None,
+            model: model.map(|m| m.to_string()),
+            model_found: None,
+        };
+        match self.get("/v2/").await {
+            Ok((url, response)) => {
+                let status = response.status();
+                reachability.url = url;
+                reachability.status = Some(status.as_u16());
+                reachability.reachable = status.is_success() || status == StatusCode::UNAUTHORIZED;
+                if !reachability.reachable {
+                    reachability.error = Some(format!("unexpected status {}", status));
+                }
+            }
+            Err(e) => {
+                reachability.error = Some(e);
+            }
+        }
+        reachability.latency_ms = start_time.elapsed().as_millis() as u64;
+
+        if let (true, Some(model)) = (reachability.reachable, model) {
+            match OllamaModelName::parse_with_default_host(model, &self.registry) {
+                Ok(model_name) => match self.fetch_manifest(&model_name).await {
+                    Ok(_) => reachability.model_found = Some(true),
+                    Err(e) => {
+                        reachability.model_found = Some(false);
+                        reachability.error = Some(e);
+                    }
+                },
+                Err(e) => reachability.error = Some(e),
+            }
+        }
+        info!(
+            "registry {} reachable={} status={:?} latency={}ms",
+            reachability.registry,
+            reachability.reachable,
+            reachability.status,
+            reachability.latency_ms
+        );
+        reachability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    const MANIFEST: &str = r#"{"schemaVersion":2,"mediaType":"application/vnd.docker.distribution.manifest.v2+json","config":{"mediaType":"application/vnd.docker.container.image.v1+json","digest":"sha256:1111111111111111111111111111111111111111111111111111111111111111","size":10},"layers":[{"mediaType":"application/vnd.ollama.image.model","digest":"sha256:2222222222222222222222222222222222222222222222222222222222222222","size":1000}]}"#;
+
+    /// Plain http stand-in for a registry mirror that only knows `library/tiny:latest`
+    fn spawn_stand_in_registry() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                // Tls handshakes never complete a request line, drop them so the client falls back
+                let _ = stream.set_read_timeout(Some(Duration::from_millis(200)));
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                if reader.read_line(&mut request_line).is_err() || !request_line.starts_with("GET ")
+                {
+                    continue;
+                }
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match path {
+                    "/v2/" => ("200 OK", "{}"),
+                    "/v2/library/tiny/manifests/latest" => ("200 OK", MANIFEST),
+                    _ => ("404 Not Found", "{}"),
+                };
+                let mut line = String::new();
+                while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+                    line.clear();
+                }
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_insecure_registry_falls_back_to_http() {
+        let registry = spawn_stand_in_registry();
+        let client =
+            OllamaRegistryClient::new(&registry, true, &RegistryProxyOptions::default()).unwrap();
+        let reachability = client.check(Some("tiny")).await;
+        assert!(reachability.reachable);
+        assert_eq!(reachability.status, Some(200));
+        assert_eq!(reachability.url, format!("http://{}/v2/", registry));
+        assert_eq!(reachability.model_found, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_registry_manifest_size() {
+        let registry = spawn_stand_in_registry();
+        let client =
+            OllamaRegistryClient::new(&registry, true, &RegistryProxyOptions::default()).unwrap();
+        let model_name =
+            OllamaModelName::parse_with_default_host("library/tiny", &registry).unwrap();
+        let manifest = client.fetch_manifest(&model_name).await.unwrap();
+        assert_eq!(manifest.total_size(), 1010);
+    }
+
+    #[tokio::test]
+    async fn test_registry_missing_model() {
+        let registry = spawn_stand_in_registry();
+        let client =
+            OllamaRegistryClient::new(&registry, true, &RegistryProxyOptions::default()).unwrap();
+        let reachability = client.check(Some("missing:7b")).await;
+        assert!(reachability.reachable);
+        assert_eq!(reachability.model_found, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_secure_registry_does_not_fall_back_to_http() {
+        let registry = spawn_stand_in_registry();
+        let client =
+            OllamaRegistryClient::new(&registry, false, &RegistryProxyOptions::default()).unwrap();
+        let reachability = client.check(None).await;
+        assert!(!reachability.reachable);
+        assert!(reachability.error.is_some());
+    }
+}
@@ -1,4 +1,6 @@
 pub mod ollama_process_handler;
 pub mod process_handler;
+pub mod process_limits;
 pub mod process_utils;
+pub mod resource_usage;
 pub mod shinkai_node_process_handler;
@@ -1,26 +1,102 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::Result;
 use regex::Regex;
-use serde::Serialize;
-use tauri::AppHandle;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
 use tauri_plugin_shell::ShellExt;
 use tokio::sync::mpsc::Sender;
 
 use crate::local_shinkai_node::ollama_api::ollama_api_client::OllamaApiClient;
+use crate::local_shinkai_node::ollama_api::ollama_disk_preflight::{
+    evaluate_disk_space, DiskSpacePreflight, PullModelError,
+};
+use crate::local_shinkai_node::ollama_api::ollama_model_store::{
+    OllamaModelName, OllamaModelStore, DEFAULT_REGISTRY_HOST,
+};
+use crate::local_shinkai_node::ollama_api::ollama_registry_client::{
+    OllamaRegistryClient, RegistryProxyOptions,
+};
 
 use super::{
-    process_handler::{ProcessHandler, ProcessHandlerEvent},
+    process_handler::{ProcessHandler, ProcessHandlerEvent, ProcessPid},
+    process_limits::ProcessLimits,
     process_utils::{kill_process_by_name, kill_process_by_pid, options_to_env},
 };
 
-#[derive(Serialize, Clone)]
+/// It matches ENV variables names from Ollama, unset optional values are inherited from the app environment
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct OllamaOptions {
     pub ollama_host: String,
     pub ollama_num_parallel: String,
     pub ollama_max_loaded_models: String,
     pub ollama_origins: String,
     pub ollama_debug: String,
+    pub https_proxy: Option<String>,
+    pub http_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    /// Registry host[:port] used for model names that don't include one, eg: an internal mirror.
+    /// Only the app reads it, it's not passed to ollama
+    pub ollama_registry: Option<String>,
+    /// "true" to allow pulling from registries served over plain http or with self signed certificates.
+    /// Only the app reads it, it's sent with every pull request
+    pub ollama_insecure_registry: Option<String>,
+}
+
+impl OllamaOptions {
+    /// Options saved by the last `save`, None when there are none or they are invalid
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content)
+            .map_err(|e| log::warn!("ignoring invalid ollama options {}: {}", path.display(), e))
+            .ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize ollama options: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("failed to save ollama options: {}", e))
+    }
+
+    /// Environment for the ollama process, registry settings are left out because ollama doesn't
+    /// know them
+    pub fn to_env(&self) -> HashMap<String, String> {
+        let mut env = options_to_env(self);
+        env.remove("OLLAMA_REGISTRY");
+        env.remove("OLLAMA_INSECURE_REGISTRY");
+        env
+    }
+
+    pub fn registry(&self) -> String {
+        self.ollama_registry
+            .clone()
+            .filter(|registry| !registry.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_REGISTRY_HOST.to_string())
+    }
+
+    pub fn is_insecure_registry(&self) -> bool {
+        self.ollama_insecure_registry.as_deref() == Some("true")
+    }
+
+    pub fn registry_proxy_options(&self) -> RegistryProxyOptions {
+        RegistryProxyOptions {
+            https_proxy: self.https_proxy.clone(),
+            http_proxy: self.http_proxy.clone(),
+            no_proxy: self.no_proxy.clone(),
+        }
+    }
+
+    /// Prefixes model names without an explicit registry with the configured one
+    pub fn qualify_model_name(&self, model: &str) -> Result<String, String> {
+        let model_name = OllamaModelName::parse_with_default_host(model, &self.registry())?;
+        Ok(model_name.display_name())
+    }
 }
 
 impl Default for OllamaOptions {
@@ -31,6 +107,11 @@ impl Default for OllamaOptions {
             ollama_max_loaded_models: "2".to_string(),
             ollama_origins: "*".to_string(),
             ollama_debug: "true".to_string(),
+            https_proxy: None,
+            http_proxy: None,
+            no_proxy: None,
+            ollama_registry: None,
+            ollama_insecure_registry: None,
         }
     }
 }
@@ -40,6 +121,7 @@ pub struct OllamaProcessHandler {
     process_handler: ProcessHandler,
     app_resource_dir: PathBuf,
     options: OllamaOptions,
+    limits: ProcessLimits,
 }
 
 impl OllamaProcessHandler {
@@ -65,6 +147,7 @@ impl OllamaProcessHandler {
             process_handler,
             app_resource_dir,
             options,
+            limits: ProcessLimits::default(),
         }
     }
 
@@ -72,11 +155,32 @@ impl OllamaProcessHandler {
         self.options.clone()
     }
 
+    /// New options are applied the next time the process is spawned
+    pub fn set_options(&mut self, options: OllamaOptions) -> OllamaOptions {
+        self.options = options;
+        self.options.clone()
+    }
+
     pub fn get_ollama_api_base_url(&self) -> String {
         let base_url: String = format!("http://{}", self.options.ollama_host);
         base_url
     }
 
+    /// Folder where the ollama sidecar keeps manifests and blobs, it honors OLLAMA_MODELS like ollama does
+    pub fn get_models_path(&self) -> Result<PathBuf, String> {
+        if let Ok(models_path) = std::env::var("OLLAMA_MODELS") {
+            if !models_path.is_empty() {
+                return Ok(PathBuf::from(models_path));
+            }
+        }
+        let home_dir = self
+            .app
+            .path()
+            .home_dir()
+            .map_err(|e| format!("failed to resolve home directory: {}", e))?;
+        Ok(home_dir.join(".ollama").join("models"))
+    }
+
     async fn wait_ollama_server(&self) -> Result<(), String> {
         let timeout = Duration::from_millis(Self::HEALTH_TIMEOUT_MS);
         let start_time = std::time::Instant::now();
@@ -99,20 +203,92 @@ impl OllamaProcessHandler {
         }
     }
 
-    pub async fn spawn(&self, ensure_model: Option<&str>) -> Result<(), String> {
-        let _ = self.kill().await;
+    /// Reads the model manifest from its registry to compute how many bytes are still missing
+    /// locally and compares them with the free space of the models and node storage volumes
+    pub async fn pull_preflight(
+        &self,
+        model: &str,
+        node_storage_path: &Path,
+    ) -> Result<DiskSpacePreflight, String> {
+        let model_name = OllamaModelName::parse_with_default_host(model, &self.options.registry())?;
+        let store = OllamaModelStore::new(self.get_models_path()?);
+        let registry_client = OllamaRegistryClient::new(
+            &model_name.host,
+            self.options.is_insecure_registry(),
+            &self.options.registry_proxy_options(),
+        )?;
+        let download_bytes = match registry_client.fetch_manifest(&model_name).await {
+            Ok(manifest) => Some(
+                manifest
+                    .blobs()
+                    .iter()
+                    .filter(|blob| !store.has_blob(&blob.digest))
+                    .map(|blob| blob.size)
+                    .sum(),
+            ),
+            Err(e) => {
+                log::warn!("failed to read manifest size of {}: {}", model, e);
+                None
+            }
+        };
+        evaluate_disk_space(
+            &model_name.display_name(),
+            download_bytes,
+            store.models_path(),
+            node_storage_path,
+        )
+    }
+
+    /// Fails with `PullModelError::InsufficientDiskSpace` when `model` doesn't fit, a preflight
+    /// that can't be completed is not considered a failure
+    pub async fn check_pull_disk_space(
+        &self,
+        model: &str,
+        node_storage_path: &Path,
+    ) -> Result<(), PullModelError> {
+        match self.pull_preflight(model, node_storage_path).await {
+            Ok(preflight) if !preflight.sufficient => {
+                Err(PullModelError::InsufficientDiskSpace { preflight })
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                log::warn!("skipping disk space check for model {}: {}", model, e);
+                Ok(())
+            }
+        }
+    }
 
-        let env = options_to_env(&self.options);
+    /// `ensure_model` is pulled once the server is up, its disk space preflight checks the models
+    /// folder along with the given node storage path
+    pub async fn spawn(&self, ensure_model: Option<(&str, &Path)>) -> Result<(), String> {
+        let ensure_model = ensure_model
+            .map(|(model, node_storage_path)| {
+                self.options
+                    .qualify_model_name(model)
+                    .map(|model| (model, node_storage_path))
+            })
+            .transpose()?;
+        if let Some((model, node_storage_path)) = &ensure_model {
+            self.check_pull_disk_space(model, node_storage_path)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        let _ = self.kill().await;
+        let env = self.options.to_env();
         self.process_handler
-            .spawn(env, ["serve"].to_vec(), None)
+            .spawn(env, ["serve"].to_vec(), None, &self.limits)
             .await?;
         if let Err(e) = self.wait_ollama_server().await {
             self.process_handler.kill().await;
             return Err(e);
         }
         let ollama_api = OllamaApiClient::new(self.get_ollama_api_base_url());
-        if let Some(model) = ensure_model {
-            if let Err(e) = ollama_api.pull(model).await {
+        if let Some((model, _)) = ensure_model {
+            if let Err(e) = ollama_api
+                .pull(&model, self.options.is_insecure_registry())
+                .await
+            {
                 self.process_handler.kill().await;
                 return Err(e.to_string());
             }
@@ -124,6 +300,25 @@ impl OllamaProcessHandler {
         self.process_handler.is_running().await
     }
 
+    pub fn pid(&self) -> ProcessPid {
+        self.process_handler.pid()
+    }
+
+    pub fn get_limits(&self) -> ProcessLimits {
+        self.limits.clone()
+    }
+
+    /// Why the limits couldn't be fully applied to the running process, None when they were
+    pub fn get_limits_error(&self) -> Option<String> {
+        self.process_handler.limits_error()
+    }
+
+    /// Applies `limits` to the running process right away and keeps them for the next spawn
+    pub async fn set_limits(&mut self, limits: ProcessLimits) -> Result<(), String> {
+        self.limits = limits;
+        self.process_handler.apply_limits(&self.limits).await
+    }
+
     async fn kill_existing_processes_using_ports(&self) -> Result<(), String> {
         // Extract port from ollama_host
         let port = self
@@ -146,7 +341,7 @@ impl OllamaProcessHandler {
                 process.pid,
                 process.name
             );
-            kill_process_by_pid(self.app.clone(), &process.pid.to_string()).await;
+            let _ = kill_process_by_pid(self.app.clone(), &process.pid.to_string()).await;
         }
         Ok(())
     }
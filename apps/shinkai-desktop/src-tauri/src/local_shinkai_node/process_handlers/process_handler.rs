@@ -9,6 +9,10 @@ use tauri_plugin_shell::{
 use tokio::sync::Mutex;
 use tokio::sync::{mpsc::Sender, RwLock};
 
+use crate::log_redaction::redact_map;
+
+use super::process_limits::{apply_process_limits, ProcessLimits};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum ProcessHandlerEvent {
     Started,
@@ -16,12 +20,24 @@ pub enum ProcessHandlerEvent {
     Error(String),
 }
 
+/// Reads the pid of a process while its handler is owned by someone else
+#[derive(Clone)]
+pub struct ProcessPid(Arc<RwLock<Option<CommandChild>>>);
+
+impl ProcessPid {
+    pub async fn get(&self) -> Option<u32> {
+        self.0.read().await.as_ref().map(|child| child.pid())
+    }
+}
+
 pub struct ProcessHandler {
     app: AppHandle,
     process_name: String,
     ready_matcher: Regex,
     process: Arc<RwLock<Option<CommandChild>>>,
     event_sender: Arc<Mutex<Sender<ProcessHandlerEvent>>>,
+    /// Why the limits couldn't be fully applied the last time, None when they were
+    limits_error: std::sync::Mutex<Option<String>>,
 }
 
 impl ProcessHandler {
@@ -42,6 +58,7 @@ impl ProcessHandler {
             ready_matcher,
             event_sender: Arc::new(Mutex::new(event_sender)),
             process: Arc::new(RwLock::new(None)),
+            limits_error: std::sync::Mutex::new(None),
         }
     }
 
@@ -74,6 +91,10 @@ impl ProcessHandler {
         }
     }
 
+    pub fn pid(&self) -> ProcessPid {
+        ProcessPid(Arc::clone(&self.process))
+    }
+
     pub async fn is_running(&self) -> bool {
         let process = self.process.read().await;
         let running = process.is_some();
@@ -85,12 +106,13 @@ impl ProcessHandler {
         env: HashMap<String, String>,
         args: Vec<&str>,
         current_dir: Option<PathBuf>,
+        limits: &ProcessLimits,
     ) -> Result<(), String> {
         log::info!(
             "[{}] attempting to spawn process with args {:?} and env {:?}",
             self.process_name,
             args,
-            env
+            redact_map(&env)
         );
         {
             let process = self.process.read().await;
@@ -124,11 +146,26 @@ impl ProcessHandler {
             child.pid()
         );
 
+        let pid = child.pid();
         {
             let mut process = self.process.write().await;
             *process = Some(child);
             log::info!("[{}] process stored in state", self.process_name);
         }
+        // Applied before the process gets busy, its threads and children inherit them
+        if *limits != ProcessLimits::default() {
+            let result = apply_process_limits(&self.app, pid, limits).await;
+            if let Err(e) = &result {
+                log::warn!(
+                    "[{}] failed to apply process limits: {}",
+                    self.process_name,
+                    e
+                );
+            }
+            self.set_limits_error(result.err());
+        } else {
+            self.set_limits_error(None);
+        }
 
         let process_mutex = Arc::clone(&self.process);
         let event_sender_mutex = Arc::clone(&self.event_sender);
@@ -218,6 +255,24 @@ impl ProcessHandler {
         Ok(())
     }
 
+    /// Applies `limits` to the running process, nothing to do when it's stopped
+    pub async fn apply_limits(&self, limits: &ProcessLimits) -> Result<(), String> {
+        let Some(pid) = self.pid().get().await else {
+            return Ok(());
+        };
+        let result = apply_process_limits(&self.app, pid, limits).await;
+        self.set_limits_error(result.clone().err());
+        result
+    }
+
+    pub fn limits_error(&self) -> Option<String> {
+        self.limits_error.lock().unwrap().clone()
+    }
+
+    fn set_limits_error(&self, error: Option<String>) {
+        *self.limits_error.lock().unwrap() = error;
+    }
+
     pub async fn kill(&self) {
         log::info!("[{}] attempting to kill process", self.process_name);
         let mut process = self.process.write().await;
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use sysinfo::{ProcessRefreshKind, System};
+use tauri::AppHandle;
+use tauri_plugin_shell::ShellExt;
+
+use super::resource_usage::process_tree;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProcessPriority {
+    #[default]
+    Normal,
+    Low,
+    Idle,
+}
+
+impl ProcessPriority {
+    fn nice(&self) -> i32 {
+        match self {
+            ProcessPriority::Normal => 0,
+            ProcessPriority::Low => 10,
+            ProcessPriority::Idle => 19,
+        }
+    }
+
+    fn windows_priority_class(&self) -> &'static str {
+        match self {
+            ProcessPriority::Normal => "Normal",
+            ProcessPriority::Low => "BelowNormal",
+            ProcessPriority::Idle => "Idle",
+        }
+    }
+}
+
+/// Limits are applied to the processes once they are running so they are best-effort, the last
+/// failure is kept by the process handler
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcessLimits {
+    #[serde(default)]
+    pub priority: ProcessPriority,
+    /// Cores the process tree can run on, every core when None. Not supported on macOS.
+    #[serde(default)]
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Inference threads, sent to Ollama as `num_thread` on the requests the app makes
+    #[serde(default)]
+    pub num_threads: Option<u32>,
+    /// Soft RLIMIT_DATA of the process tree, Linux only. It caps heap and KV cache but not the
+    /// memory mapped model weights.
+    #[serde(default)]
+    pub memory_limit_bytes: Option<u64>,
+}
+
+impl ProcessLimits {
+    /// Keeps the desktop responsive while models run: low priority on the last half of the cores
+    pub fn background(cpus: usize) -> Self {
+        let cpus = cpus.max(1);
+        let background_cpus = (cpus / 2).max(1);
+        ProcessLimits {
+            priority: ProcessPriority::Low,
+            cpu_affinity: Some((cpus - background_cpus..cpus).collect()),
+            num_threads: Some(background_cpus as u32),
+            memory_limit_bytes: None,
+        }
+    }
+
+    pub fn validate(&self, cpus: usize) -> Result<(), String> {
+        if let Some(cpu_affinity) = &self.cpu_affinity {
+            if cpu_affinity.is_empty() {
+                return Err("cpu affinity needs at least one core".to_string());
+            }
+            if let Some(cpu) = cpu_affinity.iter().find(|cpu| **cpu >= cpus) {
+                return Err(format!(
+                    "cpu {} doesn't exist, this machine has {} cpus",
+                    cpu, cpus
+                ));
+            }
+        }
+        if self.num_threads == Some(0) {
+            return Err("num threads must be greater than 0".to_string());
+        }
+        if self.memory_limit_bytes == Some(0) {
+            return Err("memory limit must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn available_cpus() -> usize {
+    std::thread::available_parallelism()
+        .map(|cpus| cpus.get())
+        .unwrap_or(1)
+}
+
+struct ProcessTree {
+    processes: Vec<u32>,
+    /// Linux priorities and affinities are per thread
+    threads: Vec<u32>,
+    cpus: usize,
+}
+
+fn get_process_tree(root_pid: u32) -> ProcessTree {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessRefreshKind::new());
+    let parents: Vec<(u32, Option<u32>)> = system
+        .processes()
+        .values()
+        .filter(|p| p.thread_kind().is_none())
+        .map(|p| (p.pid().as_u32(), p.parent().map(|parent| parent.as_u32())))
+        .collect();
+    let processes = process_tree(root_pid, &parents);
+    let mut threads = processes.clone();
+    for pid in &processes {
+        if let Some(tasks) = system
+            .process(sysinfo::Pid::from_u32(*pid))
+            .and_then(|p| p.tasks())
+        {
+            threads.extend(tasks.iter().map(|task| task.as_u32()));
+        }
+    }
+    ProcessTree {
+        processes,
+        threads,
+        cpus: available_cpus(),
+    }
+}
+
+async fn run_command(app: &AppHandle, program: &str, args: Vec<String>) -> Result<(), String> {
+    let output = app
+        .shell()
+        .command(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn join(pids: &[u32], separator: &str) -> String {
+    pids.iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Command that applies a limit to processes that are already running
+#[derive(Debug, PartialEq, Eq)]
+struct LimitCommand {
+    program: &'static str,
+    args: Vec<String>,
+}
+
+/// Commands applying `limits` to `tree` on `target_os`, along with the limits the platform
+/// doesn't support
+fn limit_commands(
+    target_os: &str,
+    tree: &ProcessTree,
+    limits: &ProcessLimits,
+) -> (Vec<LimitCommand>, Vec<String>) {
+    let cpu_affinity = limits
+        .cpu_affinity
+        .clone()
+        .unwrap_or_else(|| (0..tree.cpus).collect());
+    let mut commands = Vec::new();
+    let mut unsupported = Vec::new();
+
+    if target_os == "windows" {
+        // Affinity masks are 64 bits wide, the processor group of the process
+        let affinity_mask = cpu_affinity
+            .iter()
+            .filter(|cpu| **cpu < 64)
+            .fold(0u64, |mask, cpu| mask | (1 << cpu));
+        let script = format!(
+            "Get-Process -Id {} | ForEach-Object {{ $_.PriorityClass = '{}'; $_.ProcessorAffinity = {} }}",
+            join(&tree.processes, ","),
+            limits.priority.windows_priority_class(),
+            affinity_mask
+        );
+        commands.push(LimitCommand {
+            program: "powershell",
+            args: vec!["-NoProfile".to_string(), "-Command".to_string(), script],
+        });
+    } else {
+        // Raising the priority back needs privileges, it fails until the process restarts
+        let mut args = vec![limits.priority.nice().to_string(), "-p".to_string()];
+        args.extend(tree.threads.iter().map(|pid| pid.to_string()));
+        commands.push(LimitCommand {
+            program: "renice",
+            args,
+        });
+    }
+
+    if target_os == "linux" {
+        let cpu_list = cpu_affinity
+            .iter()
+            .map(|cpu| cpu.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let data_limit = limits
+            .memory_limit_bytes
+            .map(|bytes| bytes.to_string())
+            .unwrap_or_else(|| "unlimited".to_string());
+        for pid in &tree.processes {
+            commands.push(LimitCommand {
+                program: "taskset",
+                args: vec![
+                    "-a".to_string(),
+                    "-c".to_string(),
+                    "-p".to_string(),
+                    cpu_list.clone(),
+                    pid.to_string(),
+                ],
+            });
+            commands.push(LimitCommand {
+                program: "prlimit",
+                args: vec![
+                    "--pid".to_string(),
+                    pid.to_string(),
+                    // Only the soft limit so it can be raised again
+                    format!("--data={}:", data_limit),
+                ],
+            });
+        }
+    } else if limits.cpu_affinity.is_some() && target_os == "macos" {
+        unsupported.push("cpu affinity isn't supported on macOS".to_string());
+    }
+    if limits.memory_limit_bytes.is_some() && target_os != "linux" {
+        unsupported.push("memory limits are only supported on Linux".to_string());
+    }
+    (commands, unsupported)
+}
+
+/// Applies `limits` to `root_pid` and its children. Children spawned later (Ollama runners)
+/// inherit them. Every limit is attempted, failures are joined in the error.
+pub async fn apply_process_limits(
+    app: &AppHandle,
+    root_pid: u32,
+    limits: &ProcessLimits,
+) -> Result<(), String> {
+    let tree = tauri::async_runtime::spawn_blocking(move || get_process_tree(root_pid))
+        .await
+        .map_err(|e| e.to_string())?;
+    let (commands, mut errors) = limit_commands(std::env::consts::OS, &tree, limits);
+    for command in commands {
+        if let Err(e) = run_command(app, command.program, command.args).await {
+            errors.push(e);
+        }
+    }
+
+    if errors.is_empty() {
+        log::info!(
+            "applied process limits {:?} to pids [{}]",
+            limits,
+            join(&tree.processes, ", ")
+        );
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_background_limits_use_last_half_of_cores() {
+        let limits = ProcessLimits::background(8);
+        assert_eq!(limits.priority, ProcessPriority::Low);
+        assert_eq!(limits.cpu_affinity, Some(vec![4, 5, 6, 7]));
+        assert_eq!(limits.num_threads, Some(4));
+        assert!(limits.validate(8).is_ok());
+        assert_eq!(ProcessLimits::background(1).cpu_affinity, Some(vec![0]));
+        assert!(ProcessLimits::background(8).validate(4).is_err());
+    }
+
+    fn tree() -> ProcessTree {
+        ProcessTree {
+            processes: vec![100, 200],
+            threads: vec![100, 101, 200],
+            cpus: 4,
+        }
+    }
+
+    fn programs(commands: &[LimitCommand]) -> Vec<&str> {
+        commands.iter().map(|command| command.program).collect()
+    }
+
+    #[test]
+    fn test_linux_limit_commands() {
+        let limits = ProcessLimits {
+            priority: ProcessPriority::Idle,
+            cpu_affinity: Some(vec![2, 3]),
+            num_threads: Some(2),
+            memory_limit_bytes: Some(1024),
+        };
+        let (commands, unsupported) = limit_commands("linux", &tree(), &limits);
+        assert!(unsupported.is_empty());
+        assert_eq!(
+            programs(&commands),
+            vec!["renice", "taskset", "prlimit", "taskset", "prlimit"]
+        );
+        assert_eq!(commands[0].args, vec!["19", "-p", "100", "101", "200"]);
+        assert_eq!(commands[1].args, vec!["-a", "-c", "-p", "2,3", "100"]);
+        assert_eq!(commands[2].args, vec!["--pid", "100", "--data=1024:"]);
+
+        // Defaults lift the previous limits
+        let (commands, _) = limit_commands("linux", &tree(), &ProcessLimits::default());
+        assert_eq!(commands[0].args[0], "0");
+        assert_eq!(commands[1].args[3], "0,1,2,3");
+        assert_eq!(commands[2].args[2], "--data=unlimited:");
+    }
+
+    #[test]
+    fn test_windows_limit_commands() {
+        let limits = ProcessLimits {
+            priority: ProcessPriority::Low,
+            cpu_affinity: Some(vec![0, 2]),
+            num_threads: None,
+            memory_limit_bytes: Some(1024),
+        };
+        let (commands, unsupported) = limit_commands("windows", &tree(), &limits);
+        assert_eq!(programs(&commands), vec!["powershell"]);
+        assert_eq!(
+            commands[0].args[2],
+            concat!(
+                "Get-Process -Id 100,200 | ForEach-Object { ",
+                "$_.PriorityClass = 'BelowNormal'; $_.ProcessorAffinity = 5 }"
+            )
+        );
+        assert_eq!(
+            unsupported,
+            vec!["memory limits are only supported on Linux"]
+        );
+    }
+
+    #[test]
+    fn test_macos_limit_commands() {
+        let limits = ProcessLimits {
+            priority: ProcessPriority::Low,
+            cpu_affinity: Some(vec![1]),
+            num_threads: None,
+            memory_limit_bytes: None,
+        };
+        let (commands, unsupported) = limit_commands("macos", &tree(), &limits);
+        assert_eq!(programs(&commands), vec!["renice"]);
+        assert_eq!(commands[0].args, vec!["10", "-p", "100", "101", "200"]);
+        assert_eq!(unsupported, vec!["cpu affinity isn't supported on macOS"]);
+    }
+}
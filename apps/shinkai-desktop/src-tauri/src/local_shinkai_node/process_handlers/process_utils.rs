@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use tauri::AppHandle;
 use tauri_plugin_shell::ShellExt;
@@ -16,6 +17,23 @@ pub fn options_to_env<T: serde::Serialize>(options: &T) -> HashMap<String, Strin
     env
 }
 
+/// Stdout of `program`, None when it fails to run. No console window is opened on Windows
+pub fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = std::process::Command::new(program);
+    command.args(args);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
 pub async fn kill_process_by_name(app: AppHandle, process_name: &str) {
     let adapted_process_name = if cfg!(target_os = "windows") {
         format!("{}.exe", process_name).to_string()
@@ -75,13 +93,13 @@ pub async fn kill_existing_processes_using_ports(
                 "terminating process: PID={}, Name={}",
                 process.pid, process.name
             );
-            kill_process_by_pid(app.clone(), &process.pid.to_string()).await;
+            let _ = kill_process_by_pid(app.clone(), &process.pid.to_string()).await;
         }
     }
     Ok(())
 }
 
-pub async fn kill_process_by_pid(app: AppHandle, process_id: &str) {
+pub async fn kill_process_by_pid(app: AppHandle, process_id: &str) -> Result<(), String> {
     let output = if cfg!(target_os = "windows") {
         // windows: use taskkill command
         app.shell()
@@ -94,17 +112,92 @@ pub async fn kill_process_by_pid(app: AppHandle, process_id: &str) {
             .args(["-15", process_id])
             .output()
     };
-    if let Ok(output) = output.await {
-        if output.status.success() {
+    match output.await {
+        Ok(output) if output.status.success() => {
             log::info!("process with PID '{}' has been terminated.", process_id);
-        } else {
-            log::warn!(
+            Ok(())
+        }
+        Ok(output) => {
+            let error = format!(
                 "failed to terminate process with PID '{}'. error: {}",
                 process_id,
                 String::from_utf8_lossy(&output.stderr)
             );
+            log::warn!("{}", error);
+            Err(error)
+        }
+        Err(e) => {
+            let error = format!("failed to execute command to terminate process: {}", e);
+            log::error!("{}", error);
+            Err(error)
         }
-    } else {
-        log::error!("failed to execute command to terminate process");
     }
 }
+
+/// A sidecar the app spawned, recorded so a leftover one can be told apart from other processes
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SidecarPid {
+    pub name: String,
+    pub pid: u32,
+}
+
+/// Sidecars recorded by `save_sidecar_pids`, empty when the file is missing or invalid
+pub fn load_sidecar_pids(path: &Path) -> Vec<SidecarPid> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return vec![];
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("ignoring invalid sidecar pids {}: {}", path.display(), e);
+        vec![]
+    })
+}
+
+pub fn save_sidecar_pids(path: &Path, pids: &[SidecarPid]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(pids).map_err(|e| e.to_string())?;
+    std::fs::write(path, content)
+        .map_err(|e| format!("failed to save sidecar pids {}: {}", path.display(), e))
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct PortProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct PortListener {
+    pub port: u16,
+    /// What the app expects to be listening on the port
+    pub role: String,
+    pub processes: Vec<PortProcess>,
+    pub error: Option<String>,
+}
+
+/// Processes listening on each `(role, port)`
+pub fn get_port_listeners(ports: Vec<(String, u16)>) -> Vec<PortListener> {
+    ports
+        .into_iter()
+        .map(|(role, port)| {
+            let (processes, error) = match listeners::get_processes_by_port(port) {
+                Ok(processes) => {
+                    let mut processes: Vec<PortProcess> = processes
+                        .into_iter()
+                        .map(|process| PortProcess {
+                            pid: process.pid,
+                            name: process.name,
+                        })
+                        .collect();
+                    processes.sort_by_key(|process| process.pid);
+                    (processes, None)
+                }
+                Err(e) => (vec![], Some(e.to_string())),
+            };
+            PortListener {
+                port,
+                role,
+                processes,
+                error,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::{ProcessRefreshKind, System};
+
+use super::process_handler::ProcessPid;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProcessResourceUsage {
+    pub process: String,
+    pub pid: u32,
+    /// Processes in the tree, the root plus children like ollama_llama_server runners
+    pub process_count: usize,
+    /// Sum over the tree, 100 means one core fully used
+    pub cpu_percent: f32,
+    pub memory_bytes: u64,
+    /// None where the platform doesn't expose it (threads: Linux only, open files: Linux only)
+    pub threads: Option<u64>,
+    pub open_files: Option<u64>,
+    pub disk_read_bytes_per_sec: u64,
+    pub disk_written_bytes_per_sec: u64,
+    pub disk_total_read_bytes: u64,
+    pub disk_total_written_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResourceUsageSample {
+    /// Unix time in ms
+    pub timestamp: i64,
+    pub processes: Vec<ProcessResourceUsage>,
+}
+
+/// `root` and every process descending from it
+pub(super) fn process_tree(root: u32, parents: &[(u32, Option<u32>)]) -> Vec<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, parent) in parents {
+        if let Some(parent) = parent {
+            children.entry(*parent).or_default().push(*pid);
+        }
+    }
+    let mut tree = vec![root];
+    let mut visited = HashSet::from([root]);
+    let mut index = 0;
+    while index < tree.len() {
+        for child in children.get(&tree[index]).into_iter().flatten() {
+            if visited.insert(*child) {
+                tree.push(*child);
+            }
+        }
+        index += 1;
+    }
+    tree
+}
+
+#[cfg(target_os = "linux")]
+fn open_files(pid: u32) -> Option<u64> {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .ok()
+        .map(|entries| entries.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_files(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Counters of a single process as read from the system
+struct ProcessCounters {
+    pid: u32,
+    parent: Option<u32>,
+    cpu_percent: f32,
+    memory_bytes: u64,
+    threads: Option<u64>,
+    open_files: Option<u64>,
+    /// Since the previous refresh
+    read_bytes: u64,
+    written_bytes: u64,
+    total_read_bytes: u64,
+    total_written_bytes: u64,
+}
+
+fn process_counters(system: &System) -> Vec<ProcessCounters> {
+    system
+        .processes()
+        .values()
+        // On Linux threads are listed as processes too, they would be counted twice
+        .filter(|p| p.thread_kind().is_none())
+        .map(|p| {
+            let pid = p.pid().as_u32();
+            let disk_usage = p.disk_usage();
+            ProcessCounters {
+                pid,
+                parent: p.parent().map(|parent| parent.as_u32()),
+                cpu_percent: p.cpu_usage(),
+                memory_bytes: p.memory(),
+                // Tasks don't include the main thread
+                threads: p.tasks().map(|tasks| tasks.len() as u64 + 1),
+                open_files: open_files(pid),
+                read_bytes: disk_usage.read_bytes,
+                written_bytes: disk_usage.written_bytes,
+                total_read_bytes: disk_usage.total_read_bytes,
+                total_written_bytes: disk_usage.total_written_bytes,
+            }
+        })
+        .collect()
+}
+
+/// Sums the counters of the `root` process tree, None when `root` isn't running
+fn process_usage(
+    counters: &[ProcessCounters],
+    process: &str,
+    root: u32,
+    elapsed: Duration,
+) -> Option<ProcessResourceUsage> {
+    let by_pid: HashMap<u32, &ProcessCounters> = counters.iter().map(|c| (c.pid, c)).collect();
+    by_pid.get(&root)?;
+    let parents: Vec<(u32, Option<u32>)> = counters.iter().map(|c| (c.pid, c.parent)).collect();
+    let elapsed_secs = elapsed.as_secs_f64().max(1.0);
+    let mut usage = ProcessResourceUsage {
+        process: process.to_string(),
+        pid: root,
+        process_count: 0,
+        cpu_percent: 0.0,
+        memory_bytes: 0,
+        threads: None,
+        open_files: None,
+        disk_read_bytes_per_sec: 0,
+        disk_written_bytes_per_sec: 0,
+        disk_total_read_bytes: 0,
+        disk_total_written_bytes: 0,
+    };
+    for pid in process_tree(root, &parents) {
+        let Some(p) = by_pid.get(&pid) else {
+            continue;
+        };
+        usage.process_count += 1;
+        usage.cpu_percent += p.cpu_percent;
+        usage.memory_bytes += p.memory_bytes;
+        usage.threads = p
+            .threads
+            .map(|threads| usage.threads.unwrap_or_default() + threads);
+        usage.open_files = p
+            .open_files
+            .map(|count| usage.open_files.unwrap_or_default() + count);
+        // The first refresh of a process reports everything it did since it started
+        let first_refresh =
+            p.read_bytes == p.total_read_bytes && p.written_bytes == p.total_written_bytes;
+        if !first_refresh {
+            usage.disk_read_bytes_per_sec += (p.read_bytes as f64 / elapsed_secs) as u64;
+            usage.disk_written_bytes_per_sec += (p.written_bytes as f64 / elapsed_secs) as u64;
+        }
+        usage.disk_total_read_bytes += p.total_read_bytes;
+        usage.disk_total_written_bytes += p.total_written_bytes;
+    }
+    Some(usage)
+}
+
+/// Samples cpu, memory, threads, open files and disk I/O of the node and Ollama process trees
+/// and keeps the latest samples so charts have data as soon as they open
+pub struct ResourceUsageSampler {
+    history: Arc<Mutex<VecDeque<ResourceUsageSample>>>,
+    handle: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl ResourceUsageSampler {
+    pub const SAMPLE_INTERVAL_MS: u64 = 3000;
+    /// 10 minutes of samples
+    const HISTORY_LEN: usize = 200;
+
+    /// `processes` are named pids sampled on every tick, stopped ones are left out of the sample
+    /// and nothing is recorded while all of them are stopped
+    pub fn start(
+        processes: Vec<(String, ProcessPid)>,
+        on_sample: impl Fn(&ResourceUsageSample) + Send + Sync + 'static,
+    ) -> Self {
+        let history = Arc::new(Mutex::new(VecDeque::with_capacity(Self::HISTORY_LEN)));
+        let task_history = history.clone();
+        let handle = tauri::async_runtime::spawn(async move {
+            let mut system = System::new();
+            let mut last_refresh = Instant::now();
+            loop {
+                tokio::time::sleep(Duration::from_millis(Self::SAMPLE_INTERVAL_MS)).await;
+                let mut roots = Vec::new();
+                for (process, pid) in &processes {
+                    if let Some(pid) = pid.get().await {
+                        roots.push((process.clone(), pid));
+                    }
+                }
+                if roots.is_empty() {
+                    continue;
+                }
+                let elapsed = last_refresh.elapsed();
+                let result = tauri::async_runtime::spawn_blocking(move || {
+                    system.refresh_processes_specifics(
+                        ProcessRefreshKind::new()
+                            .with_cpu()
+                            .with_memory()
+                            .with_disk_usage(),
+                    );
+                    let counters = process_counters(&system);
+                    let processes = roots
+                        .iter()
+                        .filter_map(|(process, pid)| {
+                            process_usage(&counters, process, *pid, elapsed)
+                        })
+                        .collect();
+                    (system, processes)
+                })
+                .await;
+                let Ok((refreshed_system, processes)) = result else {
+                    log::warn!("resource usage sampling failed, stopping the sampler");
+                    break;
+                };
+                system = refreshed_system;
+                last_refresh = Instant::now();
+                let sample = ResourceUsageSample {
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                    processes,
+                };
+                on_sample(&sample);
+                let mut history = task_history.lock().unwrap();
+                if history.len() == Self::HISTORY_LEN {
+                    history.pop_front();
+                }
+                history.push_back(sample);
+            }
+        });
+        ResourceUsageSampler { history, handle }
+    }
+
+    pub fn history(&self) -> Vec<ResourceUsageSample> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Drop for ResourceUsageSampler {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_tree_includes_nested_children_only() {
+        let parents = vec![
+            (1, None),
+            (100, Some(1)),
+            (200, Some(100)),
+            (201, Some(100)),
+            (300, Some(200)),
+            (400, Some(1)),
+        ];
+        let mut tree = process_tree(100, &parents);
+        tree.sort();
+        assert_eq!(tree, vec![100, 200, 201, 300]);
+        assert_eq!(process_tree(300, &parents), vec![300]);
+    }
+
+    fn counters(pid: u32, parent: Option<u32>) -> ProcessCounters {
+        ProcessCounters {
+            pid,
+            parent,
+            cpu_percent: 50.0,
+            memory_bytes: 1000,
+            threads: Some(4),
+            open_files: Some(10),
+            read_bytes: 3000,
+            written_bytes: 600,
+            total_read_bytes: 9000,
+            total_written_bytes: 1200,
+        }
+    }
+
+    #[test]
+    fn test_process_usage_sums_the_tree() {
+        let mut runner = counters(200, Some(100));
+        runner.cpu_percent = 150.0;
+        runner.memory_bytes = 4000;
+        let processes = vec![
+            counters(1, None),
+            counters(100, Some(1)),
+            runner,
+            counters(300, Some(1)),
+        ];
+
+        let usage = process_usage(&processes, "ollama", 100, Duration::from_secs(3)).unwrap();
+
+        assert_eq!(usage.process, "ollama");
+        assert_eq!(usage.process_count, 2);
+        assert_eq!(usage.cpu_percent, 200.0);
+        assert_eq!(usage.memory_bytes, 5000);
+        assert_eq!(usage.threads, Some(8));
+        assert_eq!(usage.open_files, Some(20));
+        assert_eq!(usage.disk_total_read_bytes, 18000);
+        assert_eq!(usage.disk_total_written_bytes, 2400);
+        assert!(process_usage(&processes, "ollama", 400, Duration::from_secs(3)).is_none());
+    }
+
+    #[test]
+    fn test_process_usage_disk_rates() {
+        let mut new_process = counters(101, Some(100));
+        new_process.read_bytes = new_process.total_read_bytes;
+        new_process.written_bytes = new_process.total_written_bytes;
+        let mut unsupported = counters(102, Some(100));
+        unsupported.threads = None;
+        unsupported.open_files = None;
+        let processes = vec![counters(100, None), new_process];
+
+        // Bytes since the previous refresh over the elapsed time, the first refresh of a process
+        // is left out since it counts everything it did
+        let usage = process_usage(&processes, "shinkai-node", 100, Duration::from_secs(3)).unwrap();
+        assert_eq!(usage.disk_read_bytes_per_sec, 1000);
+        assert_eq!(usage.disk_written_bytes_per_sec, 200);
+        assert_eq!(usage.disk_total_read_bytes, 18000);
+
+        // Elapsed times under a second count as one second
+        let usage =
+            process_usage(&processes, "shinkai-node", 100, Duration::from_millis(10)).unwrap();
+        assert_eq!(usage.disk_read_bytes_per_sec, 3000);
+
+        let usage = process_usage(
+            &[counters(100, None), unsupported],
+            "shinkai-node",
+            100,
+            Duration::from_secs(3),
+        )
+        .unwrap();
+        assert_eq!(usage.threads, None);
+        assert_eq!(usage.open_files, None);
+    }
+}
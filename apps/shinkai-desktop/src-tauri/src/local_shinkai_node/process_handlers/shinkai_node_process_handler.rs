@@ -1,6 +1,6 @@
 use std::{
-    fs,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -9,10 +9,12 @@ use regex::Regex;
 use tauri::AppHandle;
 use tokio::sync::mpsc::Sender;
 
+use crate::local_shinkai_node::secrets_store::SecretsStore;
 use crate::local_shinkai_node::shinkai_node_options::ShinkaiNodeOptions;
 
 use super::{
-    process_handler::{ProcessHandler, ProcessHandlerEvent},
+    process_handler::{ProcessHandler, ProcessHandlerEvent, ProcessPid},
+    process_limits::ProcessLimits,
     process_utils::{kill_existing_processes_using_ports, options_to_env},
 };
 
@@ -22,6 +24,9 @@ pub struct ShinkaiNodeProcessHandler {
     app_resource_dir: PathBuf,
     app_data_dir: PathBuf,
     options: ShinkaiNodeOptions,
+    limits: ProcessLimits,
+    version: Mutex<Option<String>>,
+    secrets: Arc<Mutex<SecretsStore>>,
 }
 
 impl ShinkaiNodeProcessHandler {
@@ -50,16 +55,66 @@ impl ShinkaiNodeProcessHandler {
             ready_matcher,
         );
 
-        ShinkaiNodeProcessHandler {
+        let secrets = Arc::new(Mutex::new(SecretsStore::open(&app_data_dir)));
+        let mut shinkai_node_process_handler = ShinkaiNodeProcessHandler {
             app,
             process_handler,
             app_resource_dir,
             app_data_dir,
-            options,
+            options: options.clone(),
+            limits: ProcessLimits::default(),
+            version: Mutex::new(None),
+            secrets,
+        };
+        shinkai_node_process_handler.options = shinkai_node_process_handler.store_secrets(options);
+        shinkai_node_process_handler
+    }
+
+    /// Moves secret values out of `options` into the secrets store, defaults aren't saved
+    fn store_secrets(&self, mut options: ShinkaiNodeOptions) -> ShinkaiNodeOptions {
+        let mut defaults = ShinkaiNodeOptions::default();
+        let mut secrets = self.secrets.lock().unwrap();
+        for ((name, value), (_, default)) in options
+            .secret_fields()
+            .into_iter()
+            .zip(defaults.secret_fields())
+        {
+            let Some(secret) = value.take().filter(|secret| !secret.is_empty()) else {
+                continue;
+            };
+            if Some(&secret) == default.as_ref() {
+                let _ = secrets.set(name, None);
+            } else if let Err(e) = secrets.set(name, Some(&secret)) {
+                log::error!("failed to save {} in secrets store: {}", name, e);
+                // Kept in memory so it's still used by the next spawn
+                *value = Some(secret);
+            }
+        }
+        options
+    }
+
+    /// Options with their secret values, they are only decrypted to spawn the node
+    fn options_with_secrets(&self) -> Result<ShinkaiNodeOptions, String> {
+        let secrets = self.secrets.lock().unwrap().read()?;
+        let mut options = self.options.clone();
+        let mut defaults = ShinkaiNodeOptions::default();
+        for ((name, value), (_, default)) in options
+            .secret_fields()
+            .into_iter()
+            .zip(defaults.secret_fields())
+        {
+            if value.is_none() {
+                *value = secrets.get(name).cloned().or(default.take());
+            }
         }
+        Ok(options)
+    }
+
+    pub fn secrets(&self) -> Arc<Mutex<SecretsStore>> {
+        self.secrets.clone()
     }
 
-    fn get_base_url(&self) -> String {
+    pub fn get_base_url(&self) -> String {
         let ip = self.options.clone().node_api_ip.unwrap();
         let port = self.options.clone().node_api_port.unwrap();
         let base_url = format!("http://{}:{}", ip, port);
@@ -120,64 +175,54 @@ impl ShinkaiNodeProcessHandler {
         }
     }
 
-    pub fn set_options(&mut self, options: ShinkaiNodeOptions) -> ShinkaiNodeOptions {
-        self.options = ShinkaiNodeOptions::from_merge(self.options.clone(), options);
-        self.options.clone()
+    async fn fetch_version(&self) -> Option<String> {
+        let url = format!("{}/v2/health_check", self.get_base_url());
+        let response = reqwest::Client::new()
+            .get(&url)
+            .timeout(Duration::from_millis(Self::HEALTH_REQUEST_TIMEOUT_MS))
+            .send()
+            .await
+            .ok()?;
+        let body: serde_json::Value = response.json().await.ok()?;
+        body.get("version")
+            .and_then(|version| version.as_str())
+            .map(|version| version.to_string())
     }
 
-    pub async fn remove_storage(&self, preserve_keys: bool) -> Result<(), String> {
-        if self.process_handler.is_running().await {
-            return Err("can't remove node storage while it's running".to_string());
-        }
-        let options = self.options.clone();
-        let storage_path = options.node_storage_path.unwrap();
-        for entry in fs::read_dir(storage_path)
-            .map_err(|e| format!("Failed to read storage directory: {}", e))?
-        {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let path = entry.path();
-            if path.is_dir() {
-                fs::remove_dir_all(path)
-                    .map_err(|e| format!("Failed to remove directory: {}", e))?;
-            } else {
-                if preserve_keys && path.ends_with(".secret") {
-                    // Delete the line starting with 'GLOBAL_IDENTITY_NAME=' in .secret file
-                    if path.file_name().unwrap() == ".secret" {
-                        let content = fs::read_to_string(&path)
-                            .map_err(|e| format!("Failed to read .secret file: {}", e))?;
-                        let new_content: String = content
-                            .lines()
-                            .filter(|line| !line.starts_with("GLOBAL_IDENTITY_NAME="))
-                            .collect::<Vec<&str>>()
-                            .join("\n");
-                        fs::write(&path, new_content)
-                            .map_err(|e| format!("Failed to write updated .secret file: {}", e))?;
-                    }
-                    continue;
-                }
-                fs::remove_file(path).map_err(|e| format!("Failed to remove file: {}", e))?;
-            }
-        }
-        Ok(())
+    /// Version reported by the node the last time it started, None until then
+    pub fn version(&self) -> Option<String> {
+        self.version.lock().unwrap().clone()
+    }
+
+    pub fn set_options(&mut self, options: ShinkaiNodeOptions) -> ShinkaiNodeOptions {
+        let options = ShinkaiNodeOptions::from_merge(self.options.clone(), options);
+        self.options = self.store_secrets(options);
+        self.options.clone()
     }
 
     pub async fn spawn(&self) -> Result<(), String> {
         let _ = self.kill().await;
 
-        let env = options_to_env(&self.options.clone());
-        self.process_handler.spawn(env, [].to_vec(), None).await?;
+        let env = options_to_env(&self.options_with_secrets()?);
+        self.process_handler
+            .spawn(env, [].to_vec(), None, &self.limits)
+            .await?;
         if let Err(e) = self.wait_shinkai_node_server().await {
             self.process_handler.kill().await;
             return Err(e);
         }
+        if let Some(version) = self.fetch_version().await {
+            *self.version.lock().unwrap() = Some(version);
+        }
         Ok(())
     }
 
     pub fn set_default_options(&mut self) -> ShinkaiNodeOptions {
-        self.options = ShinkaiNodeOptions::with_app_options(
+        let options = ShinkaiNodeOptions::with_app_options(
             self.app_resource_dir.clone(),
             self.app_data_dir.clone(),
         );
+        self.options = self.store_secrets(options);
         self.options.clone()
     }
 
@@ -189,6 +234,25 @@ impl ShinkaiNodeProcessHandler {
         self.process_handler.is_running().await
     }
 
+    pub fn pid(&self) -> ProcessPid {
+        self.process_handler.pid()
+    }
+
+    pub fn get_limits(&self) -> ProcessLimits {
+        self.limits.clone()
+    }
+
+    /// Why the limits couldn't be fully applied to the running process, None when they were
+    pub fn get_limits_error(&self) -> Option<String> {
+        self.process_handler.limits_error()
+    }
+
+    /// Applies `limits` to the running process right away and keeps them for the next spawn
+    pub async fn set_limits(&mut self, limits: ProcessLimits) -> Result<(), String> {
+        self.limits = limits;
+        self.process_handler.apply_limits(&self.limits).await
+    }
+
     pub async fn kill_existing_processes_using_ports(&self) -> Result<(), String> {
         // Extract ports from options
         let ports: Vec<&str> = vec![
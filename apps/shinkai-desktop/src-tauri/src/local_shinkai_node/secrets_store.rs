@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use super::node_storage::identity_keys::KdfParams;
+use super::process_handlers::process_utils::command_output;
+
+const SECRETS_FORMAT_VERSION: u32 = 1;
+const SECRETS_FILE_NAME: &str = "secrets.json";
+const SEED_FILE_NAME: &str = "secrets.seed";
+const KEYRING_SERVICE: &str = "shinkai-desktop";
+const KEYRING_USER: &str = "secrets-store-seed";
+const KEY_CONTEXT: &str = "shinkai-desktop 2024-06 secrets store key";
+
+/// Where the key that encrypts the secrets comes from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecretsProtection {
+    /// Random seed saved in the OS keyring
+    Keyring,
+    /// Random seed saved in the app data folder mixed with the machine id, for systems
+    /// without a keyring. It's rejected when the OS doesn't expose a machine id.
+    MachineFile,
+    /// Derived from a passphrase the user enters after every launch
+    Passphrase,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SecretsStoreStatus {
+    pub protection: SecretsProtection,
+    /// Secrets can't be read until the passphrase is entered
+    pub locked: bool,
+    pub error: Option<String>,
+}
+
+/// Everything outside `ciphertext`, it's authenticated along with it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SecretsHeader {
+    format_version: u32,
+    protection: SecretsProtection,
+    kdf: Option<KdfParams>,
+    nonce: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct SecretsFile {
+    #[serde(flatten)]
+    header: SecretsHeader,
+    ciphertext: String,
+}
+
+fn random_seed() -> [u8; 32] {
+    let mut seed = [0u8; 32];
+    OsRng.fill_bytes(&mut seed);
+    seed
+}
+
+fn keyring_seed() -> Result<Vec<u8>, String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("keyring is not available: {}", e))?;
+    match entry.get_password() {
+        Ok(seed) => hex::decode(seed).map_err(|_| "invalid seed in keyring".to_string()),
+        Err(keyring::Error::NoEntry) => {
+            let seed = random_seed();
+            entry
+                .set_password(&hex::encode(seed))
+                .map_err(|e| format!("failed to save seed in keyring: {}", e))?;
+            Ok(seed.to_vec())
+        }
+        Err(e) => Err(format!("keyring is not available: {}", e)),
+    }
+}
+
+/// Stable id of the machine so a copied app data folder can't be decrypted elsewhere
+fn machine_id() -> Option<Vec<u8>> {
+    if cfg!(target_os = "macos") {
+        command_output("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"])
+            .and_then(|output| parse_ioreg_platform_uuid(&output))
+            .map(String::into_bytes)
+    } else if cfg!(target_os = "windows") {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKLM\SOFTWARE\Microsoft\Cryptography",
+                "/v",
+                "MachineGuid",
+            ],
+        )
+        .and_then(|output| parse_registry_machine_guid(&output))
+        .map(String::into_bytes)
+    } else {
+        ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .find_map(|path| fs::read(path).ok())
+            .filter(|id| !id.trim_ascii().is_empty())
+    }
+}
+
+/// Reads `"IOPlatformUUID" = "..."` from `ioreg -rd1 -c IOPlatformExpertDevice`
+fn parse_ioreg_platform_uuid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "\"IOPlatformUUID\"").then(|| value.trim().trim_matches('"').to_string())
+        })
+        .filter(|uuid| !uuid.is_empty())
+}
+
+/// Reads `MachineGuid    REG_SZ    ...` from `reg query`
+fn parse_registry_machine_guid(output: &str) -> Option<String> {
+    output.lines().find_map(
+        |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["MachineGuid", "REG_SZ", guid] => Some(guid.to_string()),
+            _ => None,
+        },
+    )
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(content).and_then(|_| file.sync_all()))
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    fs::write(path, content)
+}
+
+fn machine_file_seed(seed_path: &Path, machine_id: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    let machine_id = machine_id.ok_or_else(|| {
+        "there isn't a machine id to bind secrets to, protect them with a passphrase".to_string()
+    })?;
+    let seed = match fs::read(seed_path) {
+        Ok(seed) => seed,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let seed = random_seed();
+            write_private(seed_path, &seed)
+                .map_err(|e| format!("failed to save secrets seed: {}", e))?;
+            seed.to_vec()
+        }
+        Err(e) => return Err(format!("failed to read secrets seed: {}", e)),
+    };
+    Ok([&seed, machine_id].concat())
+}
+
+/// Secret option values encrypted in `app_data_dir`, they are only decrypted when needed
+pub struct SecretsStore {
+    path: PathBuf,
+    seed_path: PathBuf,
+    protection: SecretsProtection,
+    kdf: Option<KdfParams>,
+    /// Bound into the machine file seed, None when the machine has no stable id
+    machine_id: Option<Vec<u8>>,
+    /// None while locked
+    key: Option<[u8; 32]>,
+    error: Option<String>,
+}
+
+impl SecretsStore {
+    /// Opens the store in `app_data_dir`, a new one uses the keyring when there's one
+    pub fn open(app_data_dir: &Path) -> Self {
+        Self::open_on_machine(app_data_dir, machine_id())
+    }
+
+    fn open_on_machine(app_data_dir: &Path, machine_id: Option<Vec<u8>>) -> Self {
+        let path = app_data_dir.join(SECRETS_FILE_NAME);
+        let seed_path = app_data_dir.join(SEED_FILE_NAME);
+        let header = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SecretsFile>(&content).ok())
+            .map(|file| file.header);
+        let mut store = SecretsStore {
+            path,
+            seed_path,
+            protection: SecretsProtection::Keyring,
+            kdf: None,
+            machine_id,
+            key: None,
+            error: None,
+        };
+        match header {
+            Some(header) => {
+                store.protection = header.protection;
+                store.kdf = header.kdf;
+                if store.protection != SecretsProtection::Passphrase {
+                    match store.seed_key(store.protection) {
+                        Ok(key) => store.key = Some(key),
+                        Err(e) => {
+                            warn!("failed to unlock secrets store: {}", e);
+                            store.error = Some(e);
+                        }
+                    }
+                }
+            }
+            None => match store.seed_key(SecretsProtection::Keyring) {
+                Ok(key) => store.key = Some(key),
+                Err(e) => {
+                    info!("{}, secrets are protected with a machine bound seed", e);
+                    store.protection = SecretsProtection::MachineFile;
+                    match store.seed_key(SecretsProtection::MachineFile) {
+                        Ok(key) => store.key = Some(key),
+                        Err(e) => store.error = Some(e),
+                    }
+                }
+            },
+        }
+        store
+    }
+
+    fn seed_key(&self, protection: SecretsProtection) -> Result<[u8; 32], String> {
+        let seed = match protection {
+            SecretsProtection::Keyring => keyring_seed()?,
+            SecretsProtection::MachineFile => {
+                machine_file_seed(&self.seed_path, self.machine_id.as_deref())?
+            }
+            SecretsProtection::Passphrase => {
+                return Err("a passphrase is required to unlock secrets".to_string())
+            }
+        };
+        Ok(blake3::derive_key(KEY_CONTEXT, &seed))
+    }
+
+    pub fn status(&self) -> SecretsStoreStatus {
+        SecretsStoreStatus {
+            protection: self.protection,
+            locked: self.key.is_none(),
+            error: self.error.clone(),
+        }
+    }
+
+    /// Decrypts every secret, an empty map when nothing was saved yet
+    pub fn read(&self) -> Result<BTreeMap<String, String>, String> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(format!("failed to read secrets: {}", e)),
+        };
+        Self::decrypt(&content, &self.key()?)
+    }
+
+    fn key(&self) -> Result<[u8; 32], String> {
+        self.key.ok_or_else(|| match &self.error {
+            Some(error) => format!("secrets are locked: {}", error),
+            None => "secrets are locked, enter the passphrase to unlock them".to_string(),
+        })
+    }
+
+    fn decrypt(content: &str, key: &[u8; 32]) -> Result<BTreeMap<String, String>, String> {
+        let file: SecretsFile =
+            serde_json::from_str(content).map_err(|e| format!("invalid secrets file: {}", e))?;
+        let nonce = BASE64
+            .decode(&file.header.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == 24)
+            .ok_or_else(|| "invalid nonce in secrets file".to_string())?;
+        let ciphertext = BASE64
+            .decode(&file.ciphertext)
+            .map_err(|_| "invalid secrets file".to_string())?;
+        let aad = serde_json::to_vec(&file.header).map_err(|e| e.to_string())?;
+        let plaintext = XChaCha20Poly1305::new(key.into())
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| "failed to decrypt secrets, the key doesn't match".to_string())?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("invalid secrets: {}", e))
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>, key: &[u8; 32]) -> Result<(), String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let header = SecretsHeader {
+            format_version: SECRETS_FORMAT_VERSION,
+            protection: self.protection,
+            kdf: self.kdf.clone(),
+            nonce: BASE64.encode(nonce),
+        };
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+        let aad = serde_json::to_vec(&header).map_err(|e| e.to_string())?;
+        let ciphertext = XChaCha20Poly1305::new(key.into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| "failed to encrypt secrets".to_string())?;
+        let content = serde_json::to_vec_pretty(&SecretsFile {
+            header,
+            ciphertext: BASE64.encode(ciphertext),
+        })
+        .map_err(|e| e.to_string())?;
+        let partial_path = self.path.with_extension("partial");
+        write_private(&partial_path, &content)
+            .and_then(|_| fs::rename(&partial_path, &self.path))
+            .map_err(|e| format!("failed to save secrets: {}", e))
+    }
+
+    /// Saves `value` under `name`, None removes it. Nothing is written when it didn't change.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), String> {
+        let mut secrets = self.read()?;
+        let changed = match value {
+            Some(value) => {
+                secrets
+                    .insert(name.to_string(), value.to_string())
+                    .as_deref()
+                    != Some(value)
+            }
+            None => secrets.remove(name).is_some(),
+        };
+        if !changed {
+            return Ok(());
+        }
+        // read() succeeds while locked when nothing was saved yet
+        self.write(&secrets, &self.key()?)
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), String> {
+        if self.protection != SecretsProtection::Passphrase {
+            return Ok(());
+        }
+        let kdf = self
+            .kdf
+            .clone()
+            .ok_or_else(|| "secrets file has no key derivation parameters".to_string())?;
+        let key = kdf.derive_key(passphrase)?;
+        let content =
+            fs::read_to_string(&self.path).map_err(|e| format!("failed to read secrets: {}", e))?;
+        Self::decrypt(&content, &key).map_err(|_| "wrong passphrase".to_string())?;
+        self.key = Some(key);
+        self.error = None;
+        Ok(())
+    }
+
+    /// Encrypts the secrets again with a key from `protection`, the store must be unlocked
+    pub fn set_protection(
+        &mut self,
+        protection: SecretsProtection,
+        passphrase: Option<&str>,
+    ) -> Result<SecretsStoreStatus, String> {
+        let secrets = self.read()?;
+        let (kdf, key) = match protection {
+            SecretsProtection::Passphrase => {
+                let passphrase = passphrase
+                    .filter(|passphrase| passphrase.chars().count() >= 8)
+                    .ok_or_else(|| "passphrase must have at least 8 characters".to_string())?;
+                let kdf = KdfParams::default();
+                let key = kdf.derive_key(passphrase)?;
+                (Some(kdf), key)
+            }
+            _ => (None, self.seed_key(protection)?),
+        };
+        let previous = (self.protection, self.kdf.clone());
+        self.protection = protection;
+        self.kdf = kdf;
+        if let Err(e) = self.write(&secrets, &key) {
+            (self.protection, self.kdf) = previous;
+            return Err(e);
+        }
+        self.key = Some(key);
+        self.error = None;
+        if protection != SecretsProtection::MachineFile {
+            let _ = fs::remove_file(&self.seed_path);
+        }
+        Ok(self.status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MACHINE_ID: &[u8] = b"8f14e45fceea167a5a36dedd4bea2543";
+
+    #[test]
+    fn test_machine_file_secrets_round_trip_and_passphrase() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app_data_dir = temp_dir.path();
+        let mut store = SecretsStore {
+            path: app_data_dir.join(SECRETS_FILE_NAME),
+            seed_path: app_data_dir.join(SEED_FILE_NAME),
+            protection: SecretsProtection::MachineFile,
+            kdf: None,
+            machine_id: Some(MACHINE_ID.to_vec()),
+            key: None,
+            error: None,
+        };
+        store.key = Some(store.seed_key(SecretsProtection::MachineFile).unwrap());
+        store.set("initial_agent_api_keys", Some("sk-123")).unwrap();
+        let content = fs::read_to_string(app_data_dir.join(SECRETS_FILE_NAME)).unwrap();
+        assert!(!content.contains("sk-123"));
+
+        let reopened = SecretsStore::open_on_machine(app_data_dir, Some(MACHINE_ID.to_vec()));
+        assert_eq!(reopened.status().protection, SecretsProtection::MachineFile);
+        assert_eq!(
+            reopened.read().unwrap().get("initial_agent_api_keys"),
+            Some(&"sk-123".to_string())
+        );
+        let copied = SecretsStore::open_on_machine(app_data_dir, Some(b"other-machine".to_vec()));
+        assert!(copied.read().is_err());
+        let unbound = SecretsStore::open_on_machine(app_data_dir, None);
+        assert!(unbound.status().locked);
+
+        // Same as set_protection with cheap parameters so the test doesn't spend seconds
+        // deriving keys
+        let secrets = store.read().unwrap();
+        let kdf = KdfParams::new(64, 1);
+        let key = kdf.derive_key("correct horse").unwrap();
+        store.protection = SecretsProtection::Passphrase;
+        store.kdf = Some(kdf);
+        store.write(&secrets, &key).unwrap();
+        store.key = Some(key);
+        store.set("initial_agent_api_keys", None).unwrap();
+        store.set("proof_key", Some("proof")).unwrap();
+
+        let mut locked = SecretsStore::open_on_machine(app_data_dir, Some(MACHINE_ID.to_vec()));
+        assert!(locked.status().locked);
+        assert!(locked.read().is_err());
+        assert!(locked.unlock("wrong horse").is_err());
+        locked.unlock("correct horse").unwrap();
+        assert_eq!(
+            locked.read().unwrap().into_iter().collect::<Vec<_>>(),
+            vec![("proof_key".to_string(), "proof".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_set_fails_while_locked_without_secrets_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut store = SecretsStore {
+            path: temp_dir.path().join(SECRETS_FILE_NAME),
+            seed_path: temp_dir.path().join(SEED_FILE_NAME),
+            protection: SecretsProtection::Keyring,
+            kdf: None,
+            machine_id: None,
+            key: None,
+            error: Some("keyring is not available".to_string()),
+        };
+        assert!(store.read().unwrap().is_empty());
+        let error = store.set("proof_key", Some("proof")).unwrap_err();
+        assert!(error.starts_with("secrets are locked"));
+        assert!(!store.path.exists());
+    }
+
+    #[test]
+    fn test_parse_machine_ids() {
+        let ioreg = r#"+-o MacBookPro18,3  <class IOPlatformExpertDevice, id 0x100000223>
+    {
+      "IOPlatformSerialNumber" = "C02XXXXXXXXX"
+      "IOPlatformUUID" = "564D2A1C-9B5F-4E1A-8C2B-0F6B3D8A1E77"
+    }"#;
+        assert_eq!(
+            parse_ioreg_platform_uuid(ioreg).as_deref(),
+            Some("564D2A1C-9B5F-4E1A-8C2B-0F6B3D8A1E77")
+        );
+        assert_eq!(parse_ioreg_platform_uuid("\"IOPlatformUUID\" = \"\""), None);
+
+        let registry = concat!(
+            "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Cryptography\r\n",
+            "    MachineGuid    REG_SZ    3f8a1c2e-5b7d-4e9f-a1b2-c3d4e5f60718\r\n",
+        );
+        assert_eq!(
+            parse_registry_machine_guid(registry).as_deref(),
+            Some("3f8a1c2e-5b7d-4e9f-a1b2-c3d4e5f60718")
+        );
+        assert_eq!(parse_registry_machine_guid("ERROR: not found"), None);
+    }
+}
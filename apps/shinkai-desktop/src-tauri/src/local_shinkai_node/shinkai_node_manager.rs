@@ -1,21 +1,59 @@
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use super::node_storage::backup_schedule::{
+    self, BackupFile, BackupIntegrity, BackupSchedule, StorageBackupEntry,
+};
+use super::node_storage::identity_keys::{self, IdentityKeys};
+use super::node_storage::storage_backup::{
+    self, StorageBackupManifest, StorageBackupOrigin, StorageRestore,
+};
+use super::node_storage::storage_relocation::{self, StorageRelocation};
+use super::node_storage::storage_reset::{self, StorageReset, StorageResetScope};
+use super::node_storage::storage_usage::{self, StorageUsage};
 use super::ollama_api::ollama_api_client::OllamaApiClient;
-use super::ollama_api::ollama_api_types::OllamaApiPullResponse;
-use super::process_handlers::ollama_process_handler::OllamaProcessHandler;
+use super::ollama_api::ollama_api_types::{OllamaApiGenerateOptions, OllamaApiPullResponse};
+use super::ollama_api::ollama_disk_preflight::{
+    evaluate_disk_space, DiskSpacePreflight, PullModelError,
+};
+use super::ollama_api::ollama_model_archive::{self, ModelArchiveManifest};
+use super::ollama_api::ollama_model_benchmark::{self, BenchmarkRecord};
+use super::ollama_api::ollama_model_memory::{
+    self, IdleUnloadMonitor, LoadedModel, MemoryPressure, MemoryPressureMonitor,
+    MemoryPressureOptions, OllamaMemorySettings,
+};
+use super::ollama_api::ollama_model_store::{
+    OllamaModelStore, PartialDownloadsCleanup, IMPORT_STAGING_PREFIX,
+};
+use super::ollama_api::ollama_model_usage::{self, ModelGarbageCollection, ModelStoreUsage};
+use super::ollama_api::ollama_registry_client::{OllamaRegistryClient, RegistryReachability};
+use super::process_handlers::ollama_process_handler::{OllamaOptions, OllamaProcessHandler};
+use super::process_handlers::process_limits::{available_cpus, ProcessLimits};
+use super::process_handlers::process_utils::{self, PortListener, SidecarPid};
+use super::process_handlers::resource_usage::{ResourceUsageSample, ResourceUsageSampler};
 use super::process_handlers::shinkai_node_process_handler::ShinkaiNodeProcessHandler;
+use super::secrets_store::{SecretsProtection, SecretsStoreStatus};
+use crate::hardware;
+use crate::hardware_requirements::{RequirementProfiles, PROFILES_OVERRIDE_FILE_NAME};
 use crate::local_shinkai_node::shinkai_node_options::ShinkaiNodeOptions;
 use crate::models::embedding_model;
+use crate::models::model_catalog::MODEL_CATALOG;
+use crate::models::model_recommendations::{self, InstalledModel, ModelRecommendations};
 use anyhow::Result;
 use futures_util::StreamExt;
 use log::error;
 use serde::{Deserialize, Serialize};
+use sysinfo::System;
 use tauri::path::BaseDirectory;
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri::Manager;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc::channel;
+use tokio::sync::RwLock;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub enum ShinkaiNodeManagerEvent {
@@ -37,6 +75,49 @@ pub enum ShinkaiNodeManagerEvent {
     CreatingModelDone { model: String },
     CreatingModelError { model: String, error: String },
 
+    ExportingModelStart { model: String },
+    ExportingModelProgress { model: String, progress: u32 },
+    ExportingModelDone { model: String },
+    ExportingModelError { model: String, error: String },
+
+    ImportingModelStart { model: String },
+    ImportingModelProgress { model: String, progress: u32 },
+    ImportingModelDone { model: String },
+    ImportingModelError { model: String, error: String },
+
+    LoadingModelStart { model: String },
+    LoadingModelDone { model: String },
+    LoadingModelError { model: String, error: String },
+
+    UnloadingModelDone { model: String },
+    UnloadingModelError { model: String, error: String },
+
+    BenchmarkingModelStart { model: String },
+    BenchmarkingModelDone { model: String },
+    BenchmarkingModelError { model: String, error: String },
+
+    MemoryPressure { pressure: MemoryPressure },
+
+    BackingUpStorageStart,
+    BackingUpStorageProgress { progress: u32 },
+    BackingUpStorageDone,
+    BackingUpStorageError { error: String },
+
+    RestoringStorageStart,
+    RestoringStorageProgress { progress: u32 },
+    RestoringStorageDone,
+    RestoringStorageError { error: String },
+
+    RelocatingStorageStart,
+    RelocatingStorageProgress { progress: u32 },
+    RelocatingStorageDone,
+    RelocatingStorageError { error: String },
+
+    ScheduledBackupDone { backup: StorageBackupEntry },
+    ScheduledBackupError { error: String },
+
+    ScanningStorageProgress { files: u64, size: u64 },
+
     StoppingShinkaiNode,
     ShinkaiNodeStopped,
     ShinkaiNodeStopError { error: String },
@@ -46,39 +127,141 @@ pub enum ShinkaiNodeManagerEvent {
     OllamaStopError { error: String },
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SidecarLimits {
+    pub background_mode: bool,
+    pub shinkai_node: ProcessLimits,
+    pub ollama: ProcessLimits,
+    /// Limits are best-effort, these say why they couldn't be fully applied
+    pub shinkai_node_limits_error: Option<String>,
+    pub ollama_limits_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RelocatedStorage {
+    pub relocation: StorageRelocation,
+    /// Node options pointing at the new storage folder
+    pub options: ShinkaiNodeOptions,
+}
+
+/// A due scheduled backup, the node is already stopped for it
+struct ScheduledBackupJob {
+    storage_path: PathBuf,
+    origin: StorageBackupOrigin,
+    archive_path: PathBuf,
+    created_at: chrono::DateTime<chrono::Utc>,
+    node_was_running: bool,
+    event_broadcaster: broadcast::Sender<ShinkaiNodeManagerEvent>,
+}
+
 pub struct ShinkaiNodeManager {
     ollama_process: OllamaProcessHandler,
     shinkai_node_process: ShinkaiNodeProcessHandler,
     event_broadcaster: broadcast::Sender<ShinkaiNodeManagerEvent>,
     app_resource_dir: PathBuf,
+    app_data_dir: PathBuf,
     llm_models_path: PathBuf,
+    idle_unload_monitor: Option<IdleUnloadMonitor>,
+    memory_pressure_monitor: Option<MemoryPressureMonitor>,
+    resource_usage_sampler: ResourceUsageSampler,
+    background_mode: bool,
+    app_version: String,
+    backup_schedule: Option<BackupSchedule>,
+    backup_due_since: Option<Instant>,
+    backup_retry_after: Option<Instant>,
+    /// Set while a scheduled backup has the node stopped, the manager lock isn't held meanwhile
+    scheduled_backup_running: bool,
+    /// Verification results by backup path, along with the size they were computed for
+    verified_backups: Mutex<HashMap<PathBuf, (u64, BackupIntegrity)>>,
+    /// Sidecars the previous run of the app recorded, they may still be holding ports
+    previous_run_sidecars: Vec<SidecarPid>,
 }
 
 impl ShinkaiNodeManager {
+    const PARTIAL_DOWNLOAD_STALE_MS: u64 = 10 * 60 * 1000;
+    const BENCHMARK_HISTORY_FILE_NAME: &'static str = "model-benchmarks.json";
+    const BACKUP_SCHEDULE_FILE_NAME: &'static str = "backup-schedule.json";
+    const STORAGE_LOCATION_FILE_NAME: &'static str = "storage-location.json";
+    const OLLAMA_OPTIONS_FILE_NAME: &'static str = "ollama-options.json";
+    const OLLAMA_MEMORY_SETTINGS_FILE_NAME: &'static str = "ollama-memory-settings.json";
+    const STORAGE_USAGE_HISTORY_FILE_NAME: &'static str = "storage-usage-history.json";
+    const SIDECAR_PIDS_FILE_NAME: &'static str = "sidecar-pids.json";
+    const STORAGE_USAGE_DEFAULT_TOP: usize = 20;
+    const RESOURCE_USAGE_EVENT: &'static str = "shinkai-node-resource-usage";
+    const BACKUP_SCHEDULER_INTERVAL_MS: u64 = 5 * 60 * 1000;
+    /// Past this delay a due backup pauses the node even if it's busy
+    const BACKUP_MAX_DEFERRAL_MS: u64 = 2 * 60 * 60 * 1000;
+    const BACKUP_RETRY_DELAY_MS: u64 = 60 * 60 * 1000;
+    /// The node is idle when it stayed under this cpu usage for the last samples
+    const IDLE_CPU_PERCENT: f32 = 10.0;
+    const IDLE_SAMPLES: usize = 10;
+
     pub(crate) fn new(app: AppHandle, app_resource_dir: PathBuf, app_data_dir: PathBuf) -> Self {
         let (ollama_sender, _ollama_receiver) = channel(100);
         let (shinkai_node_sender, _shinkai_node_receiver) = channel(100);
         let (event_broadcaster, _) = broadcast::channel(10);
+        let app_version = app.package_info().version.to_string();
+        let backup_schedule =
+            backup_schedule::load_schedule(&app_data_dir.join(Self::BACKUP_SCHEDULE_FILE_NAME));
+        let previous_run_sidecars =
+            process_utils::load_sidecar_pids(&app_data_dir.join(Self::SIDECAR_PIDS_FILE_NAME));
         let llm_models_path = app
             .path()
             .resolve("llm-models", BaseDirectory::Resource)
             .unwrap();
-        ShinkaiNodeManager {
-            ollama_process: OllamaProcessHandler::new(
-                app.clone(),
-                ollama_sender,
-                app_resource_dir.clone(),
-            ),
-            shinkai_node_process: ShinkaiNodeProcessHandler::new(
-                app,
-                shinkai_node_sender,
-                app_resource_dir.clone(),
-                app_data_dir,
-            ),
+        let mut ollama_process =
+            OllamaProcessHandler::new(app.clone(), ollama_sender, app_resource_dir.clone());
+        if let Some(options) =
+            OllamaOptions::load(&app_data_dir.join(Self::OLLAMA_OPTIONS_FILE_NAME))
+        {
+            ollama_process.set_options(options);
+        }
+        let shinkai_node_process = ShinkaiNodeProcessHandler::new(
+            app.clone(),
+            shinkai_node_sender,
+            app_resource_dir.clone(),
+            app_data_dir.clone(),
+        );
+        let resource_usage_sampler = ResourceUsageSampler::start(
+            vec![
+                ("shinkai-node".to_string(), shinkai_node_process.pid()),
+                ("ollama".to_string(), ollama_process.pid()),
+            ],
+            // Samples have their own event so they don't crowd out state changes in the
+            // broadcast channel
+            move |sample| {
+                let _ = app.emit(Self::RESOURCE_USAGE_EVENT, sample);
+            },
+        );
+        let mut shinkai_node_manager = ShinkaiNodeManager {
+            ollama_process,
+            shinkai_node_process,
             event_broadcaster,
             app_resource_dir,
+            app_data_dir,
             llm_models_path,
-        }
+            idle_unload_monitor: None,
+            memory_pressure_monitor: None,
+            resource_usage_sampler,
+            background_mode: false,
+            app_version,
+            backup_schedule,
+            backup_due_since: None,
+            backup_retry_after: None,
+            scheduled_backup_running: false,
+            verified_backups: Mutex::new(HashMap::new()),
+            previous_run_sidecars,
+        };
+        shinkai_node_manager.load_storage_location();
+        let memory_settings = OllamaMemorySettings::load(
+            &shinkai_node_manager
+                .app_data_dir
+                .join(Self::OLLAMA_MEMORY_SETTINGS_FILE_NAME),
+        )
+        .unwrap_or_default();
+        shinkai_node_manager.start_idle_unload_monitor(memory_settings.idle_unload_timeout_minutes);
+        shinkai_node_manager.start_memory_pressure_monitor(memory_settings.memory_pressure);
+        shinkai_node_manager
     }
 
     pub async fn get_shinkai_node_options(&self) -> ShinkaiNodeOptions {
@@ -91,6 +274,7 @@ impl ShinkaiNodeManager {
     }
 
     pub async fn spawn(&mut self) -> Result<(), String> {
+        self.check_no_scheduled_backup()?;
         self.emit_event(ShinkaiNodeManagerEvent::StartingOllama);
         match self.ollama_process.spawn(None).await {
             Ok(_) => {
@@ -133,6 +317,18 @@ impl ShinkaiNodeManager {
             // Use the embedded GGUF model
             let gguf_data = embedding_model::get_model_data(&self.llm_models_path);
 
+            if let Err(e) =
+                self.check_disk_space(&default_embedding_model, Some(gguf_data.len() as u64))
+            {
+                error!("refusing to create model from gguf: {}", e);
+                self.kill().await;
+                self.emit_event(ShinkaiNodeManagerEvent::CreatingModelError {
+                    model: default_embedding_model.to_string(),
+                    error: e.to_string(),
+                });
+                return Err(e.to_string());
+            }
+
             match ollama_api
                 .create_model_from_gguf(&default_embedding_model, gguf_data)
                 .await
@@ -167,6 +363,7 @@ impl ShinkaiNodeManager {
                 return Err(e);
             }
         }
+        self.record_sidecar_pids().await;
         Ok(())
     }
 
@@ -177,12 +374,632 @@ impl ShinkaiNodeManager {
         self.emit_event(ShinkaiNodeManagerEvent::StoppingOllama);
         self.ollama_process.kill().await;
         self.emit_event(ShinkaiNodeManagerEvent::OllamaStopped);
+        self.record_sidecar_pids().await;
     }
 
+    /// Removes the whole storage for good, a quarantine would keep all of it on disk
     pub async fn remove_storage(&self, preserve_keys: bool) -> Result<(), String> {
-        self.shinkai_node_process
-            .remove_storage(preserve_keys)
-            .await
+        self.reset_storage(
+            vec![StorageResetScope::Everything],
+            preserve_keys,
+            false,
+            true,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Moves the storage entries covered by `scopes` into a quarantine so the reset can be
+    /// undone for a while, `dry_run` only lists them and `purge` deletes them right away
+    pub async fn reset_storage(
+        &self,
+        scopes: Vec<StorageResetScope>,
+        preserve_keys: bool,
+        dry_run: bool,
+        purge: bool,
+    ) -> Result<StorageReset, String> {
+        if scopes.is_empty() {
+            return Err("at least one reset scope is required".to_string());
+        }
+        let storage_path = self.get_node_storage_path();
+        if dry_run {
+            return storage_reset::plan_reset(&storage_path, &scopes, preserve_keys);
+        }
+        self.check_no_scheduled_backup()?;
+        if self.shinkai_node_process.is_running().await {
+            return Err("can't reset node storage while it's running".to_string());
+        }
+        storage_reset::reset_storage(&storage_path, &scopes, preserve_keys, purge)
+    }
+
+    pub fn get_last_storage_reset(&self) -> Result<Option<StorageReset>, String> {
+        storage_reset::last_reset(&self.get_node_storage_path())
+    }
+
+    pub async fn undo_storage_reset(&self) -> Result<StorageReset, String> {
+        self.check_no_scheduled_backup()?;
+        if self.shinkai_node_process.is_running().await {
+            return Err("can't undo a storage reset while the node is running".to_string());
+        }
+        storage_reset::undo_last_reset(&self.get_node_storage_path())
+    }
+
+    /// Encrypts the node identity keys with `passphrase` into `export_path`
+    pub async fn export_identity_keys(
+        &self,
+        export_path: PathBuf,
+        passphrase: String,
+    ) -> Result<IdentityKeys, String> {
+        let storage_path = self.get_node_storage_path();
+        tauri::async_runtime::spawn_blocking(move || {
+            identity_keys::export_identity(&storage_path, &export_path, &passphrase)
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    pub fn inspect_identity_export(&self, export_path: PathBuf) -> Result<IdentityKeys, String> {
+        identity_keys::inspect_identity_export(&export_path)
+    }
+
+    /// Imports exported identity keys into the node storage, `overwrite` confirms replacing
+    /// the identity or data already there
+    pub async fn import_identity_keys(
+        &self,
+        export_path: PathBuf,
+        passphrase: String,
+        overwrite: bool,
+    ) -> Result<IdentityKeys, String> {
+        self.check_no_scheduled_backup()?;
+        if self.shinkai_node_process.is_running().await {
+            return Err("can't import identity keys while the node is running".to_string());
+        }
+        let storage_path = self.get_node_storage_path();
+        tauri::async_runtime::spawn_blocking(move || {
+            identity_keys::import_identity(&export_path, &storage_path, &passphrase, overwrite)
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    /// Breaks down the node storage size by category, growth is relative to the previous scan
+    pub async fn get_storage_usage(&self, top: Option<usize>) -> Result<StorageUsage, String> {
+        let storage_path = self.get_node_storage_path();
+        let history_path = self
+            .app_data_dir
+            .join(Self::STORAGE_USAGE_HISTORY_FILE_NAME);
+        let top = top.unwrap_or(Self::STORAGE_USAGE_DEFAULT_TOP);
+        let event_broadcaster = self.event_broadcaster.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            let storage_path_string = storage_path.to_string_lossy().to_string();
+            let previous = storage_usage::load_history(&history_path)
+                .into_iter()
+                .rfind(|snapshot| snapshot.storage_path == storage_path_string);
+            let usage = storage_usage::scan_storage(
+                &storage_path,
+                top,
+                previous.as_ref(),
+                |files, size| {
+                    let _ = event_broadcaster
+                        .send(ShinkaiNodeManagerEvent::ScanningStorageProgress { files, size });
+                },
+            )?;
+            if let Err(e) = storage_usage::save_history(&history_path, usage.snapshot()) {
+                error!("{}", e);
+            }
+            Ok(usage)
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    /// Archives the node storage into `archive_path`, the node has to be stopped
+    pub async fn backup_storage(
+        &self,
+        archive_path: PathBuf,
+    ) -> Result<StorageBackupManifest, String> {
+        self.check_no_scheduled_backup()?;
+        if self.shinkai_node_process.is_running().await {
+            return Err("can't back up node storage while it's running".to_string());
+        }
+        let storage_path = self.get_node_storage_path();
+        let origin = self.backup_origin(&storage_path);
+        Self::archive_storage(
+            self.event_broadcaster.clone(),
+            storage_path,
+            archive_path,
+            origin,
+        )
+        .await
+    }
+
+    fn backup_origin(&self, storage_path: &Path) -> StorageBackupOrigin {
+        StorageBackupOrigin {
+            app_version: self.app_version.clone(),
+            node_version: self.shinkai_node_process.version(),
+            identity_name: self
+                .shinkai_node_process
+                .get_options()
+                .global_identity_name
+                .or_else(|| storage_backup::read_identity_name(storage_path)),
+        }
+    }
+
+    /// It doesn't borrow the manager so the backup scheduler can release its lock meanwhile
+    async fn archive_storage(
+        event_broadcaster: broadcast::Sender<ShinkaiNodeManagerEvent>,
+        storage_path: PathBuf,
+        archive_path: PathBuf,
+        origin: StorageBackupOrigin,
+    ) -> Result<StorageBackupManifest, String> {
+        let _ = event_broadcaster.send(ShinkaiNodeManagerEvent::BackingUpStorageStart);
+        let progress_broadcaster = event_broadcaster.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            storage_backup::backup_storage(&storage_path, &archive_path, origin, |progress| {
+                let _ = progress_broadcaster
+                    .send(ShinkaiNodeManagerEvent::BackingUpStorageProgress { progress });
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+
+        match &result {
+            Ok(_) => {
+                let _ = event_broadcaster.send(ShinkaiNodeManagerEvent::BackingUpStorageDone);
+            }
+            Err(e) => {
+                error!("failed to back up node storage: {}", e);
+                let _ = event_broadcaster
+                    .send(ShinkaiNodeManagerEvent::BackingUpStorageError { error: e.clone() });
+            }
+        }
+        result
+    }
+
+    pub async fn verify_storage_backup(
+        &self,
+        archive_path: PathBuf,
+    ) -> Result<StorageBackupManifest, String> {
+        tauri::async_runtime::spawn_blocking(move || {
+            storage_backup::verify_backup(&archive_path, |_| {})
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    /// Replaces the node storage with a backup, the current storage is kept aside
+    pub async fn restore_storage(&self, archive_path: PathBuf) -> Result<StorageRestore, String> {
+        self.check_no_scheduled_backup()?;
+        if self.shinkai_node_process.is_running().await {
+            return Err("can't restore node storage while it's running".to_string());
+        }
+        let storage_path = self.get_node_storage_path();
+        self.emit_event(ShinkaiNodeManagerEvent::RestoringStorageStart);
+
+        let event_broadcaster = self.event_broadcaster.clone();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            storage_backup::restore_storage(&archive_path, &storage_path, |progress| {
+                let _ = event_broadcaster
+                    .send(ShinkaiNodeManagerEvent::RestoringStorageProgress { progress });
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+
+        match result {
+            Ok(restore) => {
+                self.emit_event(ShinkaiNodeManagerEvent::RestoringStorageDone);
+                Ok(restore)
+            }
+            Err(e) => {
+                error!("failed to restore node storage: {}", e);
+                self.emit_event(ShinkaiNodeManagerEvent::RestoringStorageError {
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    /// Moves the node storage to `destination`. The node is stopped while copying and the old
+    /// folder is only removed once the copy is verified and, if the node was running, it
+    /// restarted from the new one.
+    pub async fn relocate_storage(
+        &mut self,
+        destination: PathBuf,
+    ) -> Result<RelocatedStorage, String> {
+        self.check_no_scheduled_backup()?;
+        let storage_path = self.get_node_storage_path();
+        storage_relocation::validate_destination(&storage_path, &destination)?;
+        self.emit_event(ShinkaiNodeManagerEvent::RelocatingStorageStart);
+        match self.relocate_storage_to(storage_path, destination).await {
+            Ok(relocation) => {
+                self.emit_event(ShinkaiNodeManagerEvent::RelocatingStorageDone);
+                Ok(RelocatedStorage {
+                    relocation,
+                    options: self.shinkai_node_process.get_options(),
+                })
+            }
+            Err(e) => {
+                error!("failed to relocate node storage: {}", e);
+                self.emit_event(ShinkaiNodeManagerEvent::RelocatingStorageError {
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    async fn relocate_storage_to(
+        &mut self,
+        storage_path: PathBuf,
+        destination: PathBuf,
+    ) -> Result<StorageRelocation, String> {
+        let location_path = self.app_data_dir.join(Self::STORAGE_LOCATION_FILE_NAME);
+        let previous_location = storage_relocation::load_storage_location(&location_path);
+        let node_was_running = self.shinkai_node_process.is_running().await;
+        if node_was_running {
+            self.emit_event(ShinkaiNodeManagerEvent::StoppingShinkaiNode);
+            self.shinkai_node_process.kill().await;
+            self.emit_event(ShinkaiNodeManagerEvent::ShinkaiNodeStopped);
+        }
+
+        let event_broadcaster = self.event_broadcaster.clone();
+        let source_path = storage_path.clone();
+        let copy = tauri::async_runtime::spawn_blocking(move || {
+            storage_relocation::copy_storage(&source_path, &destination, |progress| {
+                let _ = event_broadcaster
+                    .send(ShinkaiNodeManagerEvent::RelocatingStorageProgress { progress });
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+        let mut relocation = match copy {
+            Ok(relocation) => relocation,
+            Err(e) => {
+                if node_was_running {
+                    let _ = self.spawn_shinkai_node().await;
+                }
+                return Err(e);
+            }
+        };
+
+        let new_storage_path = PathBuf::from(&relocation.storage_path);
+        let switched = storage_relocation::save_storage_location(
+            &location_path,
+            Some(&relocation.storage_path),
+        );
+        let started = match switched {
+            Ok(_) => {
+                self.set_node_storage_path(relocation.storage_path.clone());
+                if node_was_running {
+                    self.spawn_shinkai_node()
+                        .await
+                        .map_err(|e| format!("node failed to start from the new folder: {}", e))
+                } else {
+                    Ok(())
+                }
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = started {
+            // The previous folder is untouched so going back to it is enough
+            if let Err(e) = storage_relocation::save_storage_location(
+                &location_path,
+                previous_location.as_deref(),
+            ) {
+                error!("failed to restore previous storage location: {}", e);
+            }
+            self.set_node_storage_path(relocation.previous_storage_path.clone());
+            if let Err(e) = fs::remove_dir_all(&new_storage_path) {
+                error!(
+                    "failed to remove storage copy {}: {}",
+                    new_storage_path.display(),
+                    e
+                );
+            }
+            if node_was_running {
+                let _ = self.spawn_shinkai_node().await;
+            }
+            return Err(e);
+        }
+
+        // The copy was verified against the source hashes, a node the user stopped stays stopped
+        relocation.source_removed =
+            tauri::async_runtime::spawn_blocking(move || fs::remove_dir_all(&storage_path))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|result| result.map_err(|e| e.to_string()))
+                .map_err(|e| error!("failed to remove previous storage folder: {}", e))
+                .is_ok();
+        Ok(relocation)
+    }
+
+    async fn spawn_shinkai_node(&self) -> Result<(), String> {
+        self.emit_event(ShinkaiNodeManagerEvent::StartingShinkaiNode);
+        match self.shinkai_node_process.spawn().await {
+            Ok(_) => {
+                self.emit_event(ShinkaiNodeManagerEvent::ShinkaiNodeStarted);
+                self.record_sidecar_pids().await;
+                Ok(())
+            }
+            Err(e) => {
+                error!("failed to start shinkai node: {}", e);
+                self.emit_event(ShinkaiNodeManagerEvent::ShinkaiNodeStartError {
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    fn check_no_scheduled_backup(&self) -> Result<(), String> {
+        if self.scheduled_backup_running {
+            return Err("a scheduled backup of the node storage is in progress".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn get_backup_schedule(&self) -> Option<BackupSchedule> {
+        self.backup_schedule.clone()
+    }
+
+    /// None disables scheduled backups, existing backups are left untouched
+    pub fn set_backup_schedule(
+        &mut self,
+        schedule: Option<BackupSchedule>,
+    ) -> Result<Option<BackupSchedule>, String> {
+        if let Some(schedule) = &schedule {
+            schedule.validate(&self.get_node_storage_path())?;
+            fs::create_dir_all(&schedule.backups_path)
+                .map_err(|e| format!("failed to create backups folder: {}", e))?;
+        }
+        backup_schedule::save_schedule(
+            &self.app_data_dir.join(Self::BACKUP_SCHEDULE_FILE_NAME),
+            schedule.as_ref(),
+        )?;
+        self.backup_schedule = schedule;
+        self.backup_due_since = None;
+        self.backup_retry_after = None;
+        Ok(self.get_backup_schedule())
+    }
+
+    async fn backup_integrity(&self, backup: &BackupFile, verify: bool) -> BackupIntegrity {
+        // Cloned so the lock isn't held across the verification
+        let verified = self
+            .verified_backups
+            .lock()
+            .unwrap()
+            .get(&backup.path)
+            .cloned();
+        if let Some((size, integrity)) = verified {
+            if size == backup.size {
+                return integrity;
+            }
+        }
+        if !verify {
+            return BackupIntegrity::Unverified;
+        }
+        let integrity = Self::verify_backup_file(backup.path.clone()).await;
+        self.record_backup_integrity(backup, integrity.clone());
+        integrity
+    }
+
+    async fn verify_backup_file(path: PathBuf) -> BackupIntegrity {
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            storage_backup::verify_backup(&path, |_| {})
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+        match result {
+            Ok(_) => BackupIntegrity::Valid,
+            Err(error) => BackupIntegrity::Corrupted { error },
+        }
+    }
+
+    fn record_backup_integrity(&self, backup: &BackupFile, integrity: BackupIntegrity) {
+        self.verified_backups
+            .lock()
+            .unwrap()
+            .insert(backup.path.clone(), (backup.size, integrity));
+    }
+
+    /// Backups in the scheduled backups folder, newest first. `verify` reads the backups that
+    /// haven't been checked yet which can take a while.
+    pub async fn list_storage_backups(
+        &self,
+        verify: bool,
+    ) -> Result<Vec<StorageBackupEntry>, String> {
+        let schedule = self
+            .backup_schedule
+            .as_ref()
+            .ok_or_else(|| "scheduled backups aren't configured".to_string())?;
+        let backups = backup_schedule::list_backup_files(Path::new(&schedule.backups_path))?;
+        let mut entries = Vec::new();
+        for backup in backups {
+            let integrity = self.backup_integrity(&backup, verify).await;
+            entries.push(backup.entry(integrity));
+        }
+        Ok(entries)
+    }
+
+    fn is_idle(&self) -> bool {
+        let history = self.resource_usage_sampler.history();
+        history.len() >= Self::IDLE_SAMPLES
+            && history[history.len() - Self::IDLE_SAMPLES..]
+                .iter()
+                .flat_map(|sample| sample.processes.iter())
+                .all(|process| process.cpu_percent < Self::IDLE_CPU_PERCENT)
+    }
+
+    /// Starts the scheduled backup when it's due. It waits for the node to be idle and stops
+    /// it, the archive is written by the caller once the manager lock is released.
+    async fn start_scheduled_backup_if_due(&mut self) -> Option<ScheduledBackupJob> {
+        let schedule = self.backup_schedule.clone()?;
+        if self
+            .backup_retry_after
+            .is_some_and(|retry_after| Instant::now() < retry_after)
+        {
+            return None;
+        }
+        let backups_path = PathBuf::from(&schedule.backups_path);
+        let last_backup = match backup_schedule::list_backup_files(&backups_path) {
+            Ok(backups) => backups.first().map(|backup| backup.created_at),
+            Err(e) => {
+                self.fail_scheduled_backup(e);
+                return None;
+            }
+        };
+        if !backup_schedule::is_backup_due(last_backup, chrono::Utc::now(), schedule.frequency) {
+            self.backup_due_since = None;
+            return None;
+        }
+        let due_since = *self.backup_due_since.get_or_insert_with(Instant::now);
+        let node_was_running = self.shinkai_node_process.is_running().await;
+        if node_was_running
+            && !self.is_idle()
+            && due_since.elapsed() < Duration::from_millis(Self::BACKUP_MAX_DEFERRAL_MS)
+        {
+            log::info!("scheduled backup is due, waiting for the node to be idle");
+            return None;
+        }
+
+        if node_was_running {
+            self.emit_event(ShinkaiNodeManagerEvent::StoppingShinkaiNode);
+            self.shinkai_node_process.kill().await;
+            self.emit_event(ShinkaiNodeManagerEvent::ShinkaiNodeStopped);
+        }
+        self.scheduled_backup_running = true;
+        let created_at = chrono::Utc::now();
+        let storage_path = self.get_node_storage_path();
+        Some(ScheduledBackupJob {
+            origin: self.backup_origin(&storage_path),
+            storage_path,
+            archive_path: backups_path.join(backup_schedule::backup_file_name(created_at)),
+            created_at,
+            node_was_running,
+            event_broadcaster: self.event_broadcaster.clone(),
+        })
+    }
+
+    /// Restarts the node once the scheduled backup archive is written
+    async fn resume_after_scheduled_backup(&mut self, node_was_running: bool) {
+        self.scheduled_backup_running = false;
+        if node_was_running {
+            let _ = self.spawn_shinkai_node().await;
+        }
+    }
+
+    fn finish_scheduled_backup(&mut self, result: Result<(BackupFile, BackupIntegrity), String>) {
+        let (backup, integrity) = match result {
+            Ok(backup) => backup,
+            Err(e) => {
+                self.fail_scheduled_backup(e);
+                return;
+            }
+        };
+        if let BackupIntegrity::Corrupted { error } = integrity {
+            if let Err(e) = backup_schedule::remove_corrupted_backup(&backup) {
+                error!("{}", e);
+            }
+            self.fail_scheduled_backup(format!("backup failed verification: {}", error));
+            return;
+        }
+        self.record_backup_integrity(&backup, integrity.clone());
+        self.backup_due_since = None;
+        // The schedule could have changed while the backup was running
+        if let Some(schedule) = self.backup_schedule.clone() {
+            self.prune_backups(&schedule);
+        }
+        self.emit_event(ShinkaiNodeManagerEvent::ScheduledBackupDone {
+            backup: backup.entry(integrity),
+        });
+    }
+
+    fn fail_scheduled_backup(&mut self, error: String) {
+        error!("scheduled backup failed: {}", error);
+        self.backup_retry_after =
+            Some(Instant::now() + Duration::from_millis(Self::BACKUP_RETRY_DELAY_MS));
+        self.emit_event(ShinkaiNodeManagerEvent::ScheduledBackupError { error });
+    }
+
+    fn prune_backups(&self, schedule: &BackupSchedule) {
+        let backups = match backup_schedule::list_backup_files(Path::new(&schedule.backups_path)) {
+            Ok(backups) => backups,
+            Err(e) => {
+                error!("failed to list backups to prune: {}", e);
+                return;
+            }
+        };
+        let created_at: Vec<_> = backups.iter().map(|backup| backup.created_at).collect();
+        for index in backup_schedule::backups_to_prune(
+            &created_at,
+            schedule.keep_daily,
+            schedule.keep_weekly,
+        ) {
+            let path = &backups[index].path;
+            log::info!("removing backup {} by retention", path.display());
+            if let Err(e) = fs::remove_file(path) {
+                error!("failed to remove backup {}: {}", path.display(), e);
+            }
+            self.verified_backups.lock().unwrap().remove(path);
+        }
+    }
+
+    /// Checks periodically if a scheduled backup is due, it runs for the whole app lifetime.
+    /// The manager lock is only held to start the backup and to restart the node so other
+    /// commands aren't blocked while the archive is written and verified.
+    pub async fn run_backup_scheduler(shinkai_node_manager: Arc<RwLock<Self>>) {
+        loop {
+            tokio::time::sleep(Duration::from_millis(Self::BACKUP_SCHEDULER_INTERVAL_MS)).await;
+            let Some(job) = shinkai_node_manager
+                .write()
+                .await
+                .start_scheduled_backup_if_due()
+                .await
+            else {
+                continue;
+            };
+            let archived = Self::archive_storage(
+                job.event_broadcaster,
+                job.storage_path,
+                job.archive_path.clone(),
+                job.origin,
+            )
+            .await;
+            shinkai_node_manager
+                .write()
+                .await
+                .resume_after_scheduled_backup(job.node_was_running)
+                .await;
+
+            let result = match archived {
+                Ok(_) => {
+                    let backup = BackupFile {
+                        size: fs::metadata(&job.archive_path)
+                            .map(|metadata| metadata.len())
+                            .unwrap_or_default(),
+                        path: job.archive_path,
+                        created_at: job.created_at,
+                    };
+                    let integrity = Self::verify_backup_file(backup.path.clone()).await;
+                    Ok((backup, integrity))
+                }
+                Err(e) => Err(e),
+            };
+            shinkai_node_manager
+                .write()
+                .await
+                .finish_scheduled_backup(result);
+        }
     }
 
     pub fn open_storage_location(&self) -> Result<(), String> {
@@ -204,17 +1021,779 @@ impl ShinkaiNodeManager {
     }
 
     pub async fn set_default_shinkai_node_options(&mut self) -> ShinkaiNodeOptions {
-        self.shinkai_node_process.set_default_options()
+        self.shinkai_node_process.set_default_options();
+        self.load_storage_location();
+        self.shinkai_node_process.get_options()
     }
 
     pub async fn set_shinkai_node_options(
         &mut self,
-        options: ShinkaiNodeOptions,
+        mut options: ShinkaiNodeOptions,
     ) -> ShinkaiNodeOptions {
+        // The folder saved by the last relocation wins, the frontend could send a stale path
+        // and only relocating the storage moves it
+        if let Some(node_storage_path) = storage_relocation::load_storage_location(
+            &self.app_data_dir.join(Self::STORAGE_LOCATION_FILE_NAME),
+        ) {
+            if options
+                .node_storage_path
+                .as_ref()
+                .is_some_and(|path| *path != node_storage_path)
+            {
+                log::warn!("ignoring node storage path option, the storage was relocated");
+            }
+            options.node_storage_path = Some(node_storage_path);
+        }
         self.shinkai_node_process.set_options(options)
     }
 
-    fn emit_event(&mut self, new_event: ShinkaiNodeManagerEvent) {
+    /// Points the node at the storage folder saved by the last relocation, if any
+    fn load_storage_location(&mut self) {
+        if let Some(node_storage_path) = storage_relocation::load_storage_location(
+            &self.app_data_dir.join(Self::STORAGE_LOCATION_FILE_NAME),
+        ) {
+            self.set_node_storage_path(node_storage_path);
+        }
+    }
+
+    fn set_node_storage_path(&mut self, node_storage_path: String) {
+        let mut options = self.shinkai_node_process.get_options();
+        options.node_storage_path = Some(node_storage_path);
+        self.shinkai_node_process.set_options(options);
+    }
+
+    pub fn get_secrets_status(&self) -> SecretsStoreStatus {
+        self.shinkai_node_process.secrets().lock().unwrap().status()
+    }
+
+    pub async fn unlock_secrets(&self, passphrase: String) -> Result<SecretsStoreStatus, String> {
+        let secrets = self.shinkai_node_process.secrets();
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut secrets = secrets.lock().unwrap();
+            secrets.unlock(&passphrase).map(|_| secrets.status())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    /// Encrypts the secret options again with a key from `protection`
+    pub async fn set_secrets_protection(
+        &self,
+        protection: SecretsProtection,
+        passphrase: Option<String>,
+    ) -> Result<SecretsStoreStatus, String> {
+        let secrets = self.shinkai_node_process.secrets();
+        tauri::async_runtime::spawn_blocking(move || {
+            secrets
+                .lock()
+                .unwrap()
+                .set_protection(protection, passphrase.as_deref())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    pub fn get_ollama_options(&self) -> OllamaOptions {
+        self.ollama_process.get_options()
+    }
+
+    /// Options are saved so registry and proxy settings survive restarts
+    pub fn set_ollama_options(&mut self, options: OllamaOptions) -> Result<OllamaOptions, String> {
+        options.save(&self.app_data_dir.join(Self::OLLAMA_OPTIONS_FILE_NAME))?;
+        let options = self.ollama_process.set_options(options);
+        // The idle timer polls the api so it has to follow host changes
+        let memory_settings = self.memory_settings();
+        self.start_idle_unload_monitor(memory_settings.idle_unload_timeout_minutes);
+        self.start_memory_pressure_monitor(memory_settings.memory_pressure);
+        Ok(options)
+    }
+
+    pub fn get_node_storage_path(&self) -> PathBuf {
+        PathBuf::from(
+            self.shinkai_node_process
+                .get_options()
+                .node_storage_path
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Fails with `PullModelError::InsufficientDiskSpace` when `download_bytes` doesn't fit,
+    /// volumes that can't be inspected are not considered a failure
+    fn check_disk_space(
+        &self,
+        model: &str,
+        download_bytes: Option<u64>,
+    ) -> Result<(), PullModelError> {
+        let models_path = self.ollama_process.get_models_path()?;
+        match evaluate_disk_space(
+            model,
+            download_bytes,
+            &models_path,
+            &self.get_node_storage_path(),
+        ) {
+            Ok(preflight) if !preflight.sufficient => {
+                Err(PullModelError::InsufficientDiskSpace { preflight })
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                log::warn!("skipping disk space check for model {}: {}", model, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads the model manifest from its registry to compute how many bytes are still missing
+    /// locally and compares them with the free space of the models and node storage volumes
+    pub async fn ollama_pull_preflight(&self, model: &str) -> Result<DiskSpacePreflight, String> {
+        self.ollama_process
+            .pull_preflight(model, &self.get_node_storage_path())
+            .await
+    }
+
+    /// Pulls `model` using the configured registry, returns the registry qualified name that was pulled
+    pub async fn pull_ollama_model(&self, model: &str) -> Result<String, PullModelError> {
+        let ollama_options = self.ollama_process.get_options();
+        let model = ollama_options.qualify_model_name(model)?;
+        self.emit_event(ShinkaiNodeManagerEvent::PullingModelStart {
+            model: model.clone(),
+        });
+
+        if let Err(e) = self
+            .ollama_process
+            .check_pull_disk_space(&model, &self.get_node_storage_path())
+            .await
+        {
+            error!("refusing to pull model {}: {}", model, e);
+            self.emit_event(ShinkaiNodeManagerEvent::PullingModelError {
+                model: model.clone(),
+                error: e.to_string(),
+            });
+            return Err(e);
+        }
+
+        let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+        let result = async {
+            let mut stream = ollama_api
+                .pull_stream(&model, ollama_options.is_insecure_registry())
+                .await?;
+            while let Some(stream_value) = stream.next().await {
+                if let OllamaApiPullResponse::Downloading {
+                    total, completed, ..
+                } = stream_value?
+                {
+                    if total > 0 {
+                        self.emit_event(ShinkaiNodeManagerEvent::PullingModelProgress {
+                            model: model.clone(),
+                            progress: ((completed.min(total) * 100) / total) as u32,
+                        });
+                    }
+                }
+            }
+            Ok::<(), String>(())
+        }
+        .await;
+
+        match result {
+            Ok(_) => {
+                self.emit_event(ShinkaiNodeManagerEvent::PullingModelDone {
+                    model: model.clone(),
+                });
+                Ok(model)
+            }
+            Err(e) => {
+                error!("failed to pull model {}: {}", model, e);
+                self.emit_event(ShinkaiNodeManagerEvent::PullingModelError {
+                    model: model.clone(),
+                    error: e.clone(),
+                });
+                Err(PullModelError::from(e))
+            }
+        }
+    }
+
+    /// Removes partial blobs left behind by interrupted pulls or imports, files modified in the
+    /// last minutes are kept because they most likely belong to a download in progress
+    pub async fn cleanup_ollama_partial_downloads(
+        &self,
+    ) -> Result<PartialDownloadsCleanup, String> {
+        let store = OllamaModelStore::new(self.ollama_process.get_models_path()?);
+        tauri::async_runtime::spawn_blocking(move || {
+            store.remove_stale_partial_downloads(Duration::from_millis(
+                Self::PARTIAL_DOWNLOAD_STALE_MS,
+            ))
+        })
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    /// Disk usage of every local model, blobs shared between models and blobs no model references
+    pub async fn get_ollama_models_disk_usage(&self) -> Result<ModelStoreUsage, String> {
+        let store = OllamaModelStore::new(self.ollama_process.get_models_path()?);
+        let tags = if self.ollama_process.is_running().await {
+            let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+            ollama_api.tags().await.ok()
+        } else {
+            None
+        };
+        tauri::async_runtime::spawn_blocking(move || {
+            ollama_model_usage::analyze_model_store(&store, tags.as_ref())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    /// Removes blobs that no model references, `dry_run` only reports what would be removed
+    pub async fn gc_ollama_models(&self, dry_run: bool) -> Result<ModelGarbageCollection, String> {
+        let store = OllamaModelStore::new(self.ollama_process.get_models_path()?);
+        tauri::async_runtime::spawn_blocking(move || {
+            ollama_model_usage::collect_garbage(
+                &store,
+                dry_run,
+                Duration::from_millis(Self::PARTIAL_DOWNLOAD_STALE_MS),
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)
+    }
+
+    /// Models currently loaded by ollama and how much RAM and VRAM they take
+    pub async fn list_ollama_loaded_models(&self) -> Result<Vec<LoadedModel>, String> {
+        if !self.ollama_process.is_running().await {
+            return Ok(vec![]);
+        }
+        let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+        let running_models = ollama_api.ps().await?;
+        Ok(running_models
+            .models
+            .iter()
+            .map(LoadedModel::from)
+            .collect())
+    }
+
+    /// Loads `model` ahead of its first request, `keep_alive` is in seconds and negative values keep it loaded
+    pub async fn preload_ollama_model(
+        &self,
+        model: &str,
+        keep_alive: Option<i64>,
+    ) -> Result<(), String> {
+        if !self.ollama_process.is_running().await {
+            return Err("ollama must be running to load a model".to_string());
+        }
+        self.check_memory_pressure()?;
+        if let Some(idle_unload_monitor) = &self.idle_unload_monitor {
+            idle_unload_monitor.touch();
+        }
+        self.emit_event(ShinkaiNodeManagerEvent::LoadingModelStart {
+            model: model.to_string(),
+        });
+        let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+        match ollama_api
+            .load_model(model, keep_alive, self.ollama_request_options())
+            .await
+        {
+            Ok(_) => {
+                self.emit_event(ShinkaiNodeManagerEvent::LoadingModelDone {
+                    model: model.to_string(),
+                });
+                Ok(())
+            }
+            Err(e) => {
+                error!("failed to load model {}: {}", model, e);
+                self.emit_event(ShinkaiNodeManagerEvent::LoadingModelError {
+                    model: model.to_string(),
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn unload_ollama_model(&self, model: &str) -> Result<(), String> {
+        if !self.ollama_process.is_running().await {
+            return Ok(());
+        }
+        let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+        let result = ollama_api.unload_model(model).await;
+        self.emit_unload_event(model, &result);
+        result
+    }
+
+    /// Frees the memory of every loaded model without stopping ollama, returns the unloaded models
+    pub async fn unload_all_ollama_models(&self) -> Result<Vec<String>, String> {
+        if !self.ollama_process.is_running().await {
+            return Ok(vec![]);
+        }
+        let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+        ollama_model_memory::unload_all_models(&ollama_api, |model, result| {
+            self.emit_unload_event(model, result)
+        })
+        .await
+    }
+
+    fn emit_unload_event(&self, model: &str, result: &Result<(), String>) {
+        let _ = self
+            .event_broadcaster
+            .send(Self::unload_event(model, result));
+    }
+
+    fn unload_event(model: &str, result: &Result<(), String>) -> ShinkaiNodeManagerEvent {
+        match result {
+            Ok(_) => ShinkaiNodeManagerEvent::UnloadingModelDone {
+                model: model.to_string(),
+            },
+            Err(e) => ShinkaiNodeManagerEvent::UnloadingModelError {
+                model: model.to_string(),
+                error: e.clone(),
+            },
+        }
+    }
+
+    /// Benchmarks `model` on this machine and stores the result with the hardware summary in the
+    /// benchmark history, `embedding_model` defaults to the node default embedding model
+    pub async fn benchmark_ollama_model(
+        &self,
+        model: &str,
+        embedding_model: Option<String>,
+    ) -> Result<BenchmarkRecord, String> {
+        if !self.ollama_process.is_running().await {
+            return Err("ollama must be running to benchmark a model".to_string());
+        }
+        self.check_memory_pressure()?;
+        if let Some(idle_unload_monitor) = &self.idle_unload_monitor {
+            idle_unload_monitor.touch();
+        }
+        let embedding_model = embedding_model
+            .or(self
+                .shinkai_node_process
+                .get_options()
+                .default_embedding_model)
+            .unwrap_or_default();
+        self.emit_event(ShinkaiNodeManagerEvent::BenchmarkingModelStart {
+            model: model.to_string(),
+        });
+
+        let result = async {
+            let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+            let benchmark = ollama_model_benchmark::run_benchmark(
+                &ollama_api,
+                model,
+                &embedding_model,
+                self.ollama_process.get_limits().num_threads,
+            )
+            .await?;
+            let history_path = self.app_data_dir.join(Self::BENCHMARK_HISTORY_FILE_NAME);
+            let node_storage_path = self.get_node_storage_path();
+            let profiles_path = self.app_data_dir.join(PROFILES_OVERRIDE_FILE_NAME);
+            tauri::async_runtime::spawn_blocking(move || {
+                let profiles = RequirementProfiles::load(Some(&profiles_path));
+                let record = BenchmarkRecord {
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                    hardware: hardware::hardware_get_summary(
+                        Some(&node_storage_path),
+                        profiles.default_profile(),
+                    ),
+                    benchmark,
+                };
+                ollama_model_benchmark::append_benchmark_history(&history_path, record.clone())?;
+                Ok(record)
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result)
+        }
+        .await;
+
+        match result {
+            Ok(record) => {
+                self.emit_event(ShinkaiNodeManagerEvent::BenchmarkingModelDone {
+                    model: model.to_string(),
+                });
+                Ok(record)
+            }
+            Err(e) => {
+                error!("failed to benchmark model {}: {}", model, e);
+                self.emit_event(ShinkaiNodeManagerEvent::BenchmarkingModelError {
+                    model: model.to_string(),
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    pub fn get_ollama_benchmark_history(&self) -> Result<Vec<BenchmarkRecord>, String> {
+        ollama_model_benchmark::read_benchmark_history(
+            &self.app_data_dir.join(Self::BENCHMARK_HISTORY_FILE_NAME),
+        )
+    }
+
+    /// Catalog models ranked for this machine and warnings for installed models that don't fit in memory
+    pub async fn get_ollama_model_recommendations(&self) -> Result<ModelRecommendations, String> {
+        let installed_models: Vec<InstalledModel> = if self.ollama_process.is_running().await {
+            let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+            ollama_api
+                .tags()
+                .await
+                .map_err(|e| e.to_string())?
+                .models
+                .iter()
+                .map(|model| InstalledModel {
+                    model: model.model.clone(),
+                    size_bytes: model.size,
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let resources =
+            tauri::async_runtime::spawn_blocking(hardware::hardware_get_machine_resources)
+                .await
+                .map_err(|e| e.to_string())?;
+        Ok(model_recommendations::recommend_models(
+            resources,
+            MODEL_CATALOG,
+            &installed_models,
+        ))
+    }
+
+    /// Minutes without requests before every model is unloaded, None when disabled
+    pub fn get_ollama_idle_unload_timeout(&self) -> Option<u64> {
+        self.idle_unload_monitor
+            .as_ref()
+            .map(|monitor| monitor.idle_timeout().as_secs() / 60)
+    }
+
+    /// Enables the idle timer with `minutes` greater than zero, None or zero disables it. The
+    /// timeout is saved so it survives restarts.
+    pub fn set_ollama_idle_unload_timeout(
+        &mut self,
+        minutes: Option<u64>,
+    ) -> Result<Option<u64>, String> {
+        let minutes = minutes.filter(|minutes| *minutes > 0);
+        self.save_memory_settings(OllamaMemorySettings {
+            idle_unload_timeout_minutes: minutes,
+            ..self.memory_settings()
+        })?;
+        self.start_idle_unload_monitor(minutes);
+        Ok(self.get_ollama_idle_unload_timeout())
+    }
+
+    fn start_idle_unload_monitor(&mut self, minutes: Option<u64>) {
+        // Dropping the previous monitor stops its polling task
+        self.idle_unload_monitor = None;
+        if let Some(minutes) = minutes {
+            let event_broadcaster = self.event_broadcaster.clone();
+            self.idle_unload_monitor = Some(IdleUnloadMonitor::start(
+                self.ollama_process.get_ollama_api_base_url(),
+                Duration::from_secs(minutes * 60),
+                move |model, result| {
+                    let _ = event_broadcaster.send(Self::unload_event(model, result));
+                },
+            ));
+        }
+    }
+
+    /// None when the memory pressure guard is disabled
+    pub fn get_ollama_memory_pressure_options(&self) -> Option<MemoryPressureOptions> {
+        self.memory_pressure_monitor
+            .as_ref()
+            .map(|monitor| monitor.options().clone())
+    }
+
+    /// Threshold suggested when the user enables the memory pressure guard
+    pub fn get_suggested_memory_pressure_options(&self) -> MemoryPressureOptions {
+        let mut system = System::new();
+        system.refresh_memory();
+        MemoryPressureOptions::for_total_memory(system.total_memory())
+    }
+
+    /// The guard is off until the user enables it, the options are saved so they survive
+    /// restarts
+    pub fn set_ollama_memory_pressure_options(
+        &mut self,
+        options: Option<MemoryPressureOptions>,
+    ) -> Result<Option<MemoryPressureOptions>, String> {
+        self.save_memory_settings(OllamaMemorySettings {
+            memory_pressure: options.clone(),
+            ..self.memory_settings()
+        })?;
+        self.start_memory_pressure_monitor(options);
+        Ok(self.get_ollama_memory_pressure_options())
+    }
+
+    fn start_memory_pressure_monitor(&mut self, options: Option<MemoryPressureOptions>) {
+        // Dropping the previous monitor stops its polling task
+        self.memory_pressure_monitor = None;
+        if let Some(options) = options {
+            let event_broadcaster = self.event_broadcaster.clone();
+            let unload_event_broadcaster = self.event_broadcaster.clone();
+            self.memory_pressure_monitor = Some(MemoryPressureMonitor::start(
+                self.ollama_process.get_ollama_api_base_url(),
+                options,
+                move |pressure| {
+                    let _ = event_broadcaster.send(ShinkaiNodeManagerEvent::MemoryPressure {
+                        pressure: pressure.clone(),
+                    });
+                },
+                move |model, result| {
+                    let _ = unload_event_broadcaster.send(Self::unload_event(model, result));
+                },
+            ));
+        }
+    }
+
+    fn memory_settings(&self) -> OllamaMemorySettings {
+        OllamaMemorySettings {
+            idle_unload_timeout_minutes: self.get_ollama_idle_unload_timeout(),
+            memory_pressure: self.get_ollama_memory_pressure_options(),
+        }
+    }
+
+    fn save_memory_settings(&self, settings: OllamaMemorySettings) -> Result<(), String> {
+        settings.save(
+            &self
+                .app_data_dir
+                .join(Self::OLLAMA_MEMORY_SETTINGS_FILE_NAME),
+        )
+    }
+
+    pub fn is_under_memory_pressure(&self) -> bool {
+        self.memory_pressure_monitor
+            .as_ref()
+            .is_some_and(|monitor| monitor.is_under_pressure())
+    }
+
+    fn check_memory_pressure(&self) -> Result<(), String> {
+        if self
+            .memory_pressure_monitor
+            .as_ref()
+            .is_some_and(|monitor| monitor.blocks_model_loads())
+        {
+            return Err(
+                "system memory is low, models can't be loaded until it recovers".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the registry is reachable with the current proxy settings, `registry` and `insecure`
+    /// override the configured values so they can be tested before saving them
+    pub async fn check_ollama_registry(
+        &self,
+        registry: Option<String>,
+        insecure: Option<bool>,
+        model: Option<String>,
+    ) -> Result<RegistryReachability, String> {
+        let ollama_options = self.ollama_process.get_options();
+        let registry = registry
+            .filter(|registry| !registry.trim().is_empty())
+            .unwrap_or_else(|| ollama_options.registry());
+        let registry_client = OllamaRegistryClient::new(
+            &registry,
+            insecure.unwrap_or(ollama_options.is_insecure_registry()),
+            &ollama_options.registry_proxy_options(),
+        )?;
+        Ok(registry_client.check(model.as_deref()).await)
+    }
+
+    pub async fn export_ollama_model(
+        &self,
+        model: &str,
+        archive_path: PathBuf,
+    ) -> Result<ModelArchiveManifest, String> {
+        let store = OllamaModelStore::new(self.ollama_process.get_models_path()?);
+        self.emit_event(ShinkaiNodeManagerEvent::ExportingModelStart {
+            model: model.to_string(),
+        });
+
+        let event_broadcaster = self.event_broadcaster.clone();
+        let model_name = model.to_string();
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            ollama_model_archive::export_model(&store, &model_name, &archive_path, |progress| {
+                let _ = event_broadcaster.send(ShinkaiNodeManagerEvent::ExportingModelProgress {
+                    model: model_name.clone(),
+                    progress,
+                });
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result);
+
+        match result {
+            Ok(archive_manifest) => {
+                self.emit_event(ShinkaiNodeManagerEvent::ExportingModelDone {
+                    model: model.to_string(),
+                });
+                Ok(archive_manifest)
+            }
+            Err(e) => {
+                error!("failed to export model {}: {}", model, e);
+                self.emit_event(ShinkaiNodeManagerEvent::ExportingModelError {
+                    model: model.to_string(),
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    /// Imports a model archive, `model` allows to register it under a different name than the exported one
+    pub async fn import_ollama_model(
+        &self,
+        archive_path: PathBuf,
+        model: Option<String>,
+    ) -> Result<ModelArchiveManifest, String> {
+        if !self.ollama_process.is_running().await {
+            return Err("ollama must be running to import a model".to_string());
+        }
+        let archive_manifest = ollama_model_archive::read_archive_manifest(&archive_path)?;
+        let model = model.unwrap_or_else(|| archive_manifest.model.clone());
+        self.emit_event(ShinkaiNodeManagerEvent::ImportingModelStart {
+            model: model.clone(),
+        });
+
+        let result = self
+            .import_ollama_model_archive(&archive_path, &model)
+            .await;
+        match result {
+            Ok(_) => {
+                self.emit_event(ShinkaiNodeManagerEvent::ImportingModelDone {
+                    model: model.clone(),
+                });
+                Ok(archive_manifest)
+            }
+            Err(e) => {
+                error!("failed to import model {}: {}", model, e);
+                self.emit_event(ShinkaiNodeManagerEvent::ImportingModelError {
+                    model: model.clone(),
+                    error: e.clone(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    async fn import_ollama_model_archive(
+        &self,
+        archive_path: &Path,
+        model: &str,
+    ) -> Result<(), String> {
+        let models_path = self.ollama_process.get_models_path()?;
+        // Staging in the models folder guarantees the extracted blobs fit in the same volume
+        let staging_path = models_path.join(format!(
+            "{}{}",
+            IMPORT_STAGING_PREFIX,
+            chrono::Utc::now().timestamp_millis()
+        ));
+
+        // Extracting and verifying is the first half of the progress, uploading is the second one
+        let event_broadcaster = self.event_broadcaster.clone();
+        let model_name = model.to_string();
+        let archive_path = archive_path.to_path_buf();
+        let staged = tauri::async_runtime::spawn_blocking(move || {
+            ollama_model_archive::stage_archive(&archive_path, &staging_path, |progress| {
+                let _ = event_broadcaster.send(ShinkaiNodeManagerEvent::ImportingModelProgress {
+                    model: model_name.clone(),
+                    progress: progress / 2,
+                });
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result)?;
+
+        let ollama_api = OllamaApiClient::new(self.ollama_process.get_ollama_api_base_url());
+        ollama_model_archive::import_staged_model(&ollama_api, &staged, model, |progress| {
+            self.emit_event(ShinkaiNodeManagerEvent::ImportingModelProgress {
+                model: model.to_string(),
+                progress: 50 + progress / 2,
+            });
+        })
+        .await
+    }
+
+    /// Options the requests the app sends to Ollama need so they honor the process limits
+    fn ollama_request_options(&self) -> Option<OllamaApiGenerateOptions> {
+        self.ollama_process
+            .get_limits()
+            .num_threads
+            .map(|num_thread| OllamaApiGenerateOptions {
+                num_thread: Some(num_thread),
+                ..Default::default()
+            })
+    }
+
+    pub fn get_sidecar_limits(&self) -> SidecarLimits {
+        SidecarLimits {
+            background_mode: self.background_mode,
+            shinkai_node: self.shinkai_node_process.get_limits(),
+            ollama: self.ollama_process.get_limits(),
+            shinkai_node_limits_error: self.shinkai_node_process.get_limits_error(),
+            ollama_limits_error: self.ollama_process.get_limits_error(),
+        }
+    }
+
+    /// Limits are kept for the next spawn even when applying them to the running processes fails
+    pub async fn set_sidecar_limits(
+        &mut self,
+        shinkai_node: ProcessLimits,
+        ollama: ProcessLimits,
+    ) -> Result<SidecarLimits, String> {
+        shinkai_node.validate(available_cpus())?;
+        ollama.validate(available_cpus())?;
+        self.background_mode = false;
+        self.apply_sidecar_limits(shinkai_node, ollama).await
+    }
+
+    /// Low priority on half of the cores for both sidecars, disabling it removes every limit
+    pub async fn set_background_mode(&mut self, enabled: bool) -> Result<SidecarLimits, String> {
+        let limits = if enabled {
+            ProcessLimits::background(available_cpus())
+        } else {
+            ProcessLimits::default()
+        };
+        self.background_mode = enabled;
+        self.apply_sidecar_limits(limits.clone(), limits).await
+    }
+
+    async fn apply_sidecar_limits(
+        &mut self,
+        shinkai_node: ProcessLimits,
+        ollama: ProcessLimits,
+    ) -> Result<SidecarLimits, String> {
+        let errors: Vec<String> = [
+            self.shinkai_node_process
+                .set_limits(shinkai_node)
+                .await
+                .map_err(|e| format!("shinkai-node: {}", e)),
+            self.ollama_process
+                .set_limits(ollama)
+                .await
+                .map_err(|e| format!("ollama: {}", e)),
+        ]
+        .into_iter()
+        .filter_map(|result| result.err())
+        .collect();
+        if !errors.is_empty() {
+            return Err(format!(
+                "limits will fully apply after restarting the processes, {}",
+                errors.join("; ")
+            ));
+        }
+        Ok(self.get_sidecar_limits())
+    }
+
+    /// Latest cpu, memory and disk samples of the node and Ollama process trees, oldest first
+    pub fn get_resource_usage_history(&self) -> Vec<ResourceUsageSample> {
+        self.resource_usage_sampler.history()
+    }
+
+    fn emit_event(&self, new_event: ShinkaiNodeManagerEvent) {
         let _ = self.event_broadcaster.send(new_event);
     }
 
@@ -228,6 +1807,85 @@ impl ShinkaiNodeManager {
         self.ollama_process.get_ollama_api_base_url()
     }
 
+    pub fn get_shinkai_node_api_url(&self) -> String {
+        self.shinkai_node_process.get_base_url()
+    }
+
+    /// Version reported by the node the last time it was spawned
+    pub fn get_shinkai_node_version(&self) -> Option<String> {
+        self.shinkai_node_process.version()
+    }
+
+    pub fn get_llm_models_path(&self) -> PathBuf {
+        self.llm_models_path.clone()
+    }
+
+    pub fn get_ollama_models_path(&self) -> Result<PathBuf, String> {
+        self.ollama_process.get_models_path()
+    }
+
+    /// Sidecars currently running
+    async fn running_sidecars(&self) -> Vec<SidecarPid> {
+        let mut sidecars = vec![];
+        for (name, pid) in [
+            ("shinkai-node", self.shinkai_node_process.pid()),
+            ("ollama", self.ollama_process.pid()),
+        ] {
+            sidecars.extend(pid.get().await.map(|pid| SidecarPid {
+                name: name.to_string(),
+                pid,
+            }));
+        }
+        sidecars
+    }
+
+    /// PIDs of the sidecars currently running
+    pub async fn get_sidecar_pids(&self) -> Vec<u32> {
+        self.running_sidecars()
+            .await
+            .into_iter()
+            .map(|sidecar| sidecar.pid)
+            .collect()
+    }
+
+    /// Sidecars the previous run of the app spawned, only these may be killed to free a port
+    pub fn get_previous_run_sidecars(&self) -> Vec<SidecarPid> {
+        self.previous_run_sidecars.clone()
+    }
+
+    /// Records the running sidecars so the next run can recognize the ones left over
+    async fn record_sidecar_pids(&self) {
+        let path = self.app_data_dir.join(Self::SIDECAR_PIDS_FILE_NAME);
+        if let Err(e) = process_utils::save_sidecar_pids(&path, &self.running_sidecars().await) {
+            log::warn!("{}", e);
+        }
+    }
+
+    /// Processes listening on the ports the node and ollama are configured to use
+    pub fn get_port_listeners(&self) -> Vec<PortListener> {
+        let options = self.shinkai_node_process.get_options();
+        let mut ports: Vec<(String, u16)> = [
+            ("shinkai-node api", options.node_api_port),
+            ("shinkai-node ws", options.node_ws_port),
+            ("shinkai-node https", options.node_https_port),
+            ("shinkai-node p2p", options.node_port),
+        ]
+        .into_iter()
+        .filter_map(|(role, port)| Some((role.to_string(), port?.parse().ok()?)))
+        .collect();
+        if let Some(port) = self
+            .ollama_process
+            .get_options()
+            .ollama_host
+            .rsplit(':')
+            .next()
+            .and_then(|port| port.parse().ok())
+        {
+            ports.push(("ollama".to_string(), port));
+        }
+        process_utils::get_port_listeners(ports)
+    }
+
     pub async fn get_ollama_version(app: AppHandle) -> Result<String> {
         OllamaProcessHandler::version(app).await
     }
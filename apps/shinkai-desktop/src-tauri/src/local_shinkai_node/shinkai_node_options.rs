@@ -48,6 +48,17 @@ impl ShinkaiNodeOptions {
         }
     }
 
+    /// Options kept in the secrets store, they are only read back to build the node env
+    pub fn secret_fields(&mut self) -> [(&'static str, &mut Option<String>); 2] {
+        [
+            ("initial_agent_api_keys", &mut self.initial_agent_api_keys),
+            (
+                "secret_desktop_installation_proof_key",
+                &mut self.secret_desktop_installation_proof_key,
+            ),
+        ]
+    }
+
     pub fn default_initial_model() -> String {
         "shinkai-backend:FREE_TEXT_INFERENCE".to_string()
     }
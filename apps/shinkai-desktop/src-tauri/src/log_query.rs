@@ -0,0 +1,628 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::log_redaction::redact;
+use crate::log_settings::LogFormat;
+
+/// Triple ideographic space written by the log formatter at the end of every text entry
+const ENTRY_SEPARATOR: &str = "　　　";
+const JSON_LINES_SEPARATOR: &str = "\n";
+/// Targets the sidecar process handlers log their process output with
+pub const SIDECAR_PROCESSES: [&str; 2] = ["shinkai-node", "ollama"];
+const APP_PROCESS: &str = "shinkai-desktop";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const DEFAULT_PAGE_SIZE: usize = 200;
+const MAX_PAGE_SIZE: usize = 2000;
+const READ_CHUNK_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    /// Process that wrote the entry, the app itself or one of its sidecars
+    #[serde(default)]
+    pub process: String,
+    pub message: String,
+}
+
+/// Process behind a log target
+pub fn source_process(target: &str) -> &str {
+    SIDECAR_PROCESSES
+        .iter()
+        .find(|process| **process == target)
+        .copied()
+        .unwrap_or(APP_PROCESS)
+}
+
+impl LogFormat {
+    fn separator(&self) -> &'static str {
+        match self {
+            LogFormat::Text => ENTRY_SEPARATOR,
+            LogFormat::JsonLines => JSON_LINES_SEPARATOR,
+        }
+    }
+}
+
+/// Parses a JSON line or a `[timestamp][level][target] message` entry, the message is redacted
+pub fn parse_entry(raw: &str) -> Option<LogEntry> {
+    static ENTRY_REGEX: OnceLock<Regex> = OnceLock::new();
+    let raw = raw.trim();
+    if raw.starts_with('{') {
+        let mut entry: LogEntry = serde_json::from_str(raw).ok()?;
+        if entry.process.is_empty() {
+            entry.process = source_process(&entry.target).to_string();
+        }
+        entry.message = redact(&entry.message).to_string();
+        return Some(entry);
+    }
+    let regex =
+        ENTRY_REGEX.get_or_init(|| Regex::new(r"\[(.*?)\]\[(.*?)\]\[(.*?)\] ((?s).*)").unwrap());
+    let captures = regex.captures(raw)?;
+    Some(LogEntry {
+        timestamp: captures[1].to_string(),
+        level: captures[2].to_string(),
+        target: captures[3].to_string(),
+        process: source_process(&captures[3]).to_string(),
+        message: redact(captures[4].trim()).to_string(),
+    })
+}
+
+/// Every entry of a log file, in either format
+pub fn read_entries(path: &Path) -> io::Result<Vec<LogEntry>> {
+    let format = LogFormat::detect(path)?.unwrap_or(LogFormat::Text);
+    Ok(fs::read_to_string(path)?
+        .split(format.separator())
+        .filter_map(parse_entry)
+        .collect())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogFilter {
+    /// Levels to keep (eg: `ERROR`, `WARN`), all of them when None
+    pub levels: Option<Vec<String>>,
+    /// Target prefixes to keep (eg: `shinkai-node`, `ollama`)
+    pub targets: Option<Vec<String>>,
+    /// RFC 3339 or `YYYY-MM-DD HH:MM:SS` local time, inclusive
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Case insensitive text searched in messages
+    pub text: Option<String>,
+    /// Treats `text` as a regular expression
+    #[serde(default)]
+    pub regex: bool,
+}
+
+pub struct LogMatcher {
+    levels: Option<Vec<String>>,
+    targets: Option<Vec<String>>,
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    text: Option<Regex>,
+}
+
+fn parse_time(value: &str) -> Result<NaiveDateTime, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Local).naive_local())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT))
+        .map_err(|_| format!("invalid time {}", value))
+}
+
+impl LogFilter {
+    pub fn matcher(&self) -> Result<LogMatcher, String> {
+        let text = match self.text.as_deref().filter(|text| !text.is_empty()) {
+            Some(text) => {
+                let pattern = if self.regex {
+                    text.to_string()
+                } else {
+                    regex::escape(text)
+                };
+                Some(
+                    RegexBuilder::new(&pattern)
+                        .case_insensitive(true)
+                        .size_limit(1024 * 1024)
+                        .build()
+                        .map_err(|e| format!("invalid search expression: {}", e))?,
+                )
+            }
+            None => None,
+        };
+        Ok(LogMatcher {
+            levels: self
+                .levels
+                .as_ref()
+                .map(|levels| levels.iter().map(|level| level.to_uppercase()).collect()),
+            targets: self
+                .targets
+                .as_ref()
+                .map(|targets| targets.iter().map(|target| target.to_lowercase()).collect()),
+            since: self.since.as_deref().map(parse_time).transpose()?,
+            until: self.until.as_deref().map(parse_time).transpose()?,
+            text,
+        })
+    }
+}
+
+impl LogMatcher {
+    /// Text entries have local times, JSON lines have RFC 3339 ones
+    fn time(entry: &LogEntry) -> Option<NaiveDateTime> {
+        parse_time(&entry.timestamp).ok()
+    }
+
+    /// Entries are written in order, nothing older than `entry` can match either
+    fn is_before_range(&self, entry: &LogEntry) -> bool {
+        match (self.since, Self::time(entry)) {
+            (Some(since), Some(time)) => time < since,
+            _ => false,
+        }
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(levels) = &self.levels {
+            if !levels
+                .iter()
+                .any(|level| entry.level.eq_ignore_ascii_case(level))
+            {
+                return false;
+            }
+        }
+        if let Some(targets) = &self.targets {
+            let target = entry.target.to_lowercase();
+            if !targets.iter().any(|prefix| target.starts_with(prefix)) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Some(time) = Self::time(entry) else {
+                return false;
+            };
+            if self.since.is_some_and(|since| time < since)
+                || self.until.is_some_and(|until| time > until)
+            {
+                return false;
+            }
+        }
+        self.text
+            .as_ref()
+            .is_none_or(|text| text.is_match(&entry.message))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogQuery {
+    #[serde(flatten)]
+    pub filter: LogFilter,
+    /// `next_cursor` of the previous page, None starts from the newest entry
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogPage {
+    /// Oldest first
+    pub entries: Vec<LogEntry>,
+    /// Cursor to fetch older entries, None when there are no more
+    pub next_cursor: Option<String>,
+}
+
+/// Log files oldest first, files rotated by the log plugin are named `<stem>_<timestamp>.log`
+pub fn log_files(log_dir: &Path, file_stem: &str) -> Vec<PathBuf> {
+    let rotated_prefix = format!("{}_", file_stem);
+    let mut rotated: Vec<PathBuf> = fs::read_dir(log_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    name.starts_with(&rotated_prefix) && name.ends_with(".log")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Timestamps are zero padded so names sort chronologically
+    rotated.sort();
+    let current = log_dir.join(format!("{}.log", file_stem));
+    if current.exists() {
+        rotated.push(current);
+    }
+    rotated
+}
+
+fn find_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+/// Reads the entries of a log file from the end
+struct ReverseEntryReader {
+    file: File,
+    separator: &'static [u8],
+    /// File offset of the first byte in `buf`
+    pos: u64,
+    /// Unread bytes, they always end right after a separator
+    buf: Vec<u8>,
+}
+
+impl ReverseEntryReader {
+    /// Starts reading before `end`, or before the last complete entry when None
+    fn open(path: &Path, end: Option<u64>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let format = LogFormat::detect(path)?.unwrap_or(LogFormat::Text);
+        let mut reader = ReverseEntryReader {
+            file,
+            separator: format.separator().as_bytes(),
+            pos: end.unwrap_or(len).min(len),
+            buf: Vec::new(),
+        };
+        if end.is_none() {
+            reader.drop_partial_entry()?;
+        }
+        Ok(reader)
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let from = self.pos.saturating_sub(READ_CHUNK_BYTES);
+        let mut chunk = vec![0u8; (self.pos - from) as usize];
+        self.file.seek(SeekFrom::Start(from))?;
+        self.file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&self.buf);
+        self.buf = chunk;
+        self.pos = from;
+        Ok(())
+    }
+
+    /// The entry being written when the file is read has no separator yet
+    fn drop_partial_entry(&mut self) -> io::Result<()> {
+        let separator = self.separator;
+        loop {
+            if let Some(index) = find_last(&self.buf, separator) {
+                self.buf.truncate(index + separator.len());
+                return Ok(());
+            }
+            if self.pos == 0 {
+                self.buf.clear();
+                return Ok(());
+            }
+            self.read_chunk()?;
+        }
+    }
+
+    /// Previous entry and the offset it starts at
+    fn prev(&mut self) -> io::Result<Option<(u64, String)>> {
+        let separator = self.separator;
+        loop {
+            let content_end = self.buf.len().saturating_sub(separator.len());
+            if let Some(index) = find_last(&self.buf[..content_end], separator) {
+                let start = index + separator.len();
+                let raw = String::from_utf8_lossy(&self.buf[start..content_end]).to_string();
+                self.buf.truncate(start);
+                return Ok(Some((self.pos + start as u64, raw)));
+            }
+            if self.pos == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                let raw = String::from_utf8_lossy(&self.buf[..content_end]).to_string();
+                self.buf.clear();
+                return Ok(Some((0, raw)));
+            }
+            self.read_chunk()?;
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Cursors are `<file name>:<offset>`, only files listed in `files` are accepted
+fn parse_cursor(cursor: &str, files: &[PathBuf]) -> Result<(usize, u64), String> {
+    let invalid = || "invalid log cursor".to_string();
+    let (name, offset) = cursor.rsplit_once(':').ok_or_else(invalid)?;
+    let offset = offset.parse::<u64>().map_err(|_| invalid())?;
+    let index = files
+        .iter()
+        .position(|path| file_name(path) == name)
+        .ok_or_else(|| "log cursor points to a file that no longer exists".to_string())?;
+    Ok((index, offset))
+}
+
+/// Newest entries matching `query`, reading the log files from the end
+pub fn query_logs(log_dir: &Path, file_stem: &str, query: &LogQuery) -> Result<LogPage, String> {
+    let matcher = query.filter.matcher()?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let files = log_files(log_dir, file_stem);
+    let (mut file_index, mut end) = match query.cursor.as_deref() {
+        Some(cursor) => {
+            let (index, offset) = parse_cursor(cursor, &files)?;
+            (index, Some(offset))
+        }
+        None if files.is_empty() => {
+            return Ok(LogPage {
+                entries: vec![],
+                next_cursor: None,
+            })
+        }
+        None => (files.len() - 1, None),
+    };
+
+    let mut entries = Vec::new();
+    let mut next_cursor = None;
+    'files: loop {
+        let path = &files[file_index];
+        let mut reader = match ReverseEntryReader::open(path, end) {
+            Ok(reader) => Some(reader),
+            // Rotated away while paging
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+        };
+        while let Some(reader) = reader.as_mut() {
+            let Some((offset, raw)) = reader
+                .prev()
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?
+            else {
+                break;
+            };
+            let Some(entry) = parse_entry(&raw) else {
+                continue;
+            };
+            if matcher.is_before_range(&entry) {
+                break 'files;
+            }
+            if matcher.matches(&entry) {
+                entries.push(entry);
+                if entries.len() == limit {
+                    next_cursor = Some(format!("{}:{}", file_name(path), offset));
+                    break 'files;
+                }
+            }
+        }
+        if file_index == 0 {
+            break;
+        }
+        file_index -= 1;
+        end = None;
+    }
+    entries.reverse();
+    Ok(LogPage {
+        entries,
+        next_cursor,
+    })
+}
+
+/// Follows the current log file returning entries as they are appended
+pub struct LogTail {
+    path: PathBuf,
+    offset: u64,
+    /// Detected once the file has content
+    format: Option<LogFormat>,
+}
+
+impl LogTail {
+    /// Starts after the last complete entry of `path`
+    pub fn new(path: PathBuf) -> Self {
+        let offset = ReverseEntryReader::open(&path, None)
+            .map(|reader| reader.pos + reader.buf.len() as u64)
+            .unwrap_or(0);
+        let format = LogFormat::detect(&path).ok().flatten();
+        LogTail {
+            path,
+            offset,
+            format,
+        }
+    }
+
+    /// Entries written since the previous poll
+    pub fn poll(&mut self) -> io::Result<Vec<LogEntry>> {
+        let len = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        // A shorter file was rotated and recreated
+        if len < self.offset {
+            self.offset = 0;
+            self.format = None;
+        }
+        if len == self.offset {
+            return Ok(vec![]);
+        }
+        let format = match self.format {
+            Some(format) => format,
+            None => match LogFormat::detect(&self.path)? {
+                Some(format) => *self.format.insert(format),
+                None => return Ok(vec![]),
+            },
+        };
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut appended = Vec::with_capacity((len - self.offset) as usize);
+        file.take(len - self.offset).read_to_end(&mut appended)?;
+        let separator = format.separator().as_bytes();
+        let Some(index) = find_last(&appended, separator) else {
+            return Ok(vec![]);
+        };
+        let complete = &appended[..index + separator.len()];
+        self.offset += complete.len() as u64;
+        Ok(String::from_utf8_lossy(complete)
+            .split(format.separator())
+            .filter_map(parse_entry)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(second: u32, level: &str, target: &str, message: &str) -> String {
+        format!(
+            "[2024-06-01 10:00:{:02}][{}][{}] {}{}\n",
+            second, level, target, message, ENTRY_SEPARATOR
+        )
+    }
+
+    fn write_logs() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path();
+        let rotated: String = (0..20)
+            .map(|i| entry(i, "INFO", "shinkai-node", &format!("rotated {}", i)))
+            .collect();
+        fs::write(log_dir.join("app_2024-06-01_09-59-59.log"), rotated).unwrap();
+        let current: String = (20..40)
+            .map(|i| {
+                let level = if i % 10 == 0 { "ERROR" } else { "INFO" };
+                entry(i, level, "ollama", &format!("current {}\nsecond line", i))
+            })
+            .collect();
+        fs::write(
+            log_dir.join("app.log"),
+            current + "[2024-06-01 10:00:40][INFO][ollama] partial",
+        )
+        .unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_query_pages_back_through_rotated_files() {
+        let temp_dir = write_logs();
+        let log_dir = temp_dir.path();
+        let mut query = LogQuery {
+            limit: Some(15),
+            ..Default::default()
+        };
+        let mut messages = Vec::new();
+        loop {
+            let page = query_logs(log_dir, "app", &query).unwrap();
+            messages.splice(0..0, page.entries.into_iter().map(|e| e.message));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(messages.len(), 40);
+        assert_eq!(messages[0], "rotated 0");
+        assert_eq!(messages[39], "current 39\nsecond line");
+    }
+
+    #[test]
+    fn test_query_filters_entries() {
+        let temp_dir = write_logs();
+        let log_dir = temp_dir.path();
+        let query = |filter: LogFilter| {
+            query_logs(
+                log_dir,
+                "app",
+                &LogQuery {
+                    filter,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect::<Vec<String>>()
+        };
+        let errors = query(LogFilter {
+            levels: Some(vec!["error".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(
+            errors,
+            vec!["current 20\nsecond line", "current 30\nsecond line"]
+        );
+        let rotated = query(LogFilter {
+            targets: Some(vec!["shinkai".to_string()]),
+            since: Some("2024-06-01 10:00:18".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(rotated, vec!["rotated 18", "rotated 19"]);
+        let searched = query(LogFilter {
+            text: Some(r"^(rotated|current) 1[05]$".to_string()),
+            regex: true,
+            ..Default::default()
+        });
+        assert_eq!(searched, vec!["rotated 10", "rotated 15"]);
+    }
+
+    #[test]
+    fn test_query_reads_json_lines_after_text() {
+        let temp_dir = write_logs();
+        let log_dir = temp_dir.path();
+        let json_lines: String = (0..3)
+            .map(|i| {
+                format!(
+                    "{}\n",
+                    serde_json::json!({
+                        "timestamp": format!("2024-06-01T10:01:0{}.000+00:00", i),
+                        "level": "WARN",
+                        "target": "shinkai-node",
+                        "process": "shinkai-node",
+                        "message": format!("json {}\nwith token=abcdef123", i),
+                    })
+                )
+            })
+            .collect();
+        fs::rename(
+            log_dir.join("app.log"),
+            log_dir.join("app_2024-06-01_10-00-59.log"),
+        )
+        .unwrap();
+        fs::write(log_dir.join("app.log"), json_lines + "{\"timestamp\":").unwrap();
+
+        let page = query_logs(
+            log_dir,
+            "app",
+            &LogQuery {
+                limit: Some(4),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let messages: Vec<&str> = page.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "current 39\nsecond line",
+                "json 0\nwith token=[REDACTED]",
+                "json 1\nwith token=[REDACTED]",
+                "json 2\nwith token=[REDACTED]"
+            ]
+        );
+        assert_eq!(page.entries[0].process, "ollama");
+        assert_eq!(read_entries(&log_dir.join("app.log")).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_tail_returns_appended_entries() {
+        let temp_dir = write_logs();
+        let log_dir = temp_dir.path();
+        let path = log_dir.join("app.log");
+        let mut tail = LogTail::new(path.clone());
+        assert!(tail.poll().unwrap().is_empty());
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        io::Write::write_all(&mut file, format!("{}\n", ENTRY_SEPARATOR).as_bytes()).unwrap();
+        let appended = tail.poll().unwrap();
+        assert_eq!(appended.len(), 1);
+        assert_eq!(appended[0].message, "partial");
+        assert!(tail.poll().unwrap().is_empty());
+    }
+}
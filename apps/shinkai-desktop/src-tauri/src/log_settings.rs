@@ -0,0 +1,243 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::log_query::{log_files, source_process};
+
+pub const LOG_SETTINGS_FILE_NAME: &str = "log-settings.json";
+/// Same date format the log plugin uses to name the files it rotates
+const ROTATED_FILE_DATE_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+const MIN_MAX_FILE_SIZE: u64 = 64 * 1024;
+const MAX_MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+const MAX_ROTATION_COUNT: u32 = 100;
+const MAX_RETENTION_DAYS: u32 = 3650;
+/// Files are rotated while the app runs, they are pruned again every hour
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `[timestamp][level][target] message` records ended by a triple ideographic space
+    Text,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl LogFormat {
+    /// Format of an existing log file, None while it's empty
+    pub fn detect(path: &Path) -> io::Result<Option<LogFormat>> {
+        let mut file = File::open(path)?;
+        let mut head = [0u8; 64];
+        let read = file.read(&mut head)?;
+        Ok(head[..read]
+            .iter()
+            .find(|byte| !byte.is_ascii_whitespace())
+            .map(|byte| match byte {
+                b'{' => LogFormat::JsonLines,
+                _ => LogFormat::Text,
+            }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LogSettings {
+    pub format: LogFormat,
+    /// Bytes written to a log file before it's rotated
+    pub max_file_size: u64,
+    /// Rotated files kept next to the current one
+    pub rotation_count: u32,
+    /// Rotated files older than this are removed, 0 keeps them until `rotation_count` is reached
+    pub retention_days: u32,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            format: LogFormat::Text,
+            max_file_size: 10 * 1024 * 1024,
+            rotation_count: 5,
+            retention_days: 14,
+        }
+    }
+}
+
+impl LogSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_MAX_FILE_SIZE..=MAX_MAX_FILE_SIZE).contains(&self.max_file_size) {
+            return Err(format!(
+                "max file size must be between {} and {} bytes",
+                MIN_MAX_FILE_SIZE, MAX_MAX_FILE_SIZE
+            ));
+        }
+        if self.rotation_count > MAX_ROTATION_COUNT {
+            return Err(format!(
+                "rotation count can't be greater than {}",
+                MAX_ROTATION_COUNT
+            ));
+        }
+        if self.retention_days > MAX_RETENTION_DAYS {
+            return Err(format!(
+                "retention can't be longer than {} days",
+                MAX_RETENTION_DAYS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Settings saved in `path`, defaults when there aren't any or they are invalid
+pub fn load_settings(path: &Path) -> LogSettings {
+    let Ok(content) = fs::read_to_string(path) else {
+        return LogSettings::default();
+    };
+    // The logger isn't set up yet when settings are loaded at startup
+    match serde_json::from_str::<LogSettings>(&content) {
+        Ok(settings) if settings.validate().is_ok() => settings,
+        _ => {
+            eprintln!("ignoring invalid log settings {}", path.display());
+            LogSettings::default()
+        }
+    }
+}
+
+pub fn save_settings(path: &Path, settings: &LogSettings) -> Result<(), String> {
+    settings.validate()?;
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("failed to serialize log settings: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("failed to save log settings: {}", e))
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    timestamp: String,
+    level: &'a str,
+    target: &'a str,
+    process: &'a str,
+    message: &'a str,
+}
+
+/// A record of the JSON lines format, `message` must be already redacted
+pub fn json_record(record: &log::Record, message: &str) -> String {
+    let level = record.level().to_string();
+    serde_json::to_string(&JsonRecord {
+        timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        level: &level,
+        target: record.target(),
+        process: source_process(record.target()),
+        message,
+    })
+    .unwrap_or_default()
+}
+
+/// Removes rotated files beyond `rotation_count` or older than `retention_days`, oldest first
+pub fn prune_rotated_logs(
+    log_dir: &Path,
+    file_stem: &str,
+    settings: &LogSettings,
+) -> Result<u32, String> {
+    let current = log_dir.join(format!("{}.log", file_stem));
+    let rotated: Vec<_> = log_files(log_dir, file_stem)
+        .into_iter()
+        .filter(|path| *path != current)
+        .collect();
+    let excess = rotated
+        .len()
+        .saturating_sub(settings.rotation_count as usize);
+    let expires_at = Utc::now() - chrono::Duration::days(settings.retention_days as i64);
+    let mut removed = 0;
+    for (index, path) in rotated.iter().enumerate() {
+        let expired = settings.retention_days > 0
+            && fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| DateTime::<Utc>::from(modified) < expires_at)
+                .unwrap_or(false);
+        if index < excess || expired {
+            fs::remove_file(path)
+                .map_err(|e| format!("failed to remove {}: {}", path.display(), e))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Rotates the current file when it was written in another format so every file holds a
+/// single one, then prunes rotated files. Runs before the logger is set up.
+pub fn prepare_log_files(
+    log_dir: &Path,
+    file_stem: &str,
+    settings: &LogSettings,
+) -> Result<(), String> {
+    let current = log_dir.join(format!("{}.log", file_stem));
+    if let Ok(Some(format)) = LogFormat::detect(&current) {
+        if format != settings.format {
+            let rotated = log_dir.join(format!(
+                "{}_{}.log",
+                file_stem,
+                Local::now().format(ROTATED_FILE_DATE_FORMAT)
+            ));
+            fs::rename(&current, &rotated)
+                .map_err(|e| format!("failed to rotate {}: {}", current.display(), e))?;
+        }
+    }
+    prune_rotated_logs(log_dir, file_stem, settings).map(|_| ())
+}
+
+/// Prunes rotated files with the latest saved settings for as long as the app runs
+pub async fn run_log_pruner(log_dir: PathBuf, file_stem: String, settings_path: PathBuf) {
+    loop {
+        tokio::time::sleep(PRUNE_INTERVAL).await;
+        let settings = load_settings(&settings_path);
+        match prune_rotated_logs(&log_dir, &file_stem, &settings) {
+            Ok(0) => {}
+            Ok(removed) => log::info!("removed {} rotated log files", removed),
+            Err(e) => log::warn!("failed to prune rotated log files: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_rotates_on_format_change_and_prunes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_dir = temp_dir.path();
+        for day in 1..=4 {
+            fs::write(
+                log_dir.join(format!("app_2024-06-0{}_10-00-00.log", day)),
+                "[2024-06-01 10:00:00][INFO][app] old　　　\n",
+            )
+            .unwrap();
+        }
+        fs::write(
+            log_dir.join("app.log"),
+            "[2024-06-05 10:00:00][INFO][app] text　　　\n",
+        )
+        .unwrap();
+        let settings = LogSettings {
+            format: LogFormat::JsonLines,
+            rotation_count: 2,
+            retention_days: 0,
+            ..Default::default()
+        };
+
+        prepare_log_files(log_dir, "app", &settings).unwrap();
+        let mut names: Vec<String> = fs::read_dir(log_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        // The text file was rotated, pushing out all but the newest rotated file
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0], "app_2024-06-04_10-00-00.log");
+        assert!(!log_dir.join("app.log").exists());
+        assert_eq!(
+            LogFormat::detect(&log_dir.join(&names[1])).unwrap(),
+            Some(LogFormat::Text)
+        );
+    }
+}
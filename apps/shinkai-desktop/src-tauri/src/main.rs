@@ -5,14 +5,26 @@ use std::sync::Arc;
 
 use crate::commands::fetch::{get_request, post_request};
 use crate::commands::galxe::galxe_generate_proof;
-use crate::commands::hardware::hardware_get_summary;
+use crate::commands::hardware::{
+    hardware_evaluate_requirements, hardware_get_requirement_profiles, hardware_get_summary,
+};
 use crate::commands::shinkai_node_manager_commands::{
     shinkai_node_get_default_model, shinkai_node_get_ollama_api_url,
     shinkai_node_get_ollama_version, shinkai_node_get_options, shinkai_node_is_running,
     shinkai_node_kill, shinkai_node_remove_storage, shinkai_node_set_default_options,
     shinkai_node_set_options, shinkai_node_spawn, show_shinkai_node_manager_window,
     shinkai_node_open_storage_location, shinkai_node_open_storage_location_with_path,
-    shinkai_node_open_chat_folder,
+    shinkai_node_open_chat_folder, shinkai_node_get_resource_usage_history,
+    shinkai_node_get_sidecar_limits, shinkai_node_set_sidecar_limits,
+    shinkai_node_set_background_mode, shinkai_node_backup_storage,
+    shinkai_node_verify_storage_backup, shinkai_node_restore_storage,
+    shinkai_node_get_backup_schedule, shinkai_node_set_backup_schedule,
+    shinkai_node_list_storage_backups, shinkai_node_reset_storage,
+    shinkai_node_get_last_storage_reset, shinkai_node_undo_storage_reset,
+    shinkai_node_get_storage_usage, shinkai_node_relocate_storage, shinkai_node_export_identity,
+    shinkai_node_inspect_identity_export, shinkai_node_import_identity,
+    shinkai_node_get_secrets_status, shinkai_node_unlock_secrets,
+    shinkai_node_set_secrets_protection,
 };
 use crate::commands::mcp_clients_install::{
     check_claude_installed,
@@ -26,24 +38,55 @@ use crate::commands::mcp_clients_install::{
     register_command_server_in_cursor,
     register_sse_server_in_cursor,
 };
-use crate::commands::logs::{download_logs, retrieve_logs};
+use crate::commands::diagnostics::export_diagnostics;
+use crate::commands::doctor::doctor;
+use crate::commands::logs::{
+    download_logs, get_log_location, get_log_settings, query_logs, retrieve_logs,
+    set_log_settings, subscribe_logs, unsubscribe_logs,
+};
+use crate::commands::ollama_commands::{
+    ollama_benchmark_model, ollama_check_registry, ollama_cleanup_partial_downloads,
+    ollama_export_model, ollama_gc_models, ollama_get_benchmark_history,
+    ollama_get_idle_unload_timeout, ollama_get_memory_pressure_options,
+    ollama_get_model_recommendations, ollama_get_models_disk_usage, ollama_get_options,
+    ollama_get_suggested_memory_pressure_options, ollama_import_model,
+    ollama_is_under_memory_pressure, ollama_list_loaded_models, ollama_preload_model,
+    ollama_pull_model, ollama_pull_preflight, ollama_set_idle_unload_timeout,
+    ollama_set_memory_pressure_options, ollama_set_options, ollama_unload_all_models,
+    ollama_unload_model,
+};
 use crate::commands::spotlight_commands::{hide_spotlight_window_app, show_spotlight_window_app, open_main_window_with_path_app};
 use deep_links::setup_deep_links;
 use global_shortcuts::global_shortcut_handler;
 use globals::SHINKAI_NODE_MANAGER_INSTANCE;
 use local_shinkai_node::shinkai_node_manager::ShinkaiNodeManager;
+use log_settings::{
+    json_record, load_settings, prepare_log_files, run_log_pruner, LogFormat, LogSettings,
+    LOG_SETTINGS_FILE_NAME,
+};
+use tauri::plugin::TauriPlugin;
 use tauri::{Emitter, WindowEvent};
-use tauri::{Manager, RunEvent};
+use tauri::{Manager, RunEvent, Wry};
+use tauri_plugin_log::RotationStrategy;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{RwLock};
 use tray::create_tray;
 use windows::{recreate_window, Window};
 mod commands;
 mod deep_links;
+mod diagnostics;
+mod disk_space;
+mod doctor;
 mod galxe;
 mod global_shortcuts;
 mod globals;
+mod gpu_memory;
 mod hardware;
+mod hardware_requirements;
 mod local_shinkai_node;
+mod log_query;
+mod log_redaction;
+mod log_settings;
 mod models;
 mod tray;
 mod windows;
@@ -54,6 +97,32 @@ struct Payload {
     cwd: String,
 }
 
+fn build_log_plugin(settings: &LogSettings) -> TauriPlugin<Wry> {
+    let format = settings.format;
+    tauri_plugin_log::Builder::new()
+        .max_file_size(settings.max_file_size as u128)
+        // Rotated files are pruned by count and age with the log settings
+        .rotation_strategy(RotationStrategy::KeepAll)
+        .format(move |out, message, record| {
+            // Secrets are masked before anything reaches the log file
+            let message = log_redaction::redact(&message.to_string()).to_string();
+            match format {
+                LogFormat::JsonLines => {
+                    out.finish(format_args!("{}", json_record(record, &message)))
+                }
+                // Ending with a triple ideographic space as separator so then we can group texts that belongs to the same log
+                LogFormat::Text => out.finish(format_args!(
+                    "[{}][{}][{}] {}　　　",
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    record.level(),
+                    record.target(),
+                    message
+                )),
+            }
+        })
+        .build()
+}
+
 fn main() {
     let _ = fix_path_env::fix();
     tauri::Builder::default()
@@ -61,20 +130,6 @@ fn main() {
             app.emit("single-instance", Payload { args: argv, cwd })
                 .unwrap();
         }))
-        .plugin(
-            tauri_plugin_log::Builder::new()
-                .format(|out, message, record| {
-                    // Ending with a triple ideographic space as separator so then we can group texts that belongs to the same log
-                    out.finish(format_args!(
-                        "[{}][{}][{}] {}　　　",
-                        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
-                        record.level(),
-                        record.target(),
-                        message
-                    ))
-                })
-                .build(),
-        )
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
@@ -114,19 +169,72 @@ fn main() {
             shinkai_node_spawn,
             shinkai_node_kill,
             shinkai_node_remove_storage,
+            shinkai_node_backup_storage,
+            shinkai_node_verify_storage_backup,
+            shinkai_node_restore_storage,
+            shinkai_node_get_backup_schedule,
+            shinkai_node_set_backup_schedule,
+            shinkai_node_list_storage_backups,
+            shinkai_node_reset_storage,
+            shinkai_node_get_last_storage_reset,
+            shinkai_node_undo_storage_reset,
+            shinkai_node_get_storage_usage,
+            shinkai_node_relocate_storage,
+            shinkai_node_export_identity,
+            shinkai_node_inspect_identity_export,
+            shinkai_node_import_identity,
+            shinkai_node_get_secrets_status,
+            shinkai_node_unlock_secrets,
+            shinkai_node_set_secrets_protection,
             shinkai_node_open_storage_location,
             shinkai_node_open_storage_location_with_path,
             shinkai_node_open_chat_folder,
+            shinkai_node_get_resource_usage_history,
+            shinkai_node_get_sidecar_limits,
+            shinkai_node_set_sidecar_limits,
+            shinkai_node_set_background_mode,
             shinkai_node_set_default_options,
             shinkai_node_get_ollama_api_url,
             shinkai_node_get_default_model,
             hardware_get_summary,
+            hardware_get_requirement_profiles,
+            hardware_evaluate_requirements,
             galxe_generate_proof,
             get_request,
             post_request,
             shinkai_node_get_ollama_version,
+            ollama_get_options,
+            ollama_set_options,
+            ollama_pull_preflight,
+            ollama_pull_model,
+            ollama_cleanup_partial_downloads,
+            ollama_check_registry,
+            ollama_export_model,
+            ollama_import_model,
+            ollama_get_models_disk_usage,
+            ollama_gc_models,
+            ollama_list_loaded_models,
+            ollama_preload_model,
+            ollama_unload_model,
+            ollama_unload_all_models,
+            ollama_get_idle_unload_timeout,
+            ollama_set_idle_unload_timeout,
+            ollama_get_memory_pressure_options,
+            ollama_get_suggested_memory_pressure_options,
+            ollama_set_memory_pressure_options,
+            ollama_is_under_memory_pressure,
+            ollama_benchmark_model,
+            ollama_get_benchmark_history,
+            ollama_get_model_recommendations,
             retrieve_logs,
+            query_logs,
+            subscribe_logs,
+            unsubscribe_logs,
+            get_log_settings,
+            set_log_settings,
             download_logs,
+            export_diagnostics,
+            doctor,
             check_claude_installed,
             is_server_registered_in_claude,
             register_server_in_claude,
@@ -139,10 +247,23 @@ fn main() {
             get_cursor_sse_config_help,
         ])
         .setup(|app| {
-            log::info!("starting app version: {}", env!("CARGO_PKG_VERSION"));
             let app_resource_dir = app.path().resource_dir()?;
             let app_data_dir = app.path().app_data_dir()?;
 
+            // Registered here because its settings live in the app data folder
+            let (log_dir, log_file_stem) = get_log_location(app.handle())?;
+            let log_settings = load_settings(&app_data_dir.join(LOG_SETTINGS_FILE_NAME));
+            if let Err(e) = prepare_log_files(&log_dir, &log_file_stem, &log_settings) {
+                eprintln!("failed to prepare log files: {}", e);
+            }
+            app.handle().plugin(build_log_plugin(&log_settings))?;
+            log::info!("starting app version: {}", env!("CARGO_PKG_VERSION"));
+            tauri::async_runtime::spawn(run_log_pruner(
+                log_dir,
+                log_file_stem,
+                app_data_dir.join(LOG_SETTINGS_FILE_NAME),
+            ));
+
             {
                 let _ = SHINKAI_NODE_MANAGER_INSTANCE.set(Arc::new(RwLock::new(
                     ShinkaiNodeManager::new(app.handle().clone(), app_resource_dir, app_data_dir),
@@ -178,7 +299,16 @@ fn main() {
                         SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
                     let mut receiver = shinkai_node_manager_guard.subscribe_to_events();
                     drop(shinkai_node_manager_guard);
-                    while let Ok(state_change) = receiver.recv().await {
+                    loop {
+                        let state_change = match receiver.recv().await {
+                            Ok(state_change) => state_change,
+                            // Periodic resource usage events can outpace the forwarding
+                            Err(RecvError::Lagged(skipped)) => {
+                                log::warn!("skipped {} node manager events", skipped);
+                                continue;
+                            }
+                            Err(RecvError::Closed) => break,
+                        };
                         app_handle
                             .emit("shinkai-node-state-change", state_change)
                             .unwrap_or_else(|e| {
@@ -187,6 +317,10 @@ fn main() {
                     }
                 }
             });
+
+            tauri::async_runtime::spawn(ShinkaiNodeManager::run_backup_scheduler(
+                SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().clone(),
+            ));
             Ok(())
         })
         .build(tauri::generate_context!())
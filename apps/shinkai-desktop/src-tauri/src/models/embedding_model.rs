@@ -3,14 +3,20 @@
 
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
+pub const MODEL_FILE_NAME: &str = "snowflake-arctic-embed-xs-f16.GGUF";
+
+pub fn get_model_path(llm_models_path: &Path) -> PathBuf {
+    llm_models_path.join(MODEL_FILE_NAME)
+}
+
 pub fn get_model_data(llm_models_path: &Path) -> &'static [u8] {
     static FULL_MODEL_DATA: OnceLock<Vec<u8>> = OnceLock::new();
 
     FULL_MODEL_DATA.get_or_init(|| {
-        let model_path = llm_models_path.join("snowflake-arctic-embed-xs-f16.GGUF");
+        let model_path = get_model_path(llm_models_path);
         
         let mut file = match File::open(&model_path) {
             Ok(file) => file,
@@ -1 +1,3 @@
-pub mod embedding_model; 
\ No newline at end of file
+pub mod embedding_model;
+pub mod model_catalog;
+pub mod model_recommendations;
@@ -0,0 +1,55 @@
+use serde::Serialize;
+
+const GB: u64 = 1000 * 1000 * 1000;
+const MB: u64 = 1000 * 1000;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CatalogModel {
+    pub model: &'static str,
+    pub family: &'static str,
+    pub parameters: &'static str,
+    pub parameters_billion: f32,
+    pub quantization: &'static str,
+    /// Size of the weights as reported by the ollama library
+    pub size_bytes: u64,
+}
+
+const fn catalog_model(
+    model: &'static str,
+    family: &'static str,
+    parameters: &'static str,
+    parameters_billion: f32,
+    size_bytes: u64,
+) -> CatalogModel {
+    CatalogModel {
+        model,
+        family,
+        parameters,
+        parameters_billion,
+        quantization: "Q4_K_M",
+        size_bytes,
+    }
+}
+
+/// Curated local models known to work well with Shinkai, every entry uses the default ollama tag quantization
+pub const MODEL_CATALOG: &[CatalogModel] = &[
+    catalog_model("qwen2.5:0.5b", "qwen2", "494M", 0.5, 398 * MB),
+    catalog_model("llama3.2:1b", "llama", "1.2B", 1.2, 1300 * MB),
+    catalog_model("deepseek-r1:1.5b", "qwen2", "1.8B", 1.8, 1100 * MB),
+    catalog_model("qwen2.5:1.5b", "qwen2", "1.5B", 1.5, 986 * MB),
+    catalog_model("gemma2:2b", "gemma2", "2.6B", 2.6, 1600 * MB),
+    catalog_model("llama3.2:3b", "llama", "3.2B", 3.2, 2 * GB),
+    catalog_model("phi3:3.8b", "phi3", "3.8B", 3.8, 2200 * MB),
+    catalog_model("mistral:7b", "llama", "7.2B", 7.2, 4100 * MB),
+    catalog_model("qwen2.5:7b", "qwen2", "7.6B", 7.6, 4700 * MB),
+    catalog_model("deepseek-r1:7b", "qwen2", "7.6B", 7.6, 4700 * MB),
+    catalog_model("llama3.1:8b", "llama", "8.0B", 8.0, 4900 * MB),
+    catalog_model("gemma2:9b", "gemma2", "9.2B", 9.2, 5400 * MB),
+    catalog_model("qwen2.5:14b", "qwen2", "14.8B", 14.8, 9 * GB),
+    catalog_model("phi4:14b", "phi3", "14.7B", 14.7, 9100 * MB),
+    catalog_model("deepseek-r1:14b", "qwen2", "14.8B", 14.8, 9 * GB),
+    catalog_model("gemma2:27b", "gemma2", "27.2B", 27.2, 16 * GB),
+    catalog_model("qwen2.5:32b", "qwen2", "32.8B", 32.8, 20 * GB),
+    catalog_model("deepseek-r1:32b", "qwen2", "32.8B", 32.8, 20 * GB),
+    catalog_model("llama3.3:70b", "llama", "70.6B", 70.6, 43 * GB),
+];
@@ -0,0 +1,257 @@
+use serde::Serialize;
+
+use super::model_catalog::CatalogModel;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+/// Runtime buffers and a few thousand tokens of KV cache on top of the weights
+const RUNTIME_OVERHEAD_BYTES: u64 = 512 * 1024 * 1024;
+/// Share of unified memory macOS lets the GPU use
+const UNIFIED_MEMORY_GPU_RATIO: f64 = 0.75;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MachineResources {
+    pub cpus: usize,
+    pub total_memory_bytes: u64,
+    pub available_memory_bytes: u64,
+    /// Dedicated GPU memory, 0 without a discrete GPU
+    pub gpu_memory_bytes: u64,
+    /// Apple silicon GPUs share the system memory
+    pub unified_memory: bool,
+}
+
+impl MachineResources {
+    /// Memory models can take while leaving room for the OS, the app and the node
+    pub fn model_memory_budget(&self) -> u64 {
+        let reserved = (2 * GIB).max(self.total_memory_bytes / 4);
+        self.total_memory_bytes.saturating_sub(reserved) + self.gpu_memory_bytes
+    }
+
+    fn gpu_memory_budget(&self) -> u64 {
+        if self.unified_memory {
+            (self.total_memory_bytes as f64 * UNIFIED_MEMORY_GPU_RATIO) as u64
+        } else {
+            self.gpu_memory_bytes
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpeedClass {
+    /// Fully offloaded to the GPU
+    Fast,
+    /// Partially offloaded or small enough for the CPU
+    Moderate,
+    Slow,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ModelRecommendation {
+    pub rank: usize,
+    pub model: String,
+    pub family: String,
+    pub parameters: String,
+    pub quantization: String,
+    pub download_bytes: u64,
+    pub estimated_memory_bytes: u64,
+    pub speed_class: SpeedClass,
+    pub installed: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct InstalledModel {
+    pub model: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct InstalledModelWarning {
+    pub model: String,
+    pub estimated_memory_bytes: u64,
+    pub memory_budget_bytes: u64,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ModelRecommendations {
+    pub resources: MachineResources,
+    pub memory_budget_bytes: u64,
+    /// Best ranked model, None when no catalog model fits the machine
+    pub recommended_model: Option<String>,
+    pub recommendations: Vec<ModelRecommendation>,
+    pub installed_model_warnings: Vec<InstalledModelWarning>,
+}
+
+pub fn estimate_memory_bytes(size_bytes: u64) -> u64 {
+    size_bytes + size_bytes / 5 + RUNTIME_OVERHEAD_BYTES
+}
+
+fn speed_class(
+    resources: &MachineResources,
+    model: &CatalogModel,
+    memory_bytes: u64,
+) -> SpeedClass {
+    let gpu_memory_budget = resources.gpu_memory_budget();
+    if gpu_memory_budget >= memory_bytes {
+        return SpeedClass::Fast;
+    }
+    if resources.gpu_memory_bytes > 0 && !resources.unified_memory {
+        return SpeedClass::Moderate;
+    }
+    // Cpu inference is bound by memory bandwidth, small models keep a usable pace
+    let small_model = model.size_bytes <= 1300 * 1000 * 1000
+        || (model.size_bytes <= 2500 * 1000 * 1000 && resources.cpus >= 8);
+    if small_model {
+        SpeedClass::Moderate
+    } else {
+        SpeedClass::Slow
+    }
+}
+
+/// Catalog models that fit the machine ranked by speed class first and size second, so the
+/// biggest model that still runs at the best available speed comes first
+pub fn recommend_models(
+    resources: MachineResources,
+    catalog: &[CatalogModel],
+    installed_models: &[InstalledModel],
+) -> ModelRecommendations {
+    let memory_budget_bytes = resources.model_memory_budget();
+    let is_installed = |model: &str| {
+        installed_models
+            .iter()
+            .any(|installed| installed.model == model)
+    };
+
+    let mut recommendations: Vec<ModelRecommendation> = catalog
+        .iter()
+        .filter_map(|model| {
+            let estimated_memory_bytes = estimate_memory_bytes(model.size_bytes);
+            if estimated_memory_bytes > memory_budget_bytes {
+                return None;
+            }
+            Some(ModelRecommendation {
+                rank: 0,
+                model: model.model.to_string(),
+                family: model.family.to_string(),
+                parameters: model.parameters.to_string(),
+                quantization: model.quantization.to_string(),
+                download_bytes: model.size_bytes,
+                estimated_memory_bytes,
+                speed_class: speed_class(&resources, model, estimated_memory_bytes),
+                installed: is_installed(model.model),
+            })
+        })
+        .collect();
+    recommendations.sort_by(|a, b| {
+        a.speed_class
+            .cmp(&b.speed_class)
+            .then(b.estimated_memory_bytes.cmp(&a.estimated_memory_bytes))
+    });
+    for (index, recommendation) in recommendations.iter_mut().enumerate() {
+        recommendation.rank = index + 1;
+    }
+
+    let installed_model_warnings = installed_models
+        .iter()
+        .filter_map(|installed| {
+            let estimated_memory_bytes = estimate_memory_bytes(installed.size_bytes);
+            if estimated_memory_bytes <= memory_budget_bytes {
+                return None;
+            }
+            Some(InstalledModelWarning {
+                model: installed.model.clone(),
+                estimated_memory_bytes,
+                memory_budget_bytes,
+                message: format!(
+                    "{} needs about {:.1} GiB of memory but only {:.1} GiB are available for models, it may not load or make the system swap",
+                    installed.model,
+                    estimated_memory_bytes as f64 / GIB as f64,
+                    memory_budget_bytes as f64 / GIB as f64
+                ),
+            })
+        })
+        .collect();
+
+    ModelRecommendations {
+        memory_budget_bytes,
+        recommended_model: recommendations.first().map(|r| r.model.clone()),
+        resources,
+        recommendations,
+        installed_model_warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::model_catalog::MODEL_CATALOG;
+
+    fn resources(
+        total_memory_gib: u64,
+        gpu_memory_gib: u64,
+        unified_memory: bool,
+    ) -> MachineResources {
+        MachineResources {
+            cpus: 8,
+            total_memory_bytes: total_memory_gib * GIB,
+            available_memory_bytes: total_memory_gib * GIB / 2,
+            gpu_memory_bytes: gpu_memory_gib * GIB,
+            unified_memory,
+        }
+    }
+
+    #[test]
+    fn test_small_machine_only_gets_small_models() {
+        let recommendations = recommend_models(resources(8, 0, false), MODEL_CATALOG, &[]);
+        assert!(!recommendations.recommendations.is_empty());
+        assert!(recommendations
+            .recommendations
+            .iter()
+            .all(|r| r.estimated_memory_bytes <= recommendations.memory_budget_bytes));
+        assert!(!recommendations
+            .recommendations
+            .iter()
+            .any(|r| r.model == "qwen2.5:14b"));
+        // Cpu only machines favour models that keep a usable pace
+        assert_eq!(
+            recommendations.recommendations[0].speed_class,
+            SpeedClass::Moderate
+        );
+    }
+
+    #[test]
+    fn test_discrete_gpu_ranks_fully_offloaded_models_first() {
+        let recommendations = recommend_models(resources(32, 12, false), MODEL_CATALOG, &[]);
+        let first = &recommendations.recommendations[0];
+        assert_eq!(first.speed_class, SpeedClass::Fast);
+        assert!(first.estimated_memory_bytes <= 12 * GIB);
+        assert_eq!(
+            recommendations.recommended_model.as_deref(),
+            Some(first.model.as_str())
+        );
+    }
+
+    #[test]
+    fn test_warns_about_installed_models_too_large() {
+        let installed_models = vec![
+            InstalledModel {
+                model: "llama3.2:3b".to_string(),
+                size_bytes: 2 * 1000 * 1000 * 1000,
+            },
+            InstalledModel {
+                model: "llama3.3:70b".to_string(),
+                size_bytes: 43 * 1000 * 1000 * 1000,
+            },
+        ];
+        let recommendations =
+            recommend_models(resources(16, 0, true), MODEL_CATALOG, &installed_models);
+        assert_eq!(recommendations.installed_model_warnings.len(), 1);
+        assert_eq!(
+            recommendations.installed_model_warnings[0].model,
+            "llama3.3:70b"
+        );
+        assert!(recommendations
+            .recommendations
+            .iter()
+            .any(|r| r.model == "llama3.2:3b" && r.installed));
+    }
+}
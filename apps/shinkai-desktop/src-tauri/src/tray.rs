@@ -1,5 +1,5 @@
 use tauri::{
-    menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder},
+    menu::{CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder, SubmenuBuilder},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     Manager,
 };
@@ -18,8 +18,14 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
 
     let open_shinkai_node_manager_window_menu_item =
         MenuItemBuilder::with_id("open_shinkai_node_manager_window", "Open").build(app)?;
+    let free_model_memory_menu_item =
+        MenuItemBuilder::with_id("free_model_memory", "Free Model Memory").build(app)?;
+    let background_mode_menu_item =
+        CheckMenuItemBuilder::with_id("background_mode", "Background Mode").build(app)?;
     let shinkai_node_manager_menu_item = SubmenuBuilder::new(app, "Shinkai Node Manager")
         .item(&open_shinkai_node_manager_window_menu_item)
+        .item(&free_model_memory_menu_item)
+        .item(&background_mode_menu_item)
         .build()?;
 
     let menu = MenuBuilder::new(app)
@@ -57,6 +63,28 @@ pub fn create_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
             "open_shinkai_node_manager_window" => {
                 recreate_window(tray.app_handle().clone(), Window::ShinkaiNodeManager, true);
             }
+            "free_model_memory" => {
+                tauri::async_runtime::spawn(async move {
+                    let shinkai_node_manager_guard =
+                        SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().read().await;
+                    match shinkai_node_manager_guard.unload_all_ollama_models().await {
+                        Ok(models) => log::info!("freed memory of models [{}]", models.join(", ")),
+                        Err(e) => log::error!("failed to free model memory: {}", e),
+                    }
+                });
+            }
+            "background_mode" => {
+                // The item is already toggled when the event arrives
+                let enabled = background_mode_menu_item.is_checked().unwrap_or_default();
+                tauri::async_runtime::spawn(async move {
+                    let mut shinkai_node_manager_guard =
+                        SHINKAI_NODE_MANAGER_INSTANCE.get().unwrap().write().await;
+                    match shinkai_node_manager_guard.set_background_mode(enabled).await {
+                        Ok(_) => log::info!("background mode set to {}", enabled),
+                        Err(e) => log::warn!("failed to set background mode: {}", e),
+                    }
+                });
+            }
             "shinkai_spotlight" => {
                 recreate_window(tray.app_handle().clone(), Window::Spotlight, true);
             }
@@ -0,0 +1,72 @@
+import {
+  useMutation,
+  type UseMutationOptions,
+  type UseMutationResult,
+} from '@tanstack/react-query';
+import { invoke } from '@tauri-apps/api/core';
+
+import { save } from '@tauri-apps/plugin-dialog';
+
+import { info } from '@tauri-apps/plugin-log';
+
+export type DiagnosticsItemStatus = 'Collected' | 'Failed';
+
+export type DiagnosticsItem = {
+  path: string | null;
+  name: string;
+  description: string;
+  status: DiagnosticsItemStatus;
+  size: number;
+  error: string | null;
+};
+
+export type DiagnosticsManifest = {
+  created_at: string;
+  app_version: string;
+  os: string;
+  arch: string;
+  items: DiagnosticsItem[];
+};
+
+export const useExportDiagnosticsMutation = (
+  options?: UseMutationOptions<
+    { savePath: string; manifest: DiagnosticsManifest },
+    Error,
+    void
+  >,
+): UseMutationResult<
+  { savePath: string; manifest: DiagnosticsManifest },
+  Error,
+  void
+> => {
+  return useMutation({
+    mutationFn: async () => {
+      const fileName = `shinkai-diagnostics-${new Date().toISOString().replace(/:/g, '-')}.zip`;
+
+      void info('opening save dialog for diagnostics export');
+      const savePath = await save({
+        defaultPath: fileName,
+        filters: [
+          {
+            name: 'Zip',
+            extensions: ['zip'],
+          },
+        ],
+      });
+
+      if (!savePath) {
+        void info('diagnostics save dialog cancelled by user');
+        throw new Error('diagnostics export cancelled');
+      }
+
+      const manifest = await invoke<DiagnosticsManifest>(
+        'export_diagnostics',
+        { savePath },
+      );
+      void info(`diagnostics bundle saved at path: ${savePath}`);
+
+      return { savePath, manifest };
+    },
+    ...options,
+  });
+};
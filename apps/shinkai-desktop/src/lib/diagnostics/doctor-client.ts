@@ -0,0 +1,67 @@
+import {
+  type QueryObserverOptions,
+  useMutation,
+  type UseMutationOptions,
+  type UseMutationResult,
+  useQuery,
+  useQueryClient,
+  type UseQueryResult,
+} from '@tanstack/react-query';
+import { invoke } from '@tauri-apps/api/core';
+
+export type DoctorCheckStatus = 'Pass' | 'Warn' | 'Fail';
+
+export type DoctorFix =
+  | { kind: 'CreateDirectory'; path: string }
+  | { kind: 'MakeExecutable'; path: string }
+  | { kind: 'FreePort'; port: number; pids: number[] };
+
+export type DoctorCheck = {
+  id: string;
+  name: string;
+  status: DoctorCheckStatus;
+  message: string;
+  remediation: string | null;
+  fix: DoctorFix | null;
+};
+
+export type DoctorFixResult = {
+  fix: DoctorFix;
+  error: string | null;
+};
+
+export type DoctorReport = {
+  created_at: string;
+  status: DoctorCheckStatus;
+  checks: DoctorCheck[];
+  fixes: DoctorFixResult[];
+};
+
+export const useDoctorQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<DoctorReport, Error> => {
+  const query = useQuery({
+    queryKey: ['doctor'],
+    queryFn: (): Promise<DoctorReport> => invoke('doctor', { fix: false }),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<DoctorReport, Error>;
+};
+
+// Applies the safe automatic fixes and returns the checks run again afterwards
+export const useDoctorFixMutation = (
+  options?: UseMutationOptions<DoctorReport, Error, void>,
+): UseMutationResult<DoctorReport, Error, void> => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: (): Promise<DoctorReport> => invoke('doctor', { fix: true }),
+    ...options,
+    onSuccess: (...onSuccessParameters) => {
+      queryClient.setQueryData(['doctor'], onSuccessParameters[0]);
+      if (options?.onSuccess) {
+        options.onSuccess(...onSuccessParameters);
+      }
+    },
+  });
+  return { ...response };
+};
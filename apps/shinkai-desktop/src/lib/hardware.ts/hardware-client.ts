@@ -16,20 +16,74 @@ export type Requirement = {
   cpus: number;
   memory: number;
   discrete_gpu: boolean;
+  gpu_memory?: number;
+  free_disk?: number;
+  cpu_features?: string[];
+};
+export type Requirements = {
+  still_usable: Requirement;
+  minimum: Requirement;
+  recommended: Requirement;
+  optimal: Requirement;
+};
+export type RequirementProfile = {
+  id: string;
+  name: string;
+  description: string;
+  tiers: Requirements;
+};
+export type RequirementProfiles = {
+  default_profile: string;
+  profiles: RequirementProfile[];
+};
+export type DowngradeReason = {
+  tier: RequirementsStatus;
+  criterion: 'Cpu' | 'Ram' | 'Gpu' | 'Disk' | 'InstructionSet';
+  required: string;
+  actual: string;
+};
+export type GpuAdapter = {
+  name: string;
+  device_type: string;
+  backend: string;
+  vendor_id: number;
+  device_id: number;
+  driver: string;
+  driver_info: string;
+  memory_bytes: number | null;
+  unified_memory: boolean;
+  memory_source:
+    | 'Sysfs'
+    | 'NvidiaSmi'
+    | 'SystemProfiler'
+    | 'WindowsRegistry'
+    | 'UnifiedMemory'
+    | null;
+};
+export type VolumeSpace = {
+  path: string;
+  mount_point: string;
+  total_bytes: number;
+  available_bytes: number;
 };
 export type HardwareSummary = {
   hardware: {
     discrete_gpu: boolean;
     memory: number;
+    available_memory_bytes: number;
+    total_swap_bytes: number;
+    free_swap_bytes: number;
+    gpu_memory: number;
+    gpus: GpuAdapter[];
     cpus: number;
+    cpu_model: string;
+    cpu_features: string[];
+    storage: VolumeSpace | null;
   };
-  requirements: {
-    still_usable: Requirement;
-    minimum: Requirement;
-    recommended: Requirement;
-    optimal: Requirement;
-  };
+  requirements_profile: string;
+  requirements: Requirements;
   requirements_status: RequirementsStatus;
+  downgrade_reasons: DowngradeReason[];
 };
 
 // Queries
@@ -45,3 +99,30 @@ export const useHardwareGetSummaryQuery = (
   });
   return { ...query } as UseQueryResult<HardwareSummary, Error>;
 };
+export const useHardwareGetRequirementProfilesQuery = (
+  options?: QueryObserverOptions,
+): UseQueryResult<RequirementProfiles, Error> => {
+  const query = useQuery({
+    ...options,
+    queryKey: ['hardware_get_requirement_profiles'],
+    queryFn: async (): Promise<RequirementProfiles> => {
+      return invoke('hardware_get_requirement_profiles');
+    },
+  });
+  return { ...query } as UseQueryResult<RequirementProfiles, Error>;
+};
+export const useHardwareEvaluateRequirementsQuery = (
+  input: { profile?: string },
+  options?: QueryObserverOptions,
+): UseQueryResult<HardwareSummary, Error> => {
+  const query = useQuery({
+    ...options,
+    queryKey: ['hardware_evaluate_requirements', input],
+    queryFn: async (): Promise<HardwareSummary> => {
+      return invoke('hardware_evaluate_requirements', {
+        profile: input.profile ?? null,
+      });
+    },
+  });
+  return { ...query } as UseQueryResult<HardwareSummary, Error>;
+};
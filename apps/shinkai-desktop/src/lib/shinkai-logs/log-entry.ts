@@ -2,5 +2,39 @@ export interface LogEntry {
   timestamp: string;
   level: string;
   target: string;
+  process: string;
   message: string;
 }
+
+export interface LogFilter {
+  levels?: string[];
+  targets?: string[];
+  since?: string;
+  until?: string;
+  text?: string;
+  regex?: boolean;
+}
+
+export interface LogQuery extends LogFilter {
+  cursor?: string;
+  limit?: number;
+}
+
+export interface LogPage {
+  entries: LogEntry[];
+  next_cursor: string | null;
+}
+
+export interface LogTailEvent {
+  subscription_id: number;
+  entries: LogEntry[];
+}
+
+export type LogFormat = 'Text' | 'JsonLines';
+
+export interface LogSettings {
+  format: LogFormat;
+  max_file_size: number;
+  rotation_count: number;
+  retention_days: number;
+}
@@ -4,15 +4,24 @@ import {
   type UseMutationOptions,
   type UseMutationResult,
   useQuery,
+  useQueryClient,
   type UseQueryResult,
 } from '@tanstack/react-query';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 import { save } from '@tauri-apps/plugin-dialog';
 
 import { info } from '@tauri-apps/plugin-log';
 
-import { type LogEntry } from './log-entry';
+import {
+  type LogEntry,
+  type LogFilter,
+  type LogPage,
+  type LogQuery,
+  type LogSettings,
+  type LogTailEvent,
+} from './log-entry';
 
 export const useRetrieveLogsQuery = (
   options?: Omit<QueryObserverOptions, 'queryKey'>,
@@ -25,6 +34,49 @@ export const useRetrieveLogsQuery = (
   return { ...query } as UseQueryResult<LogEntry[], Error>;
 };
 
+export const useQueryLogsQuery = (
+  logQuery: LogQuery,
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<LogPage, Error> => {
+  const query = useQuery({
+    queryKey: ['query_logs', logQuery],
+    queryFn: (): Promise<LogPage> =>
+      invoke('query_logs', { query: logQuery }),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<LogPage, Error>;
+};
+
+export const useGetLogSettingsQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<LogSettings, Error> => {
+  const query = useQuery({
+    queryKey: ['get_log_settings'],
+    queryFn: (): Promise<LogSettings> => invoke('get_log_settings'),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<LogSettings, Error>;
+};
+
+// Retention applies right away, format and max file size after restarting the app
+export const useSetLogSettingsMutation = (
+  options?: UseMutationOptions<LogSettings, Error, LogSettings>,
+): UseMutationResult<LogSettings, Error, LogSettings> => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: (settings: LogSettings): Promise<LogSettings> =>
+      invoke('set_log_settings', { settings }),
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({ queryKey: ['get_log_settings'] });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
 export const useDownloadTauriLogsMutation = (
   options?: UseMutationOptions<
     { savePath: string; fileName: string },
@@ -65,3 +117,28 @@ export const useDownloadTauriLogsMutation = (
 export const retrieveLogs = async (): Promise<LogEntry[]> => {
   return invoke('retrieve_logs');
 };
+
+export const queryLogs = async (query: LogQuery): Promise<LogPage> => {
+  return invoke('query_logs', { query });
+};
+
+/**
+ * Streams new log entries matching `filter` as they are written.
+ * @returns A function that stops the subscription.
+ */
+export const subscribeLogs = async (
+  filter: LogFilter,
+  onEntries: (entries: LogEntry[]) => void,
+): Promise<() => Promise<void>> => {
+  let subscriptionId: number | null = null;
+  const unlisten = await listen<LogTailEvent>('logs-tail', (event) => {
+    if (event.payload.subscription_id === subscriptionId) {
+      onEntries(event.payload.entries);
+    }
+  });
+  subscriptionId = await invoke<number>('subscribe_logs', { filter });
+  return async () => {
+    unlisten();
+    await invoke('unsubscribe_logs', { subscriptionId });
+  };
+};
@@ -12,6 +12,28 @@ export enum ShinkaiNodeManagerEvent {
   PullingModelDone = 'PullingModelDone',
   PullingModelError = 'PullingModelError',
 
+  MemoryPressure = 'MemoryPressure',
+
+  BackingUpStorageStart = 'BackingUpStorageStart',
+  BackingUpStorageProgress = 'BackingUpStorageProgress',
+  BackingUpStorageDone = 'BackingUpStorageDone',
+  BackingUpStorageError = 'BackingUpStorageError',
+
+  RestoringStorageStart = 'RestoringStorageStart',
+  RestoringStorageProgress = 'RestoringStorageProgress',
+  RestoringStorageDone = 'RestoringStorageDone',
+  RestoringStorageError = 'RestoringStorageError',
+
+  RelocatingStorageStart = 'RelocatingStorageStart',
+  RelocatingStorageProgress = 'RelocatingStorageProgress',
+  RelocatingStorageDone = 'RelocatingStorageDone',
+  RelocatingStorageError = 'RelocatingStorageError',
+
+  ScheduledBackupDone = 'ScheduledBackupDone',
+  ScheduledBackupError = 'ScheduledBackupError',
+
+  ScanningStorageProgress = 'ScanningStorageProgress',
+
   StoppingShinkaiNode = 'StoppingShinkaiNode',
   ShinkaiNodeStopped = 'ShinkaiNodeStopped',
   ShinkaiNodeStopError = 'ShinkaiNodeStopError',
@@ -43,6 +65,50 @@ export interface PullingModelErrorEvent {
   error: string;
 }
 
+export type ProcessResourceUsage = {
+  process: string;
+  pid: number;
+  process_count: number;
+  cpu_percent: number;
+  memory_bytes: number;
+  threads: number | null;
+  open_files: number | null;
+  disk_read_bytes_per_sec: number;
+  disk_written_bytes_per_sec: number;
+  disk_total_read_bytes: number;
+  disk_total_written_bytes: number;
+};
+export type ResourceUsageSample = {
+  timestamp: number;
+  processes: ProcessResourceUsage[];
+};
+
+export type MemoryPressure = {
+  under_pressure: boolean;
+  available_memory_bytes: number;
+  total_memory_bytes: number;
+  min_available_memory_bytes: number;
+  unloaded_models: string[];
+};
+export interface MemoryPressureEvent {
+  pressure: MemoryPressure;
+}
+
+export interface StorageProgressEvent {
+  progress: number;
+}
+export interface StorageErrorEvent {
+  error: string;
+}
+export interface ScheduledBackupDoneEvent {
+  backup: StorageBackupEntry;
+}
+
+export interface ScanningStorageProgressEvent {
+  files: number;
+  size: number;
+}
+
 export interface ShinkaiNodeStopErrorEvent {
   error: string;
 }
@@ -79,6 +145,52 @@ export type ShinkaiNodeManagerEventMap =
       type: ShinkaiNodeManagerEvent.PullingModelError;
       payload: PullingModelErrorEvent;
     }
+  | {
+      type: ShinkaiNodeManagerEvent.MemoryPressure;
+      payload: MemoryPressureEvent;
+    }
+  | { type: ShinkaiNodeManagerEvent.BackingUpStorageStart; payload: never }
+  | {
+      type: ShinkaiNodeManagerEvent.BackingUpStorageProgress;
+      payload: StorageProgressEvent;
+    }
+  | { type: ShinkaiNodeManagerEvent.BackingUpStorageDone; payload: never }
+  | {
+      type: ShinkaiNodeManagerEvent.BackingUpStorageError;
+      payload: StorageErrorEvent;
+    }
+  | { type: ShinkaiNodeManagerEvent.RestoringStorageStart; payload: never }
+  | {
+      type: ShinkaiNodeManagerEvent.RestoringStorageProgress;
+      payload: StorageProgressEvent;
+    }
+  | { type: ShinkaiNodeManagerEvent.RestoringStorageDone; payload: never }
+  | {
+      type: ShinkaiNodeManagerEvent.RestoringStorageError;
+      payload: StorageErrorEvent;
+    }
+  | { type: ShinkaiNodeManagerEvent.RelocatingStorageStart; payload: never }
+  | {
+      type: ShinkaiNodeManagerEvent.RelocatingStorageProgress;
+      payload: StorageProgressEvent;
+    }
+  | { type: ShinkaiNodeManagerEvent.RelocatingStorageDone; payload: never }
+  | {
+      type: ShinkaiNodeManagerEvent.RelocatingStorageError;
+      payload: StorageErrorEvent;
+    }
+  | {
+      type: ShinkaiNodeManagerEvent.ScheduledBackupDone;
+      payload: ScheduledBackupDoneEvent;
+    }
+  | {
+      type: ShinkaiNodeManagerEvent.ScheduledBackupError;
+      payload: StorageErrorEvent;
+    }
+  | {
+      type: ShinkaiNodeManagerEvent.ScanningStorageProgress;
+      payload: ScanningStorageProgressEvent;
+    }
   | { type: ShinkaiNodeManagerEvent.StoppingShinkaiNode; payload: never }
   | { type: ShinkaiNodeManagerEvent.ShinkaiNodeStopped; payload: never }
   | {
@@ -113,6 +225,138 @@ export type ShinkaiNodeOptions = {
    secret_desktop_installation_proof_key?: string,
 };
 
+// Limits are applied once the processes are running so they are best-effort,
+// num_threads only affects the requests the app sends to Ollama
+export type ProcessLimits = {
+  priority: 'Normal' | 'Low' | 'Idle';
+  cpu_affinity: number[] | null;
+  num_threads: number | null;
+  memory_limit_bytes: number | null;
+};
+export type SidecarLimits = {
+  background_mode: boolean;
+  shinkai_node: ProcessLimits;
+  ollama: ProcessLimits;
+  shinkai_node_limits_error: string | null;
+  ollama_limits_error: string | null;
+};
+
+export type StorageBackupFile = {
+  path: string;
+  size: number;
+  digest: string;
+};
+export type StorageBackupManifest = {
+  format_version: number;
+  created_at: string;
+  app_version: string;
+  node_version: string | null;
+  identity_name: string | null;
+  total_size: number;
+  files: StorageBackupFile[];
+};
+export type StorageRestore = {
+  manifest: StorageBackupManifest;
+  previous_storage_path: string | null;
+};
+
+export type StorageRelocation = {
+  previous_storage_path: string;
+  storage_path: string;
+  files: number;
+  total_size: number;
+  source_removed: boolean;
+};
+export type RelocatedStorage = {
+  relocation: StorageRelocation;
+  options: ShinkaiNodeOptions;
+};
+
+export type BackupSchedule = {
+  frequency: 'Daily' | 'Weekly';
+  backups_path: string;
+  keep_daily: number;
+  keep_weekly: number;
+};
+export type BackupIntegrity =
+  | { status: 'Unverified' }
+  | { status: 'Valid' }
+  | { status: 'Corrupted'; error: string };
+export type StorageBackupEntry = {
+  path: string;
+  file_name: string;
+  created_at: string;
+  size: number;
+  integrity: BackupIntegrity;
+};
+
+export type StorageResetScope =
+  | 'Database'
+  | 'Files'
+  | 'Tools'
+  | 'Caches'
+  | 'Everything';
+export type StorageResetEntry = {
+  path: string;
+  is_dir: boolean;
+  size: number;
+  action: 'Remove' | 'ClearIdentityName';
+};
+export type StorageReset = {
+  scopes: StorageResetScope[];
+  preserve_keys: boolean;
+  dry_run: boolean;
+  reset_at: string;
+  entries: StorageResetEntry[];
+  total_size: number;
+  quarantine_path: string | null;
+  quarantine_size: number;
+  undo_until: string | null;
+};
+
+export type SecretsProtection = 'Keyring' | 'MachineFile' | 'Passphrase';
+export type SecretsStoreStatus = {
+  protection: SecretsProtection;
+  locked: boolean;
+  error: string | null;
+};
+
+export type IdentityKeys = {
+  identity_name: string | null;
+  fingerprint: string;
+};
+
+export type StorageCategory =
+  | 'Database'
+  | 'Filesystem'
+  | 'Embeddings'
+  | 'Tools'
+  | 'ToolsRunnerCache'
+  | 'Logs'
+  | 'Other';
+export type StorageCategoryUsage = {
+  category: StorageCategory;
+  size: number;
+  files: number;
+  growth: number | null;
+};
+export type StorageItem = {
+  size: number;
+  path: string;
+  category: StorageCategory;
+};
+export type StorageUsage = {
+  storage_path: string;
+  scanned_at: string;
+  total_size: number;
+  total_files: number;
+  total_growth: number | null;
+  previous_scanned_at: string | null;
+  categories: StorageCategoryUsage[];
+  largest_items: StorageItem[];
+  unreadable: string[];
+};
+
 export type LogEntry = {
   timestamp: number;
   process: string;
@@ -11,7 +11,24 @@ import {
 import { invoke } from '@tauri-apps/api/core';
 import { relaunch } from '@tauri-apps/plugin-process';
 
-import { type ShinkaiNodeOptions } from './shinkai-node-manager-client-types';
+import { useShinkaiNodeManager } from '../../store/shinkai-node-manager';
+import {
+  type IdentityKeys,
+  type ProcessLimits,
+  type RelocatedStorage,
+  type ResourceUsageSample,
+  type SecretsProtection,
+  type SecretsStoreStatus,
+  type ShinkaiNodeOptions,
+  type SidecarLimits,
+  type BackupSchedule,
+  type StorageBackupEntry,
+  type StorageBackupManifest,
+  type StorageReset,
+  type StorageResetScope,
+  type StorageRestore,
+  type StorageUsage,
+} from './shinkai-node-manager-client-types';
 
 // Client
 
@@ -69,6 +86,102 @@ export const useShinkaiNodeGetOllamaVersionQuery = (
   });
   return { ...query } as UseQueryResult<string, Error>;
 };
+export const useShinkaiNodeGetResourceUsageHistoryQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<ResourceUsageSample[], Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_get_resource_usage_history'],
+    queryFn: (): Promise<ResourceUsageSample[]> =>
+      invoke('shinkai_node_get_resource_usage_history'),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<ResourceUsageSample[], Error>;
+};
+export const useShinkaiNodeGetSidecarLimitsQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<SidecarLimits, Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_get_sidecar_limits'],
+    queryFn: (): Promise<SidecarLimits> =>
+      invoke('shinkai_node_get_sidecar_limits'),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<SidecarLimits, Error>;
+};
+
+export const useShinkaiNodeGetBackupScheduleQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<BackupSchedule | null, Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_get_backup_schedule'],
+    queryFn: (): Promise<BackupSchedule | null> =>
+      invoke('shinkai_node_get_backup_schedule'),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<BackupSchedule | null, Error>;
+};
+export const useShinkaiNodeListStorageBackupsQuery = (
+  input: { verify: boolean },
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<StorageBackupEntry[], Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_list_storage_backups', input],
+    queryFn: (): Promise<StorageBackupEntry[]> =>
+      invoke('shinkai_node_list_storage_backups', input),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<StorageBackupEntry[], Error>;
+};
+
+export const useShinkaiNodeGetLastStorageResetQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<StorageReset | null, Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_get_last_storage_reset'],
+    queryFn: (): Promise<StorageReset | null> =>
+      invoke('shinkai_node_get_last_storage_reset'),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<StorageReset | null, Error>;
+};
+
+export const useShinkaiNodeGetStorageUsageQuery = (
+  input: { top?: number },
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<StorageUsage, Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_get_storage_usage', input],
+    queryFn: (): Promise<StorageUsage> =>
+      invoke('shinkai_node_get_storage_usage', input),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<StorageUsage, Error>;
+};
+
+export const useShinkaiNodeInspectIdentityExportQuery = (
+  input: { exportPath: string },
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<IdentityKeys, Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_inspect_identity_export', input],
+    queryFn: (): Promise<IdentityKeys> =>
+      invoke('shinkai_node_inspect_identity_export', input),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<IdentityKeys, Error>;
+};
+
+export const useShinkaiNodeGetSecretsStatusQuery = (
+  options?: Omit<QueryObserverOptions, 'queryKey'>,
+): UseQueryResult<SecretsStoreStatus, Error> => {
+  const query = useQuery({
+    queryKey: ['shinkai_node_get_secrets_status'],
+    queryFn: (): Promise<SecretsStoreStatus> =>
+      invoke('shinkai_node_get_secrets_status'),
+    ...options,
+  });
+  return { ...query } as UseQueryResult<SecretsStoreStatus, Error>;
+};
 
 // Mutations
 export const useShinkaiNodeSpawnMutation = (options?: UseMutationOptions) => {
@@ -235,3 +348,320 @@ export const shinkaiNodeSetDefaultLlmProvider = async (
 
   return Promise.resolve();
 };
+
+export const useShinkaiNodeSetSidecarLimitsMutation = (
+  options?: UseMutationOptions<
+    SidecarLimits,
+    Error,
+    { shinkaiNode: ProcessLimits; ollama: ProcessLimits }
+  >,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (input: {
+      shinkaiNode: ProcessLimits;
+      ollama: ProcessLimits;
+    }): Promise<SidecarLimits> => {
+      return invoke('shinkai_node_set_sidecar_limits', input);
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_sidecar_limits'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeSetBackgroundModeMutation = (
+  options?: UseMutationOptions<SidecarLimits, Error, { enabled: boolean }>,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (input: { enabled: boolean }): Promise<SidecarLimits> => {
+      return invoke('shinkai_node_set_background_mode', input);
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_sidecar_limits'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeBackupStorageMutation = (
+  options?: UseMutationOptions<
+    StorageBackupManifest,
+    Error,
+    { archivePath: string }
+  >,
+) => {
+  const response = useMutation({
+    mutationFn: async (input: {
+      archivePath: string;
+    }): Promise<StorageBackupManifest> => {
+      return invoke('shinkai_node_backup_storage', input);
+    },
+    ...options,
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeVerifyStorageBackupMutation = (
+  options?: UseMutationOptions<
+    StorageBackupManifest,
+    Error,
+    { archivePath: string }
+  >,
+) => {
+  const response = useMutation({
+    mutationFn: async (input: {
+      archivePath: string;
+    }): Promise<StorageBackupManifest> => {
+      return invoke('shinkai_node_verify_storage_backup', input);
+    },
+    ...options,
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeRestoreStorageMutation = (
+  options?: UseMutationOptions<StorageRestore, Error, { archivePath: string }>,
+) => {
+  const response = useMutation({
+    mutationFn: async (input: {
+      archivePath: string;
+    }): Promise<StorageRestore> => {
+      return invoke('shinkai_node_restore_storage', input);
+    },
+    ...options,
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeRelocateStorageMutation = (
+  options?: UseMutationOptions<
+    RelocatedStorage,
+    Error,
+    { destination: string }
+  >,
+) => {
+  const queryClient = useQueryClient();
+  const setShinkaiNodeOptions = useShinkaiNodeManager(
+    (state) => state.setShinkaiNodeOptions,
+  );
+  const response = useMutation({
+    mutationFn: async (input: {
+      destination: string;
+    }): Promise<RelocatedStorage> => {
+      return invoke('shinkai_node_relocate_storage', input);
+    },
+    ...options,
+    onSuccess: (...onSuccessParameters) => {
+      // Persisted options are sent back on startup, they must point at the new folder
+      setShinkaiNodeOptions(onSuccessParameters[0].options);
+      if (options?.onSuccess) {
+        options.onSuccess(...onSuccessParameters);
+      }
+    },
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_options'],
+      });
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_is_running'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeSetBackupScheduleMutation = (
+  options?: UseMutationOptions<
+    BackupSchedule | null,
+    Error,
+    { schedule: BackupSchedule | null }
+  >,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (input: {
+      schedule: BackupSchedule | null;
+    }): Promise<BackupSchedule | null> => {
+      return invoke('shinkai_node_set_backup_schedule', input);
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_backup_schedule'],
+      });
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_list_storage_backups'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export type ShinkaiNodeResetStorageInput = {
+  scopes: StorageResetScope[];
+  preserveKeys: boolean;
+  dryRun: boolean;
+  purge: boolean;
+};
+export const useShinkaiNodeResetStorageMutation = (
+  options?: UseMutationOptions<
+    StorageReset,
+    Error,
+    ShinkaiNodeResetStorageInput
+  >,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (
+      input: ShinkaiNodeResetStorageInput,
+    ): Promise<StorageReset> => {
+      return invoke('shinkai_node_reset_storage', input);
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_last_storage_reset'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeUndoStorageResetMutation = (
+  options?: UseMutationOptions<StorageReset, Error, void>,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (): Promise<StorageReset> => {
+      return invoke('shinkai_node_undo_storage_reset');
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_last_storage_reset'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeExportIdentityMutation = (
+  options?: UseMutationOptions<
+    IdentityKeys,
+    Error,
+    { exportPath: string; passphrase: string }
+  >,
+) => {
+  const response = useMutation({
+    mutationFn: async (input: {
+      exportPath: string;
+      passphrase: string;
+    }): Promise<IdentityKeys> => {
+      return invoke('shinkai_node_export_identity', input);
+    },
+    ...options,
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeImportIdentityMutation = (
+  options?: UseMutationOptions<
+    IdentityKeys,
+    Error,
+    { exportPath: string; passphrase: string; overwrite: boolean }
+  >,
+) => {
+  const response = useMutation({
+    mutationFn: async (input: {
+      exportPath: string;
+      passphrase: string;
+      overwrite: boolean;
+    }): Promise<IdentityKeys> => {
+      return invoke('shinkai_node_import_identity', input);
+    },
+    ...options,
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeUnlockSecretsMutation = (
+  options?: UseMutationOptions<
+    SecretsStoreStatus,
+    Error,
+    { passphrase: string }
+  >,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (input: {
+      passphrase: string;
+    }): Promise<SecretsStoreStatus> => {
+      return invoke('shinkai_node_unlock_secrets', input);
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_secrets_status'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
+
+export const useShinkaiNodeSetSecretsProtectionMutation = (
+  options?: UseMutationOptions<
+    SecretsStoreStatus,
+    Error,
+    { protection: SecretsProtection; passphrase?: string }
+  >,
+) => {
+  const queryClient = useQueryClient();
+  const response = useMutation({
+    mutationFn: async (input: {
+      protection: SecretsProtection;
+      passphrase?: string;
+    }): Promise<SecretsStoreStatus> => {
+      return invoke('shinkai_node_set_secrets_protection', input);
+    },
+    ...options,
+    onSettled: (...onSettledParameters) => {
+      void queryClient.invalidateQueries({
+        queryKey: ['shinkai_node_get_secrets_status'],
+      });
+      if (options?.onSettled) {
+        options.onSettled(...onSettledParameters);
+      }
+    },
+  });
+  return { ...response };
+};
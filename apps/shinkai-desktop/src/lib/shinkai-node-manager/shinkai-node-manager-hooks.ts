@@ -3,6 +3,7 @@ import { warn } from '@tauri-apps/plugin-log';
 import { useEffect, useState } from 'react';
 
 import {
+  type ResourceUsageSample,
   ShinkaiNodeManagerEvent,
   type ShinkaiNodeManagerEventMap,
 } from './shinkai-node-manager-client-types';
@@ -54,6 +55,15 @@ export const useShinkaiNodeStateChange = (
   );
 };
 
+export const useShinkaiNodeResourceUsage = (
+  callback: EventCallback<ResourceUsageSample>,
+) => {
+  return useTauriEvent<ResourceUsageSample>(
+    'shinkai-node-resource-usage',
+    callback,
+  );
+};
+
 export const mapEvent = (
   event: object | string,
 ): ShinkaiNodeManagerEventMap => {
@@ -6,10 +6,17 @@ import {
 } from '@shinkai_network/shinkai-ui';
 import { useVirtualizer } from '@tanstack/react-virtual';
 import { openPath } from '@tauri-apps/plugin-opener';
-import { DownloadIcon, Loader2, RotateCw, Search } from 'lucide-react';
+import {
+  DownloadIcon,
+  FileArchive,
+  Loader2,
+  RotateCw,
+  Search,
+} from 'lucide-react';
 import { useEffect, useMemo, useRef, useState } from 'react';
 import { toast } from 'sonner';
 
+import { useExportDiagnosticsMutation } from '../../../lib/diagnostics/diagnostics-client';
 import {
   useDownloadTauriLogsMutation,
   useRetrieveLogsQuery,
@@ -56,6 +63,33 @@ export const Logs = () => {
       },
     });
 
+  const {
+    mutate: exportDiagnostics,
+    isPending: exportDiagnosticsIsPending,
+  } = useExportDiagnosticsMutation({
+    onSuccess: (result) => {
+      const failed = result.manifest.items.filter(
+        (item) => item.status === 'Failed',
+      ).length;
+      toast.success('Diagnostics exported successfully', {
+        description: failed
+          ? `${failed} items could not be collected, see manifest.json`
+          : 'Share the zip file with the Shinkai team',
+        action: {
+          label: 'Open',
+          onClick: async () => {
+            await openPath(result.savePath);
+          },
+        },
+      });
+    },
+    onError: (error) => {
+      toast.error('Failed to export diagnostics', {
+        description: error.message,
+      });
+    },
+  });
+
   const virtualizerParentRef = useRef<HTMLDivElement>(null);
   const virtualizer = useVirtualizer({
     count: filteredLogs?.length ?? 0,
@@ -154,6 +188,21 @@ export const Logs = () => {
           )}
           Download Logs
         </Button>
+        <Button
+          disabled={exportDiagnosticsIsPending}
+          onClick={() => exportDiagnostics()}
+          rounded="lg"
+          size="sm"
+          type="button"
+          variant="default"
+        >
+          {exportDiagnosticsIsPending ? (
+            <Loader2 className="animate-spin" />
+          ) : (
+            <FileArchive className="ml-2 size-3.5" />
+          )}
+          Export Diagnostics
+        </Button>
       </div>
 
       <Dialog onOpenChange={() => setSelectedLog(null)} open={!!selectedLog}>